@@ -0,0 +1,91 @@
+// Escapes a 64 KiB string with sparse `&` characters
+//
+// Compares the word at a time scanner in `EscapeText` against scanning byte by byte.
+// Compares `EscapeText::write_char` against the default `write_char` which goes through `write_str`.
+// Run with `cargo bench --bench escape`.
+
+use std::fmt::{self, Write};
+use std::hint::black_box;
+use std::time::Instant;
+
+// The byte by byte scanner `EscapeText` used before
+struct ByteEscape<'a>(&'a mut String);
+impl<'a> fmt::Write for ByteEscape<'a> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let mut start = 0;
+		for (i, chr) in s.bytes().enumerate() {
+			let entity = match chr {
+				b'<' => "&lt;",
+				b'&' => "&amp;",
+				b'>' => "&gt;",
+				_ => continue,
+			};
+			self.0.push_str(&s[start..i]);
+			self.0.push_str(entity);
+			start = i + 1;
+		}
+		self.0.push_str(&s[start..]);
+		Ok(())
+	}
+}
+
+// Forwards only `write_str` so `write_char` takes the default path
+struct StrOnly<'a>(&'a mut String);
+impl<'a> fmt::Write for StrOnly<'a> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		format_xml::EscapeText::wrap(&mut *self.0).write_str(s)
+	}
+}
+
+// Formats the string a char at a time
+struct Chars<'a>(&'a str);
+impl<'a> fmt::Display for Chars<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.chars().try_for_each(|chr| f.write_char(chr))
+	}
+}
+
+fn main() {
+	let mut text = String::with_capacity(64 * 1024);
+	while text.len() < 64 * 1024 {
+		text.push_str("Lorem ipsum dolor sit amet, consectetur adipiscing elit & sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. ");
+	}
+	text.truncate(64 * 1024);
+
+	let mut word = String::with_capacity(96 * 1024);
+	let mut byte = String::with_capacity(96 * 1024);
+	write!(format_xml::EscapeText::wrap(&mut word), "{}", text).unwrap();
+	write!(ByteEscape(&mut byte), "{}", text).unwrap();
+	assert_eq!(word, byte);
+
+	const ITERS: u32 = 1000;
+
+	let start = Instant::now();
+	for _ in 0..ITERS {
+		word.clear();
+		format_xml::EscapeText::wrap(&mut word).write_str(black_box(&text)).unwrap();
+	}
+	println!("{:>15}: {:?} per 64 KiB", "word at a time", start.elapsed() / ITERS);
+
+	let start = Instant::now();
+	for _ in 0..ITERS {
+		byte.clear();
+		ByteEscape(&mut byte).write_str(black_box(&text)).unwrap();
+	}
+	println!("{:>15}: {:?} per 64 KiB", "byte by byte", start.elapsed() / ITERS);
+
+	let start = Instant::now();
+	for _ in 0..ITERS {
+		word.clear();
+		write!(format_xml::EscapeText::wrap(&mut word), "{}", Chars(black_box(&text))).unwrap();
+	}
+	println!("{:>15}: {:?} per 64 KiB", "write_char", start.elapsed() / ITERS);
+
+	let start = Instant::now();
+	for _ in 0..ITERS {
+		byte.clear();
+		write!(StrOnly(&mut byte), "{}", Chars(black_box(&text))).unwrap();
+	}
+	println!("{:>15}: {:?} per 64 KiB", "via write_str", start.elapsed() / ITERS);
+	assert_eq!(word, byte);
+}
@@ -0,0 +1,63 @@
+// Renders a list of 1000 items
+//
+// Compares writing string values directly to the escaping writer against formatting them through `fmt::write`.
+// Run with `cargo bench --bench list`.
+
+use std::fmt::{self, Write};
+use std::hint::black_box;
+use std::time::Instant;
+
+// Hides the string type so the value goes through `fmt::write`
+struct Opaque<'a>(&'a str);
+impl<'a> fmt::Display for Opaque<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(self.0)
+	}
+}
+
+fn direct(items: &[String]) -> impl fmt::Display + '_ {
+	format_xml::xfmt! { move
+		<ul>
+		for item in (items) {
+			<li>{item}</li>
+		}
+		</ul>
+	}
+}
+
+fn opaque(items: &[String]) -> impl fmt::Display + '_ {
+	format_xml::xfmt! { move
+		<ul>
+		for item in (items) {
+			<li>{Opaque(item)}</li>
+		}
+		</ul>
+	}
+}
+
+fn main() {
+	let items: Vec<String> = (0..1000).map(|i| format!("Item #{} & <friends>", i)).collect();
+
+	let mut a = String::with_capacity(64 * 1024);
+	let mut b = String::with_capacity(64 * 1024);
+	write!(a, "{}", direct(&items)).unwrap();
+	write!(b, "{}", opaque(&items)).unwrap();
+	assert_eq!(a, b);
+
+	const ITERS: u32 = 1000;
+
+	let start = Instant::now();
+	for _ in 0..ITERS {
+		a.clear();
+		write!(a, "{}", direct(black_box(&items))).unwrap();
+	}
+	println!("{:>15}: {:?} per render", "write_str", start.elapsed() / ITERS);
+
+	let start = Instant::now();
+	for _ in 0..ITERS {
+		b.clear();
+		write!(b, "{}", opaque(black_box(&items))).unwrap();
+	}
+	println!("{:>15}: {:?} per render", "fmt::write", start.elapsed() / ITERS);
+	assert_eq!(a, b);
+}
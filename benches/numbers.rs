@@ -0,0 +1,42 @@
+// Renders a table of a million numbers
+//
+// Compares the brace fast path for numbers against routing the same numbers through the escaping writer.
+// Run with `cargo bench --bench numbers`.
+
+use std::fmt::Write;
+use std::hint::black_box;
+use std::time::Instant;
+
+const ROWS: usize = 100_000;
+const COLS: usize = 10;
+
+fn main() {
+	let numbers: Vec<u32> = (0..(ROWS * COLS) as u32).collect();
+
+	let fast = format_xml::xfmt! {
+		<table>
+		for row in (numbers.chunks(COLS)) {
+			<tr> for &n in (row) { <td>{n}</td> } </tr>
+		}
+		</table>
+	};
+	let slow = format_xml::xfmt! {
+		<table>
+		for row in (numbers.chunks(COLS)) {
+			<tr> for &n in (row) { <td>|f| write!(format_xml::EscapeText::wrap(f), "{}", n)?;</td> } </tr>
+		}
+		</table>
+	};
+
+	assert_eq!(fast.to_string(), slow.to_string());
+
+	let mut buf = String::with_capacity(32 << 20);
+	for (name, value) in [("no escape", &fast as &dyn std::fmt::Display), ("escape", &slow)] {
+		let start = Instant::now();
+		for _ in 0..10 {
+			buf.clear();
+			write!(buf, "{}", black_box(value)).unwrap();
+		}
+		println!("{:>10}: {:?} per render", name, start.elapsed() / 10);
+	}
+}
@@ -0,0 +1,117 @@
+// This module implements rendering static templates once and caching the result
+
+/// Renders the template once and caches the result.
+///
+/// Returns a `&'static str` with the rendered template, the template is rendered on first use only.
+///
+/// # Examples
+///
+/// ```
+/// fn footer() -> &'static str {
+/// 	format_xml::cached! {
+/// 		<footer>"© "{2024}" Example"</footer>
+/// 	}
+/// }
+///
+/// assert_eq!(footer(), "<footer>© 2024 Example</footer>");
+/// assert!(std::ptr::eq(footer(), footer()));
+/// ```
+///
+/// The keyed variant renders and caches the template once per distinct key.
+/// The key is bound by reference to the given name and its type must implement `Eq + Hash`:
+///
+/// ```
+/// fn nav(lang: &'static str) -> &'static str {
+/// 	format_xml::cached!(lang: &'static str = lang, {
+/// 		<nav lang={lang}>
+/// 			if (*lang == "nl") { "Thuis" } else { "Home" }
+/// 		</nav>
+/// 	})
+/// }
+///
+/// assert_eq!(nav("en"), r#"<nav lang="en">Home</nav>"#);
+/// assert_eq!(nav("nl"), r#"<nav lang="nl">Thuis</nav>"#);
+/// ```
+///
+/// The template is rendered in a separate function and cannot capture local variables:
+///
+/// ```compile_fail
+/// let user = "Casper";
+/// let greeting = format_xml::cached! { <p>"Hello "{user}</p> };
+/// ```
+///
+/// Rendered keyed variants are leaked for the remainder of the program, only use it with a small set of keys.
+/// The cache is not locked while rendering, the template may use the cache itself and concurrent first uses of a key may render it more than once.
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! cached {
+	($key:ident : $ty:ty = $e:expr, { $($tt:tt)* }) => {{
+		fn render($key: &$ty) -> ::std::string::String {
+			::std::string::ToString::to_string(&$crate::xfmt! { $($tt)* })
+		}
+		static CACHE: ::std::sync::OnceLock<::std::sync::Mutex<::std::collections::HashMap<$ty, &'static str>>> = ::std::sync::OnceLock::new();
+		let key: $ty = $e;
+		let lock = || match CACHE.get_or_init(::core::default::Default::default).lock() {
+			::core::result::Result::Ok(cache) => cache,
+			::core::result::Result::Err(err) => err.into_inner(),
+		};
+		// The template is rendered without holding the lock, it may use the cache itself
+		let cached = lock().get(&key).copied();
+		match cached {
+			::core::option::Option::Some(rendered) => rendered,
+			::core::option::Option::None => {
+				let rendered = render(&key);
+				*lock().entry(key).or_insert_with(|| ::std::boxed::Box::leak(rendered.into_boxed_str()))
+			},
+		}
+	}};
+	($($tt:tt)*) => {{
+		fn render() -> ::std::string::String {
+			::std::string::ToString::to_string(&$crate::xfmt! { $($tt)* })
+		}
+		static CACHE: ::std::sync::OnceLock<::std::string::String> = ::std::sync::OnceLock::new();
+		CACHE.get_or_init(render).as_str()
+	}};
+}
+
+#[test]
+fn test_cached() {
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	static RENDERS: AtomicUsize = AtomicUsize::new(0);
+	struct Instrumented;
+	impl std::fmt::Display for Instrumented {
+		fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+			RENDERS.fetch_add(1, Ordering::Relaxed);
+			f.write_str("menu")
+		}
+	}
+
+	fn nav() -> &'static str {
+		cached! { <nav>{Instrumented}</nav> }
+	}
+	assert_eq!(nav(), "<nav>menu</nav>");
+	assert_eq!(nav(), "<nav>menu</nav>");
+	assert_eq!(RENDERS.load(Ordering::Relaxed), 1);
+
+	static KEYED_RENDERS: AtomicUsize = AtomicUsize::new(0);
+	fn greeting(lang: &str) -> &'static str {
+		cached!(lang: String = lang.to_string(), {
+			|_| { KEYED_RENDERS.fetch_add(1, Ordering::Relaxed); }
+			<p lang={lang}>"hello"</p>
+		})
+	}
+	assert_eq!(greeting("en"), r#"<p lang="en">hello</p>"#);
+	assert_eq!(greeting("fr"), r#"<p lang="fr">hello</p>"#);
+	assert_eq!(greeting("en"), r#"<p lang="en">hello</p>"#);
+	assert_eq!(KEYED_RENDERS.load(Ordering::Relaxed), 2);
+
+	// The template may use its own cache for another key
+	fn crumbs(depth: u32) -> &'static str {
+		cached!(depth: u32 = depth, {
+			if (*depth > 0) { {crate::slot(crumbs(*depth - 1))}" / " }
+			<b>{depth}</b>
+		})
+	}
+	assert_eq!(crumbs(2), "<b>0</b> / <b>1</b> / <b>2</b>");
+}
@@ -0,0 +1,258 @@
+// This module implements selecting the escaping context at runtime
+
+use core::fmt;
+use crate::{EscapeAttrValue, EscapeCharData, EscapeComment, EscapeCss, EscapeFull, EscapeJs, EscapeText, EscapeTextAscii, EscapeSpec};
+
+/// The escaping contexts supported by [`Escape`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Context {
+	/// Text content, see [`EscapeText`].
+	Text,
+	/// Quoted attribute values, see [`EscapeAttrValue`].
+	AttrValue,
+	/// All five special characters and whitespace regardless of context, see [`EscapeFull`].
+	Full,
+	/// Text content with non-ASCII characters as character references, see [`EscapeTextAscii`].
+	TextAscii,
+	/// String literals in inline scripts, see [`EscapeJs`].
+	Js,
+	/// Values in stylesheets, see [`EscapeCss`].
+	Css,
+	/// Comments, see [`EscapeComment`].
+	Comment,
+	/// CDATA sections, see [`EscapeCharData`].
+	CharData,
+}
+
+/// Escapes in a context selected at runtime.
+///
+/// Forwards to the escape writer of the [`Context`].
+/// Some contexts hold back output across writes, call [`finish`](Escape::finish) when done.
+///
+/// ```
+/// use std::fmt::Write;
+/// use format_xml::{Context, Escape};
+///
+/// let mut s = String::new();
+/// let mut writer = Escape::wrap(&mut s, Context::AttrValue);
+/// write!(writer, "{}", "\"a\" & 'b'").unwrap();
+/// writer.finish().unwrap();
+/// assert_eq!(s, "&quot;a&quot; &amp; &apos;b&apos;");
+///
+/// let mut s = String::new();
+/// let mut writer = Escape::wrap(&mut s, Context::Comment);
+/// write!(writer, "{}", "a--b-").unwrap();
+/// writer.finish().unwrap();
+/// assert_eq!(s, "ab- ");
+/// ```
+pub struct Escape<T> {
+	inner: Inner<T>,
+}
+enum Inner<T> {
+	Text(T),
+	AttrValue(T),
+	Full(T),
+	TextAscii(T),
+	Js(T),
+	Css(T),
+	Comment(EscapeComment<T>),
+	CharData(EscapeCharData<T>),
+}
+impl<T: fmt::Write> Escape<T> {
+	#[inline]
+	pub fn new(inner: T, context: Context) -> Escape<T> {
+		let inner = match context {
+			Context::Text => Inner::Text(inner),
+			Context::AttrValue => Inner::AttrValue(inner),
+			Context::Full => Inner::Full(inner),
+			Context::TextAscii => Inner::TextAscii(inner),
+			Context::Js => Inner::Js(inner),
+			Context::Css => Inner::Css(inner),
+			Context::Comment => Inner::Comment(EscapeComment::new(inner)),
+			Context::CharData => Inner::CharData(EscapeCharData::new(inner)),
+		};
+		Escape { inner }
+	}
+	/// Returns the context.
+	#[inline]
+	pub fn context(&self) -> Context {
+		match self.inner {
+			Inner::Text(_) => Context::Text,
+			Inner::AttrValue(_) => Context::AttrValue,
+			Inner::Full(_) => Context::Full,
+			Inner::TextAscii(_) => Context::TextAscii,
+			Inner::Js(_) => Context::Js,
+			Inner::Css(_) => Context::Css,
+			Inner::Comment(_) => Context::Comment,
+			Inner::CharData(_) => Context::CharData,
+		}
+	}
+	/// Writes any held back output and returns the inner writer.
+	#[inline]
+	pub fn finish(self) -> Result<T, fmt::Error> {
+		match self.inner {
+			Inner::Text(inner) | Inner::AttrValue(inner) | Inner::Full(inner) | Inner::TextAscii(inner) | Inner::Js(inner) | Inner::Css(inner) => Ok(inner),
+			Inner::Comment(inner) => inner.finish(),
+			Inner::CharData(inner) => Ok(inner.into_inner()),
+		}
+	}
+}
+impl<'a, T: ?Sized + fmt::Write> Escape<&'a mut T> {
+	#[inline]
+	pub fn wrap(v: &'a mut T, context: Context) -> Escape<&'a mut T> {
+		Escape::new(v, context)
+	}
+}
+// Forward Write calls
+#[doc(hidden)]
+impl<T: fmt::Write> Escape<T> {
+	#[inline]
+	pub fn write_str(&mut self, s: &str) -> fmt::Result {
+		<Self as fmt::Write>::write_str(self, s)
+	}
+	#[inline]
+	pub fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
+		<Self as fmt::Write>::write_fmt(self, args)
+	}
+}
+impl<T: fmt::Write> fmt::Write for Escape<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		match &mut self.inner {
+			Inner::Text(inner) => EscapeText::wrap(inner).write_str(s),
+			Inner::AttrValue(inner) => EscapeAttrValue::wrap(inner).write_str(s),
+			Inner::Full(inner) => EscapeFull::wrap(inner).write_str(s),
+			Inner::TextAscii(inner) => EscapeTextAscii::wrap(inner).write_str(s),
+			Inner::Js(inner) => EscapeJs::wrap(inner).write_str(s),
+			Inner::Css(inner) => EscapeCss::wrap(inner).write_str(s),
+			Inner::Comment(inner) => inner.write_str(s),
+			Inner::CharData(inner) => inner.write_str(s),
+		}
+	}
+}
+
+/// Escapes the value in the given context when formatted.
+///
+/// Streams the value through [`Escape`] without buffering, use it to pass escaped values to `format!` or store them in structs.
+/// Used as `{value}` in templates the result is not escaped again where the context is safe for the location.
+/// In text elements these are all contexts except [`Context::Comment`] and [`Context::CharData`], in attribute values only [`Context::AttrValue`] and [`Context::Full`].
+/// Elsewhere the result is escaped again like any other value.
+///
+/// Converting a plain value with `From` escapes it as [`Context::Text`], converting a `(value, context)` pair uses the given context.
+///
+/// # Examples
+///
+/// ```
+/// use format_xml::{Context, Escaped};
+///
+/// let text = Escaped::from("<b>Tom & Jerry</b>");
+/// assert_eq!(format!("<p>{}</p>", text), "<p>&lt;b&gt;Tom &amp; Jerry&lt;/b&gt;</p>");
+///
+/// let attr = Escaped::new("\"quoted\"\n", Context::AttrValue);
+/// assert_eq!(format!("<p title=\"{}\"></p>", attr), "<p title=\"&quot;quoted&quot;&#10;\"></p>");
+///
+/// let comment = Escaped::from(("--> <script>", Context::Comment));
+/// assert_eq!(format!("<!--{}-->", comment), "<!--> <script>-->");
+///
+/// let cdata = Escaped::new(format_args!("{}>", "]]"), Context::CharData);
+/// assert_eq!(format!("<![CDATA[{}]]>", cdata), "<![CDATA[]]]]><![CDATA[>]]>");
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct Escaped<T> {
+	value: T,
+	context: Context,
+}
+impl<T: fmt::Display> Escaped<T> {
+	#[inline]
+	pub fn new(value: T, context: Context) -> Escaped<T> {
+		Escaped { value, context }
+	}
+	/// Returns the context.
+	#[inline]
+	pub fn context(&self) -> Context {
+		self.context
+	}
+	/// Returns the unescaped value.
+	#[inline]
+	pub fn into_inner(self) -> T {
+		self.value
+	}
+}
+impl<T: fmt::Display> From<T> for Escaped<T> {
+	#[inline]
+	fn from(value: T) -> Escaped<T> {
+		Escaped::new(value, Context::Text)
+	}
+}
+impl<T: fmt::Display> From<(T, Context)> for Escaped<T> {
+	#[inline]
+	fn from((value, context): (T, Context)) -> Escaped<T> {
+		Escaped::new(value, context)
+	}
+}
+impl<T: fmt::Display> fmt::Display for Escaped<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut writer = Escape::new(f, self.context);
+		write!(writer, "{}", self.value)?;
+		writer.finish()?;
+		Ok(())
+	}
+}
+// Escaped values are trusted in templates only where their context matches the location
+#[doc(hidden)]
+impl<T> EscapeSpec<'_, Escaped<T>> {
+	#[inline]
+	pub fn __xfmt_escape(&self) -> bool {
+		!matches!(self.0.context, Context::Text | Context::AttrValue | Context::Full | Context::TextAscii | Context::Js | Context::Css)
+	}
+	#[inline]
+	pub fn __xfmt_escape_attr(&self) -> bool {
+		!matches!(self.0.context, Context::AttrValue | Context::Full)
+	}
+}
+
+#[test]
+fn test_context() {
+	#[track_caller]
+	fn check(context: Context, input: &[&str], escaped: &str) {
+		let mut s = String::new();
+		let mut writer = Escape::wrap(&mut s, context);
+		assert_eq!(writer.context(), context);
+		for part in input {
+			fmt::Write::write_str(&mut writer, part).unwrap();
+		}
+		writer.finish().unwrap();
+		assert_eq!(s, escaped);
+	}
+
+	let input = &["<a href=\"x\">", "'é' & -", "-]]", ">\n-"];
+	check(Context::Text, input, "&lt;a href=\"x\"&gt;'é' &amp; --]]&gt;\n-");
+	check(Context::AttrValue, input, "&lt;a href=&quot;x&quot;&gt;&apos;é&apos; &amp; --]]&gt;&#10;-");
+	check(Context::Full, input, "&lt;a href=&quot;x&quot;&gt;&apos;é&apos; &amp; --]]&gt;&#10;-");
+	check(Context::TextAscii, input, "&lt;a href=\"x\"&gt;'&#xE9;' &amp; --]]&gt;\n-");
+	check(Context::Js, input, "\\u003Ca href=\\\"x\\\"\\u003E\\'é\\' \\u0026 --]]\\u003E\\n-");
+	check(Context::Css, input, "\\3C a href\\3D \\22 x\\22 \\3E \\27 é\\27  \\26  --\\5D \\5D \\3E \\A -");
+	check(Context::Comment, input, "<a href=\"x\">'é' & ]]>\n- ");
+	check(Context::CharData, input, "<a href=\"x\">'é' & --]]]]><![CDATA[>\n-");
+}
+
+#[test]
+fn test_escaped() {
+	let value = "<a>&\"";
+	assert_eq!(Escaped::from(value).to_string(), "&lt;a&gt;&amp;\"");
+	assert_eq!(Escaped::from((value, Context::Full)).to_string(), "&lt;a&gt;&amp;&quot;");
+	assert_eq!(Escaped::new(value, Context::Full).context(), Context::Full);
+	assert_eq!(Escaped::new(value, Context::Text).into_inner(), value);
+
+	// Not escaped twice in templates
+	let page = crate::xfmt! { <p title={Escaped::new(value, Context::AttrValue)}>{Escaped::from(value)}</p> }.to_string();
+	assert_eq!(page, "<p title=\"&lt;a&gt;&amp;&quot;\">&lt;a&gt;&amp;\"</p>");
+
+	// Scripts and stylesheets are trusted in text, their output contains no markup characters
+	let page = crate::xfmt! { <p>{Escaped::new(value, Context::Js)}" "{Escaped::new(value, Context::Css)}</p> }.to_string();
+	assert_eq!(page, "<p>\\u003Ca\\u003E\\u0026\\\" \\3C a\\3E \\26 \\22 </p>");
+
+	// Escaped again where the context does not match the location
+	let page = crate::xfmt! { <p title={Escaped::from(value)}>{Escaped::new(value, Context::CharData)}</p> }.to_string();
+	assert_eq!(page, "<p title=\"&amp;lt;a&amp;gt;&amp;amp;&quot;\">&lt;a&gt;&amp;\"</p>");
+}
@@ -1,17 +1,32 @@
 // This module implements escaping for various xml contexts
 
-use core::{fmt, mem, str};
+use core::{fmt, mem, ptr, str};
 
-// All the routines here work only with and slice only at ascii characters
-// This means conversion between `&str` and `&[u8]` is a noop even when slicing
+// Converts bytes known to be valid utf8 back to a string
+// Only for bytes sliced at ascii characters by `split_ascii` or buffers which only ever contain whole strings or ascii characters
 #[inline]
-fn from_utf8(v: &[u8]) -> &str {
+pub(crate) fn from_utf8(v: &[u8]) -> &str {
 	#[cfg(debug_assertions)]
 	return str::from_utf8(v).unwrap();
 	#[cfg(not(debug_assertions))]
 	return unsafe { str::from_utf8_unchecked(v) };
 }
 
+// Splits the bytes of a string at index `i` which must be an ascii character or the end of the string
+// Returns the text before it and, if not at the end, the ascii character and the bytes after it
+// The escaping writers only slice their input through here, an ascii character is always a char boundary on both sides
+#[inline]
+fn split_ascii(bytes: &[u8], i: usize) -> (&str, Option<(u8, &[u8])>) {
+	let (prefix, rest) = bytes.split_at(i);
+	match rest.split_first() {
+		Some((&chr, rest)) => {
+			debug_assert!(chr.is_ascii(), "split at non-ascii byte {:#04x}", chr);
+			(from_utf8(prefix), Some((chr, rest)))
+		},
+		None => (from_utf8(prefix), None),
+	}
+}
+
 // LLVM is big dum dum, trust me I'm a human
 #[cfg(debug_assertions)]
 macro_rules! unsafe_assert {
@@ -22,13 +37,33 @@ macro_rules! unsafe_assert {
 	($e:expr) => { unsafe { if !$e { ::core::hint::unreachable_unchecked(); } } };
 }
 
-// Returns the index of the first one of `<`, `&`, `>`
+// Returns the index of the first byte equal to one of the needles
 // Returns the length of the input string if none found
+// Scans a word at a time so long runs of clean text are skipped quickly
 #[inline]
-fn split_text(bytes: &[u8]) -> usize {
+fn scan<const N: usize>(bytes: &[u8], needles: [u8; N]) -> usize {
+	const LO: u64 = 0x0101010101010101;
+	const HI: u64 = 0x8080808080808080;
 	let mut i = 0;
+	while i + 8 <= bytes.len() {
+		let mut word = [0u8; 8];
+		word.copy_from_slice(&bytes[i..i + 8]);
+		let word = u64::from_le_bytes(word);
+		let mut found = 0;
+		for &needle in &needles {
+			// Sets the high bit of every byte equal to the needle
+			let x = word ^ (LO * needle as u64);
+			found |= x.wrapping_sub(LO) & !x & HI;
+		}
+		if found != 0 {
+			i += (found.trailing_zeros() / 8) as usize;
+			unsafe_assert!(i <= bytes.len());
+			return i;
+		}
+		i += 8;
+	}
 	while i < bytes.len() {
-		if bytes[i] == b'<' || bytes[i] == b'&' || bytes[i] == b'>' {
+		if needles.contains(&bytes[i]) {
 			break;
 		}
 		i += 1;
@@ -37,23 +72,29 @@ fn split_text(bytes: &[u8]) -> usize {
 	return i;
 }
 
-// Returns the index of the first one of `<`, `&`, `>`, `'`, `"`
+// Returns the index of the first one of `<`, `&`, `>`
+// Returns the length of the input string if none found
+#[inline]
+fn split_text(bytes: &[u8]) -> usize {
+	scan(bytes, [b'<', b'&', b'>'])
+}
+
+// Returns the index of the first one of `<`, `&`, `>`, `'`, `"`, `\n`, `\t`, `\r`
 // Returns the length of the input string if none found
 #[inline]
 fn split_attr(bytes: &[u8]) -> usize {
-	let mut i = 0;
-	while i < bytes.len() {
-		if bytes[i] == b'<' || bytes[i] == b'&' || bytes[i] == b'>' || bytes[i] == b'\'' || bytes[i] == b'\"' {
-			break;
-		}
-		i += 1;
-	}
-	unsafe_assert!(i <= bytes.len());
-	return i;
+	scan(bytes, [b'<', b'&', b'>', b'\'', b'\"', b'\n', b'\t', b'\r'])
+}
+
+// Returns the index of the first one of `<`, `&`, `>`, `'`, `"`, `\n`, `\t`, `\r`
+// Returns the length of the input string if none found
+#[inline]
+fn split_full(bytes: &[u8]) -> usize {
+	scan(bytes, [b'<', b'&', b'>', b'\'', b'\"', b'\n', b'\t', b'\r'])
 }
 
 // Writes the escaped character as a xml entity
-// Writes nothing if chr is not one of `<`, `&`, `>`, `'`, `"`
+// Writes nothing if chr is not one of `<`, `&`, `>`, `'`, `"`, `\n`, `\t`, `\r`
 #[inline]
 fn escape_chr<W: ?Sized + fmt::Write>(write: &mut W, chr: u8) -> fmt::Result {
 	match chr {
@@ -62,10 +103,240 @@ fn escape_chr<W: ?Sized + fmt::Write>(write: &mut W, chr: u8) -> fmt::Result {
 		b'>' => write.write_str(crate::obfstr!("&gt;")),
 		b'\'' => write.write_str(crate::obfstr!("&apos;")),
 		b'\"' => write.write_str(crate::obfstr!("&quot;")),
+		b'\n' => write.write_str(crate::obfstr!("&#10;")),
+		b'\t' => write.write_str(crate::obfstr!("&#9;")),
+		b'\r' => write.write_str(crate::obfstr!("&#13;")),
 		_ => Ok(()),
 	}
 }
 
+// Writes the escaped character like `escape_chr` but the quotes as numeric character references
+#[inline]
+fn escape_chr_numeric<W: ?Sized + fmt::Write>(write: &mut W, chr: u8) -> fmt::Result {
+	match chr {
+		b'\'' => write.write_str(crate::obfstr!("&#39;")),
+		b'\"' => write.write_str(crate::obfstr!("&#34;")),
+		_ => escape_chr(write, chr),
+	}
+}
+
+// Returns the length of the xml entity written by `escape_chr`
+// Returns 1 if chr is not one of `<`, `&`, `>`, `'`, `"`, `\n`, `\t`, `\r`
+#[inline]
+fn escape_len(chr: u8) -> usize {
+	match chr {
+		b'<' => "&lt;".len(),
+		b'&' => "&amp;".len(),
+		b'>' => "&gt;".len(),
+		b'\'' => "&apos;".len(),
+		b'\"' => "&quot;".len(),
+		b'\n' => "&#10;".len(),
+		b'\t' => "&#9;".len(),
+		b'\r' => "&#13;".len(),
+		_ => 1,
+	}
+}
+
+/// Returns an upper bound of the length of the string after escaping.
+///
+/// Every special character is counted as the longest xml entity, the bound holds for both [`EscapeText`] and [`EscapeAttrValue`].
+///
+/// ```
+/// assert_eq!(format_xml::escaped_len_bound("hello"), 5);
+/// assert_eq!(format_xml::escaped_len_bound("a<b"), 8);
+/// ```
+#[inline]
+pub fn escaped_len_bound(s: &str) -> usize {
+	let specials = s.bytes().filter(|&chr| escape_len(chr) != 1).count();
+	s.len() + specials * ("&apos;".len() - 1)
+}
+
+/// Returns the exact length of the string after escaping with [`EscapeAttrValue`].
+///
+/// ```
+/// assert_eq!(format_xml::escaped_len_exact("hello"), 5);
+/// assert_eq!(format_xml::escaped_len_exact("a<b"), 6);
+/// assert_eq!(format_xml::escaped_len_exact("'&'"), 17);
+/// ```
+#[inline]
+pub fn escaped_len_exact(s: &str) -> usize {
+	s.bytes().map(escape_len).sum()
+}
+
+/// Marker for types whose `Display` output is written without escaping in text elements.
+///
+/// Implemented for types which never produce characters that need escaping, for trusted markup (see [`slot`](crate::slot)) and for the results of the text escaping helpers such as [`escape_text`].
+/// Values of these types are written directly to the formatter when used as `{value}` in text elements.
+/// In attribute values they are escaped unless they also implement [`NoEscapeAttr`].
+/// A `char` takes the same path unless it is one of the special characters.
+#[doc(hidden)]
+pub trait NoEscape {}
+
+/// Marker for types whose `Display` output is written without escaping in attribute values as well.
+///
+/// Implemented for types which never produce characters that need escaping and for the results of [`escape_attr`] and [`escape_full`].
+#[doc(hidden)]
+pub trait NoEscapeAttr: NoEscape {}
+macro_rules! impl_no_escape {
+	($($ty:ty),*) => { $(impl NoEscape for $ty {} impl NoEscapeAttr for $ty {})* };
+}
+impl_no_escape!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, bool);
+impl<T: ?Sized + NoEscape> NoEscape for &T {}
+impl<T: ?Sized + NoEscape> NoEscape for &mut T {}
+impl<T: ?Sized + NoEscapeAttr> NoEscapeAttr for &T {}
+impl<T: ?Sized + NoEscapeAttr> NoEscapeAttr for &mut T {}
+
+// Results of the escaping helpers, these are not escaped again where the escaping is sufficient for the location
+#[doc(hidden)]
+#[derive(Copy, Clone)]
+pub struct EscapedText<T>(T);
+#[doc(hidden)]
+#[derive(Copy, Clone)]
+pub struct EscapedAttr<T>(T);
+macro_rules! impl_escaped {
+	($($ty:ident),*) => {$(
+		impl<T: fmt::Display> fmt::Display for $ty<T> {
+			#[inline]
+			fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				self.0.fmt(f)
+			}
+		}
+		impl<T: fmt::Debug> fmt::Debug for $ty<T> {
+			#[inline]
+			fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				self.0.fmt(f)
+			}
+		}
+		impl<T> NoEscape for $ty<T> {}
+	)*};
+}
+impl_escaped!(EscapedText, EscapedAttr);
+impl<T> NoEscapeAttr for EscapedAttr<T> {}
+
+// Autoref specialization selecting whether a value needs escaping
+// `NoEscapeKind` is found first by method resolution if the value implements `NoEscape`
+#[doc(hidden)]
+pub struct EscapeSpec<'a, T: ?Sized>(pub &'a T);
+#[doc(hidden)]
+pub trait NoEscapeKind {
+	#[inline]
+	fn __xfmt_escape(&self) -> bool { false }
+}
+impl<T: ?Sized + NoEscape> NoEscapeKind for EscapeSpec<'_, T> {}
+#[doc(hidden)]
+pub trait EscapeKind {
+	#[inline]
+	fn __xfmt_escape(&self) -> bool { true }
+}
+impl<T: ?Sized> EscapeKind for &EscapeSpec<'_, T> {}
+
+// Autoref specialization selecting whether a value needs escaping in attribute values
+#[doc(hidden)]
+pub trait NoEscapeAttrKind {
+	#[inline]
+	fn __xfmt_escape_attr(&self) -> bool { false }
+}
+impl<T: ?Sized + NoEscapeAttr> NoEscapeAttrKind for EscapeSpec<'_, T> {}
+#[doc(hidden)]
+pub trait EscapeAttrKind {
+	#[inline]
+	fn __xfmt_escape_attr(&self) -> bool { true }
+}
+impl<T: ?Sized> EscapeAttrKind for &EscapeSpec<'_, T> {}
+
+/// Marker for string types whose `Display` output is the string itself.
+///
+/// Values of these types are written to the escaping writer directly without going through the formatting machinery.
+#[doc(hidden)]
+pub trait StrDisplay {
+	fn __as_str(&self) -> &str;
+}
+impl StrDisplay for str {
+	#[inline]
+	fn __as_str(&self) -> &str { self }
+}
+#[cfg(feature = "std")]
+impl StrDisplay for String {
+	#[inline]
+	fn __as_str(&self) -> &str { self }
+}
+#[cfg(feature = "std")]
+impl StrDisplay for std::borrow::Cow<'_, str> {
+	#[inline]
+	fn __as_str(&self) -> &str { self }
+}
+#[cfg(feature = "std")]
+impl StrDisplay for Box<str> {
+	#[inline]
+	fn __as_str(&self) -> &str { self }
+}
+impl<T: ?Sized + StrDisplay> StrDisplay for &T {
+	#[inline]
+	fn __as_str(&self) -> &str { (**self).__as_str() }
+}
+impl<T: ?Sized + StrDisplay> StrDisplay for &mut T {
+	#[inline]
+	fn __as_str(&self) -> &str { (**self).__as_str() }
+}
+
+// Autoref specialization selecting whether a value is written as a string
+// `StrKind` is found first by method resolution if the value implements `StrDisplay`
+#[doc(hidden)]
+pub trait StrKind {
+	fn __xfmt_str(&self) -> Option<&str>;
+}
+impl<T: ?Sized + StrDisplay> StrKind for EscapeSpec<'_, T> {
+	#[inline]
+	fn __xfmt_str(&self) -> Option<&str> { Some(self.0.__as_str()) }
+}
+#[doc(hidden)]
+pub trait DisplayKind {
+	#[inline]
+	fn __xfmt_str(&self) -> Option<&str> { None }
+}
+impl<T: ?Sized> DisplayKind for &EscapeSpec<'_, T> {}
+
+// Autoref specialization selecting whether an optional value is written
+// `OptionKind` is found first by method resolution if the value is an `Option`, other values are always written
+#[doc(hidden)]
+pub trait OptionKind {
+	fn __xfmt_some(&self) -> Option<&dyn fmt::Display>;
+}
+impl<T: fmt::Display> OptionKind for EscapeSpec<'_, Option<T>> {
+	#[inline]
+	fn __xfmt_some(&self) -> Option<&dyn fmt::Display> {
+		self.0.as_ref().map(|v| v as &dyn fmt::Display)
+	}
+}
+impl<T: fmt::Display> OptionKind for EscapeSpec<'_, &Option<T>> {
+	#[inline]
+	fn __xfmt_some(&self) -> Option<&dyn fmt::Display> {
+		self.0.as_ref().map(|v| v as &dyn fmt::Display)
+	}
+}
+#[doc(hidden)]
+pub trait SomeKind {
+	fn __xfmt_some(&self) -> Option<&dyn fmt::Display>;
+}
+impl<T: fmt::Display> SomeKind for &EscapeSpec<'_, T> {
+	#[inline]
+	fn __xfmt_some(&self) -> Option<&dyn fmt::Display> {
+		Some(self.0)
+	}
+}
+
+// Inherent methods take precedence, a char needs escaping only if it is one of the special characters
+impl EscapeSpec<'_, char> {
+	#[inline]
+	pub fn __xfmt_escape(&self) -> bool {
+		matches!(*self.0, '<' | '&' | '>' | '\'' | '"' | '\n' | '\t' | '\r')
+	}
+	#[inline]
+	pub fn __xfmt_escape_attr(&self) -> bool {
+		self.__xfmt_escape()
+	}
+}
+
 /// Escapes `<`, `&`, `>` when it appears in the formatted string.
 ///
 /// # Examples
@@ -120,15 +391,14 @@ impl<T: ?Sized + fmt::Write> fmt::Write for EscapeText<T> {
 	fn write_str(&mut self, s: &str) -> fmt::Result {
 		let mut bytes = s.as_bytes();
 		while bytes.len() > 0 {
-			let i = split_text(bytes);
-			let prefix = &bytes[..i];
+			let (prefix, next) = split_ascii(bytes, split_text(bytes));
 			if prefix.len() > 0 {
-				self.inner.write_str(from_utf8(prefix))?;
+				self.inner.write_str(prefix)?;
 			}
 
-			if let Some(&chr) = bytes.get(i) {
+			if let Some((chr, rest)) = next {
 				escape_chr(&mut self.inner, chr)?;
-				bytes = &bytes[i + 1..];
+				bytes = rest;
 			}
 			else {
 				break;
@@ -136,10 +406,19 @@ impl<T: ?Sized + fmt::Write> fmt::Write for EscapeText<T> {
 		}
 		Ok(())
 	}
+	#[inline]
+	fn write_char(&mut self, chr: char) -> fmt::Result {
+		match chr {
+			'<' | '&' | '>' => escape_chr(&mut self.inner, chr as u8),
+			_ => self.inner.write_char(chr),
+		}
+	}
 }
 
 /// Escapes `<`, `&`, `>`, `'`, `"` when it appears in the formatted string.
 ///
+/// The whitespace characters `\n`, `\t`, `\r` are escaped as well, otherwise attribute value normalization replaces them with spaces.
+///
 /// # Examples
 ///
 /// ```
@@ -162,6 +441,7 @@ impl<T: ?Sized + fmt::Write> fmt::Write for EscapeText<T> {
 /// check("pre<script>post", "pre&lt;script&gt;post");
 /// check("&<>\"\'", "&amp;&lt;&gt;&quot;&apos;");
 /// check("&a'b<c>", "&amp;a&apos;b&lt;c&gt;");
+/// check("a\nb\tc\r\n", "a&#10;b&#9;c&#13;&#10;");
 /// ```
 #[repr(transparent)]
 pub struct EscapeAttrValue<T: ?Sized> {
@@ -195,15 +475,14 @@ impl<T: ?Sized + fmt::Write> fmt::Write for EscapeAttrValue<T> {
 	fn write_str(&mut self, s: &str) -> fmt::Result {
 		let mut bytes = s.as_bytes();
 		while bytes.len() > 0 {
-			let i = split_attr(bytes);
-			let prefix = &bytes[..i];
+			let (prefix, next) = split_ascii(bytes, split_attr(bytes));
 			if prefix.len() > 0 {
-				self.inner.write_str(from_utf8(prefix))?;
+				self.inner.write_str(prefix)?;
 			}
 
-			if let Some(&chr) = bytes.get(i) {
+			if let Some((chr, rest)) = next {
 				escape_chr(&mut self.inner, chr)?;
-				bytes = &bytes[i + 1..];
+				bytes = rest;
 			}
 			else {
 				break;
@@ -211,27 +490,45 @@ impl<T: ?Sized + fmt::Write> fmt::Write for EscapeAttrValue<T> {
 		}
 		Ok(())
 	}
+	#[inline]
+	fn write_char(&mut self, chr: char) -> fmt::Result {
+		match chr {
+			'<' | '&' | '>' | '\'' | '"' | '\n' | '\t' | '\r' => escape_chr(&mut self.inner, chr as u8),
+			_ => self.inner.write_char(chr),
+		}
+	}
 }
 
-/// Escapes `--` in comments by not writing it at all.
+/// Escapes attribute values like [`EscapeAttrValue`] but writes the quotes as numeric character references.
 ///
-/// # Safety
+/// Writes `'` as `&#39;` and `"` as `&#34;`, `&apos;` is not defined in HTML 4 and some mail clients render it literally.
 ///
-/// This implementation is incorrect, it won't catch callers who split `--` in multiple `write_str` calls!
-#[doc(hidden)]
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut s = String::new();
+/// write!(format_xml::EscapeAttrValueNumeric::wrap(&mut s), "{}", "'quoted' \"title\" & <more>").unwrap();
+/// assert_eq!(s, "&#39;quoted&#39; &#34;title&#34; &amp; &lt;more&gt;");
+/// ```
 #[repr(transparent)]
-pub struct EscapeComment<T: ?Sized> {
+pub struct EscapeAttrValueNumeric<T: ?Sized> {
 	inner: T,
 }
-impl<T: ?Sized + fmt::Write> EscapeComment<T> {
+impl<T: ?Sized + fmt::Write> EscapeAttrValueNumeric<T> {
 	#[inline]
-	pub fn wrap(v: &mut T) -> &mut EscapeComment<T> {
+	pub fn wrap(v: &mut T) -> &mut EscapeAttrValueNumeric<T> {
 		unsafe { mem::transmute(v) }
 	}
 }
+impl<T> From<T> for EscapeAttrValueNumeric<T> {
+	#[inline]
+	fn from(inner: T) -> Self {
+		EscapeAttrValueNumeric { inner }
+	}
+}
 // Forward Write calls
 #[doc(hidden)]
-impl<T: ?Sized + fmt::Write> EscapeComment<T> {
+impl<T: ?Sized + fmt::Write> EscapeAttrValueNumeric<T> {
 	#[inline]
 	pub fn write_str(&mut self, s: &str) -> fmt::Result {
 		<Self as fmt::Write>::write_str(self, s)
@@ -241,34 +538,141 @@ impl<T: ?Sized + fmt::Write> EscapeComment<T> {
 		<Self as fmt::Write>::write_fmt(self, args)
 	}
 }
-impl<T: ?Sized + fmt::Write> fmt::Write for EscapeComment<T> {
+impl<T: ?Sized + fmt::Write> fmt::Write for EscapeAttrValueNumeric<T> {
 	fn write_str(&mut self, s: &str) -> fmt::Result {
-		for piece in s.split(crate::obfstr!("--")) {
-			self.inner.write_str(piece)?;
+		let mut bytes = s.as_bytes();
+		while !bytes.is_empty() {
+			let (prefix, next) = split_ascii(bytes, split_attr(bytes));
+			if !prefix.is_empty() {
+				self.inner.write_str(prefix)?;
+			}
+
+			if let Some((chr, rest)) = next {
+				escape_chr_numeric(&mut self.inner, chr)?;
+				bytes = rest;
+			}
+			else {
+				break;
+			}
 		}
 		Ok(())
 	}
+	#[inline]
+	fn write_char(&mut self, chr: char) -> fmt::Result {
+		match chr {
+			'<' | '&' | '>' | '\'' | '"' | '\n' | '\t' | '\r' => escape_chr_numeric(&mut self.inner, chr as u8),
+			_ => self.inner.write_char(chr),
+		}
+	}
 }
 
-/// Escapes `]]>` in CDATA sections.
+/// Escapes text elements with as few character references as possible.
+///
+/// Escapes `&` and `<`, and `>` only when it ends `]]>` which is not allowed in xml text, also when it is split across `write_str` calls.
+/// The output is well-formed xml but not suitable for HTML `<script>` or `<style>` contexts, same as [`EscapeText`].
 ///
-/// # Safety
+/// ```
+/// use std::fmt::Write;
 ///
-/// This implementation is incorrect, it won't catch callers who split writing `]]>` in multiple `write_str` calls!
+/// let mut s = String::new();
+/// let mut writer = format_xml::EscapeTextMinimal::wrap(&mut s);
+/// write!(writer, "{}", "a > b && c < d ]]").unwrap();
+/// write!(writer, "{}", ">").unwrap();
+/// assert_eq!(s, "a > b &amp;&amp; c &lt; d ]]&gt;");
+/// ```
+pub struct EscapeTextMinimal<T> {
+	inner: T,
+	brackets: u8,
+}
+impl<T: fmt::Write> EscapeTextMinimal<T> {
+	#[inline]
+	pub fn new(inner: T) -> EscapeTextMinimal<T> {
+		EscapeTextMinimal { inner, brackets: 0 }
+	}
+	/// Returns the inner writer.
+	#[inline]
+	pub fn into_inner(self) -> T {
+		self.inner
+	}
+}
+impl<'a, T: ?Sized + fmt::Write> EscapeTextMinimal<&'a mut T> {
+	#[inline]
+	pub fn wrap(v: &'a mut T) -> EscapeTextMinimal<&'a mut T> {
+		EscapeTextMinimal::new(v)
+	}
+}
+// Forward Write calls
 #[doc(hidden)]
+impl<T: fmt::Write> EscapeTextMinimal<T> {
+	#[inline]
+	pub fn write_str(&mut self, s: &str) -> fmt::Result {
+		<Self as fmt::Write>::write_str(self, s)
+	}
+	#[inline]
+	pub fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
+		<Self as fmt::Write>::write_fmt(self, args)
+	}
+}
+impl<T: fmt::Write> fmt::Write for EscapeTextMinimal<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let mut start = 0;
+		for (i, chr) in s.bytes().enumerate() {
+			match chr {
+				b'&' | b'<' => (),
+				b'>' if self.brackets == 2 => (),
+				b']' => {
+					self.brackets = u8::min(self.brackets + 1, 2);
+					continue;
+				},
+				_ => {
+					self.brackets = 0;
+					continue;
+				},
+			}
+			self.brackets = 0;
+			if start < i {
+				self.inner.write_str(&s[start..i])?;
+			}
+			escape_chr(&mut self.inner, chr)?;
+			start = i + 1;
+		}
+		if start < s.len() {
+			self.inner.write_str(&s[start..])?;
+		}
+		Ok(())
+	}
+}
+
+/// Escapes double quoted attribute values with as few character references as possible.
+///
+/// Escapes `&`, `<` and `"`, the whitespace `\n`, `\t`, `\r` are escaped as well so they survive attribute value normalization.
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut s = String::new();
+/// write!(format_xml::EscapeAttrValueMinimal::wrap(&mut s), "{}", "'a' > \"b\" & c\n").unwrap();
+/// assert_eq!(s, "'a' > &quot;b&quot; &amp; c&#10;");
+/// ```
 #[repr(transparent)]
-pub struct EscapeCharData<T: ?Sized> {
+pub struct EscapeAttrValueMinimal<T: ?Sized> {
 	inner: T,
 }
-impl<T: ?Sized + fmt::Write> EscapeCharData<T> {
+impl<T: ?Sized + fmt::Write> EscapeAttrValueMinimal<T> {
 	#[inline]
-	pub fn wrap(v: &mut T) -> &mut EscapeCharData<T> {
+	pub fn wrap(v: &mut T) -> &mut EscapeAttrValueMinimal<T> {
 		unsafe { mem::transmute(v) }
 	}
 }
+impl<T> From<T> for EscapeAttrValueMinimal<T> {
+	#[inline]
+	fn from(inner: T) -> Self {
+		EscapeAttrValueMinimal { inner }
+	}
+}
 // Forward Write calls
 #[doc(hidden)]
-impl<T: ?Sized + fmt::Write> EscapeCharData<T> {
+impl<T: ?Sized + fmt::Write> EscapeAttrValueMinimal<T> {
 	#[inline]
 	pub fn write_str(&mut self, s: &str) -> fmt::Result {
 		<Self as fmt::Write>::write_str(self, s)
@@ -278,43 +682,1672 @@ impl<T: ?Sized + fmt::Write> EscapeCharData<T> {
 		<Self as fmt::Write>::write_fmt(self, args)
 	}
 }
-impl<T: ?Sized + fmt::Write> fmt::Write for EscapeCharData<T> {
+impl<T: ?Sized + fmt::Write> fmt::Write for EscapeAttrValueMinimal<T> {
 	fn write_str(&mut self, s: &str) -> fmt::Result {
-		let mut write_sep = false;
-		for piece in s.split(crate::obfstr!("]]>")) {
-			if write_sep {
-				self.inner.write_str(crate::obfstr!("]]]]><![CDATA[>"))?;
+		let mut start = 0;
+		for (i, chr) in s.bytes().enumerate() {
+			if !matches!(chr, b'&' | b'<' | b'"' | b'\n' | b'\t' | b'\r') {
+				continue;
 			}
-			write_sep = true;
-			self.inner.write_str(piece)?;
+			if start < i {
+				self.inner.write_str(&s[start..i])?;
+			}
+			escape_chr(&mut self.inner, chr)?;
+			start = i + 1;
+		}
+		if start < s.len() {
+			self.inner.write_str(&s[start..])?;
 		}
 		Ok(())
 	}
 }
 
-#[test]
-fn test_comment() {
-	#[track_caller]
-	fn check(input: &str, escaped: &str) {
-		let mut writer = EscapeComment { inner: String::new() };
-		writer.write_str(input).unwrap();
-		assert_eq!(writer.inner, escaped);
+/// Escapes like [`EscapeText`] or [`EscapeAttrValue`] but leaves well-formed character references alone.
+///
+/// An `&` followed by one of the xml entities `amp`, `lt`, `gt`, `quot`, `apos` or a numeric character reference and `;` is written as-is.
+/// Any other `&` is escaped as `&amp;`.
+///
+/// A possible reference at the end of a write is held back until the next write as it may be split across `write_str` calls.
+/// Call [`finish`](EscapePreserveEntities::finish) to write it when done, dropping the writer writes it as well but ignores errors.
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut s = String::new();
+/// let mut writer = format_xml::EscapePreserveEntities::text(&mut s);
+/// write!(writer, "{}", "Tom &amp; Jerry&#x2019;s & <friends> &nbsp; &am").unwrap();
+/// writer.finish().unwrap();
+/// assert_eq!(s, "Tom &amp; Jerry&#x2019;s &amp; &lt;friends&gt; &amp;nbsp; &amp;am");
+/// ```
+pub struct EscapePreserveEntities<T: fmt::Write> {
+	inner: T,
+	attr: bool,
+	// Only ever holds ascii characters
+	pending: [u8; 10],
+	len: u8,
+}
+impl<T: fmt::Write> EscapePreserveEntities<T> {
+	/// Escapes text elements like [`EscapeText`].
+	#[inline]
+	pub fn text(inner: T) -> EscapePreserveEntities<T> {
+		EscapePreserveEntities { inner, attr: false, pending: [0; 10], len: 0 }
+	}
+	/// Escapes attribute values like [`EscapeAttrValue`].
+	#[inline]
+	pub fn attr_value(inner: T) -> EscapePreserveEntities<T> {
+		EscapePreserveEntities { inner, attr: true, pending: [0; 10], len: 0 }
+	}
+	/// Writes the held back `&` if any and returns the inner writer.
+	#[inline]
+	pub fn finish(mut self) -> Result<T, fmt::Error> {
+		self.flush()?;
+		// Nothing is pending, move the inner writer out without running drop
+		let this = mem::ManuallyDrop::new(self);
+		Ok(unsafe { ptr::read(&this.inner) })
+	}
+	// Writes the pending characters after the `&` as plain text
+	fn flush(&mut self) -> fmt::Result {
+		if self.len > 0 {
+			let len = self.len as usize;
+			self.len = 0;
+			self.inner.write_str(crate::obfstr!("&amp;"))?;
+			self.inner.write_str(from_utf8(&self.pending[1..len]))?;
+		}
+		Ok(())
+	}
+	fn write_plain(&mut self, s: &str) -> fmt::Result {
+		let mut bytes = s.as_bytes();
+		while !bytes.is_empty() {
+			let i = if self.attr { split_attr(bytes) } else { split_text(bytes) };
+			let (prefix, next) = split_ascii(bytes, i);
+			if !prefix.is_empty() {
+				self.inner.write_str(prefix)?;
+			}
+			match next {
+				Some((b'&', _)) => {
+					self.pending[0] = b'&';
+					self.len = 1;
+					break;
+				},
+				Some((chr, rest)) => {
+					escape_chr(&mut self.inner, chr)?;
+					bytes = rest;
+				},
+				None => break,
+			}
+		}
+		Ok(())
+	}
+}
+impl<T: fmt::Write> Drop for EscapePreserveEntities<T> {
+	fn drop(&mut self) {
+		let _ = self.flush();
+	}
+}
+impl<'a, T: ?Sized + fmt::Write> EscapePreserveEntities<&'a mut T> {
+	/// Escapes text elements like [`EscapeText`].
+	#[inline]
+	pub fn wrap_text(v: &'a mut T) -> EscapePreserveEntities<&'a mut T> {
+		EscapePreserveEntities::text(v)
+	}
+	/// Escapes attribute values like [`EscapeAttrValue`].
+	#[inline]
+	pub fn wrap_attr_value(v: &'a mut T) -> EscapePreserveEntities<&'a mut T> {
+		EscapePreserveEntities::attr_value(v)
+	}
+}
+// Forward Write calls
+#[doc(hidden)]
+impl<T: fmt::Write> EscapePreserveEntities<T> {
+	#[inline]
+	pub fn write_str(&mut self, s: &str) -> fmt::Result {
+		<Self as fmt::Write>::write_str(self, s)
+	}
+	#[inline]
+	pub fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
+		<Self as fmt::Write>::write_fmt(self, args)
+	}
+}
+impl<T: fmt::Write> fmt::Write for EscapePreserveEntities<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let mut s = s;
+		loop {
+			// Write plain text up to the next `&`
+			if self.len == 0 {
+				let i = s.find('&').map(|i| i + 1).unwrap_or(s.len());
+				self.write_plain(&s[..i])?;
+				s = &s[i..];
+				if self.len == 0 {
+					return Ok(());
+				}
+			}
+			// Collect the characters of a possible reference
+			let mut i = 0;
+			for &chr in s.as_bytes() {
+				let len = self.len as usize;
+				if chr == b';' {
+					if is_entity(&self.pending[1..len]) {
+						self.inner.write_str(from_utf8(&self.pending[..len]))?;
+						self.inner.write_str(";")?;
+						self.len = 0;
+						i += 1;
+					}
+					break;
+				}
+				if len >= self.pending.len() || !(chr.is_ascii_alphanumeric() || chr == b'#' && len == 1) {
+					break;
+				}
+				self.pending[len] = chr;
+				self.len += 1;
+				i += 1;
+			}
+			s = &s[i..];
+			if s.is_empty() {
+				return Ok(());
+			}
+			self.flush()?;
+		}
 	}
+}
 
-	check("", "");
-	check(" ", " ");
-	check("--", "");
-	check(" --", " ");
-	check("-- ", " ");
+// Checks the name between `&` and `;` of a character reference
+fn is_entity(name: &[u8]) -> bool {
+	let (digits, radix) = match name {
+		b"amp" | b"lt" | b"gt" | b"quot" | b"apos" => return true,
+		[b'#', b'x', digits @ ..] => (digits, 16),
+		[b'#', digits @ ..] => (digits, 10),
+		_ => return false,
+	};
+	let value = match str::from_utf8(digits) {
+		Ok(digits) if !digits.is_empty() && digits.bytes().all(|chr| chr.is_ascii_hexdigit()) => u32::from_str_radix(digits, radix),
+		_ => return false,
+	};
+	matches!(value.ok().and_then(char::from_u32), Some(chr) if chr != '\0')
+}
+
+/// Escapes all five special characters regardless of context.
+///
+/// Escapes `<`, `&`, `>`, `'`, `"` for fragments which may end up in both text elements and attribute values.
+/// The whitespace characters `\n`, `\t`, `\r` are escaped as well so attribute value normalization does not replace them with spaces.
+/// Unlike [`EscapeAttrValue`] this writer is guaranteed to stay conservative.
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut s = String::new();
+/// write!(format_xml::EscapeFull::wrap(&mut s), "{}", "\"it's\" <b>&</b>").unwrap();
+/// assert_eq!(s, "&quot;it&apos;s&quot; &lt;b&gt;&amp;&lt;/b&gt;");
+/// ```
+#[repr(transparent)]
+pub struct EscapeFull<T: ?Sized> {
+	inner: T,
+}
+impl<T: ?Sized + fmt::Write> EscapeFull<T> {
+	#[inline]
+	pub fn wrap(v: &mut T) -> &mut EscapeFull<T> {
+		unsafe { mem::transmute(v) }
+	}
+}
+impl<T> From<T> for EscapeFull<T> {
+	#[inline]
+	fn from(inner: T) -> Self {
+		EscapeFull { inner }
+	}
+}
+// Forward Write calls
+#[doc(hidden)]
+impl<T: ?Sized + fmt::Write> EscapeFull<T> {
+	#[inline]
+	pub fn write_str(&mut self, s: &str) -> fmt::Result {
+		<Self as fmt::Write>::write_str(self, s)
+	}
+	#[inline]
+	pub fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
+		<Self as fmt::Write>::write_fmt(self, args)
+	}
+}
+impl<T: ?Sized + fmt::Write> fmt::Write for EscapeFull<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let mut bytes = s.as_bytes();
+		while !bytes.is_empty() {
+			let (prefix, next) = split_ascii(bytes, split_full(bytes));
+			if !prefix.is_empty() {
+				self.inner.write_str(prefix)?;
+			}
+
+			if let Some((chr, rest)) = next {
+				escape_chr(&mut self.inner, chr)?;
+				bytes = rest;
+			}
+			else {
+				break;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Escapes text elements for ASCII-only output.
+///
+/// Escapes `<`, `&`, `>` like [`EscapeText`] and writes every non-ASCII character as a numeric character reference, eg. `é` as `&#xE9;`.
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut s = String::new();
+/// write!(format_xml::EscapeTextAscii::wrap(&mut s), "{}", "Café <b>😀</b>").unwrap();
+/// assert_eq!(s, "Caf&#xE9; &lt;b&gt;&#x1F600;&lt;/b&gt;");
+/// ```
+#[repr(transparent)]
+pub struct EscapeTextAscii<T: ?Sized> {
+	inner: T,
+}
+impl<T: ?Sized + fmt::Write> EscapeTextAscii<T> {
+	#[inline]
+	pub fn wrap(v: &mut T) -> &mut EscapeTextAscii<T> {
+		unsafe { mem::transmute(v) }
+	}
+}
+impl<T> From<T> for EscapeTextAscii<T> {
+	#[inline]
+	fn from(inner: T) -> Self {
+		EscapeTextAscii { inner }
+	}
+}
+// Forward Write calls
+#[doc(hidden)]
+impl<T: ?Sized + fmt::Write> EscapeTextAscii<T> {
+	#[inline]
+	pub fn write_str(&mut self, s: &str) -> fmt::Result {
+		<Self as fmt::Write>::write_str(self, s)
+	}
+	#[inline]
+	pub fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
+		<Self as fmt::Write>::write_fmt(self, args)
+	}
+}
+impl<T: ?Sized + fmt::Write> fmt::Write for EscapeTextAscii<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let mut start = 0;
+		for (i, chr) in s.char_indices() {
+			if chr.is_ascii() && !matches!(chr, '<' | '&' | '>') {
+				continue;
+			}
+			if start < i {
+				self.inner.write_str(&s[start..i])?;
+			}
+			if chr.is_ascii() {
+				escape_chr(&mut self.inner, chr as u8)?;
+			}
+			else {
+				write!(self.inner, "&#x{:X};", chr as u32)?;
+			}
+			start = i + chr.len_utf8();
+		}
+		if start < s.len() {
+			self.inner.write_str(&s[start..])?;
+		}
+		Ok(())
+	}
+}
+
+/// Escapes text elements with a table of additional replacements.
+///
+/// Each character found in the table is replaced with its string, use an empty string to remove the character.
+/// Other characters are escaped like [`EscapeText`], use [`table_only`](EscapeCustom::table_only) to apply the table without the standard escaping.
+/// The replacements are written as-is and are not escaped.
+///
+/// ASCII characters are looked up in an array, characters outside ASCII by searching the table.
+/// When a character appears more than once the first entry wins.
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// const TABLE: &[(char, &str)] = &[('\u{A0}', "&#160;"), ('\u{AD}', "")];
+///
+/// let mut s = String::new();
+/// write!(format_xml::EscapeCustom::wrap(&mut s, TABLE), "{}", "Tom\u{A0}&\u{A0}Jer\u{AD}ry").unwrap();
+/// assert_eq!(s, "Tom&#160;&amp;&#160;Jerry");
+/// ```
+pub struct EscapeCustom<'t, T> {
+	inner: T,
+	table: &'t [(char, &'t str)],
+	// The index in the table plus one of every ASCII character, `0` if not replaced or `STANDARD` if escaped as usual
+	ascii: [u16; 128],
+	// The table contains characters outside ASCII
+	wide: bool,
+}
+const STANDARD: u16 = u16::MAX;
+impl<'t, T: fmt::Write> EscapeCustom<'t, T> {
+	/// Applies the table on top of escaping text elements.
+	#[inline]
+	pub fn new(inner: T, table: &'t [(char, &'t str)]) -> EscapeCustom<'t, T> {
+		EscapeCustom::with(inner, table, true)
+	}
+	/// Applies the table only, without escaping the special characters.
+	#[inline]
+	pub fn table_only(inner: T, table: &'t [(char, &'t str)]) -> EscapeCustom<'t, T> {
+		EscapeCustom::with(inner, table, false)
+	}
+	fn with(inner: T, table: &'t [(char, &'t str)], standard: bool) -> EscapeCustom<'t, T> {
+		let mut ascii = [0; 128];
+		if standard {
+			for &chr in b"<&>" {
+				ascii[chr as usize] = STANDARD;
+			}
+		}
+		let mut wide = false;
+		for (index, &(key, _)) in table.iter().enumerate().take(STANDARD as usize - 1) {
+			if !key.is_ascii() {
+				wide = true;
+			}
+			else if matches!(ascii[key as usize], 0 | STANDARD) {
+				ascii[key as usize] = index as u16 + 1;
+			}
+		}
+		EscapeCustom { inner, table, ascii, wide }
+	}
+	/// Returns the inner writer.
+	#[inline]
+	pub fn into_inner(self) -> T {
+		self.inner
+	}
+}
+impl<'a, 't, T: ?Sized + fmt::Write> EscapeCustom<'t, &'a mut T> {
+	/// Applies the table on top of escaping text elements.
+	#[inline]
+	pub fn wrap(v: &'a mut T, table: &'t [(char, &'t str)]) -> EscapeCustom<'t, &'a mut T> {
+		EscapeCustom::new(v, table)
+	}
+}
+// Forward Write calls
+#[doc(hidden)]
+impl<'t, T: fmt::Write> EscapeCustom<'t, T> {
+	#[inline]
+	pub fn write_str(&mut self, s: &str) -> fmt::Result {
+		<Self as fmt::Write>::write_str(self, s)
+	}
+	#[inline]
+	pub fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
+		<Self as fmt::Write>::write_fmt(self, args)
+	}
+}
+impl<'t, T: fmt::Write> fmt::Write for EscapeCustom<'t, T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let bytes = s.as_bytes();
+		let mut start = 0;
+		let mut i = 0;
+		while let Some(&chr) = bytes.get(i) {
+			if chr.is_ascii() {
+				let index = self.ascii[chr as usize];
+				if index != 0 {
+					self.inner.write_str(&s[start..i])?;
+					if index == STANDARD {
+						escape_chr(&mut self.inner, chr)?;
+					}
+					else {
+						self.inner.write_str(self.table[index as usize - 1].1)?;
+					}
+					start = i + 1;
+				}
+				i += 1;
+			}
+			else if self.wide {
+				let chr = s[i..].chars().next().unwrap_or_default();
+				let len = chr.len_utf8();
+				if let Some(&(_, replacement)) = self.table.iter().find(|&&(key, _)| key == chr) {
+					self.inner.write_str(&s[start..i])?;
+					self.inner.write_str(replacement)?;
+					start = i + len;
+				}
+				i += len;
+			}
+			// Without characters outside ASCII in the table the bytes of multibyte characters are skipped one at a time
+			else {
+				i += 1;
+			}
+		}
+		if start < s.len() {
+			self.inner.write_str(&s[start..])?;
+		}
+		Ok(())
+	}
+}
+
+/// Escapes string literals in inline scripts.
+///
+/// Escapes `\`, `"`, `'`, `` ` `` with a backslash and writes control characters, `<`, `>`, `&` and the line separators U+2028 and U+2029 as unicode escapes.
+/// Escaping `<` prevents the value from closing the script element with `</script` or starting a comment with `<!--`.
+/// Escaping `&` keeps the output well-formed in text elements outside `<script>` and in XHTML, where script content is parsed as markup.
+///
+/// The value must be placed inside a quoted string literal.
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut s = String::new();
+/// write!(format_xml::EscapeJs::wrap(&mut s), "{}", "</script>'\n").unwrap();
+/// assert_eq!(s, r#"\u003C/script\u003E\'\n"#);
+/// ```
+#[repr(transparent)]
+pub struct EscapeJs<T: ?Sized> {
+	inner: T,
+}
+impl<T: ?Sized + fmt::Write> EscapeJs<T> {
+	#[inline]
+	pub fn wrap(v: &mut T) -> &mut EscapeJs<T> {
+		unsafe { mem::transmute(v) }
+	}
+}
+impl<T> From<T> for EscapeJs<T> {
+	#[inline]
+	fn from(inner: T) -> Self {
+		EscapeJs { inner }
+	}
+}
+// Forward Write calls
+#[doc(hidden)]
+impl<T: ?Sized + fmt::Write> EscapeJs<T> {
+	#[inline]
+	pub fn write_str(&mut self, s: &str) -> fmt::Result {
+		<Self as fmt::Write>::write_str(self, s)
+	}
+	#[inline]
+	pub fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
+		<Self as fmt::Write>::write_fmt(self, args)
+	}
+}
+impl<T: ?Sized + fmt::Write> fmt::Write for EscapeJs<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let mut start = 0;
+		for (i, chr) in s.char_indices() {
+			let escaped = match chr {
+				'\\' => "\\\\",
+				'"' => "\\\"",
+				'\'' => "\\'",
+				'`' => "\\`",
+				'\n' => "\\n",
+				'\r' => "\\r",
+				'\t' => "\\t",
+				'<' | '>' | '&' | '\u{2028}' | '\u{2029}' | '\0'..='\x1F' | '\x7F' => "",
+				_ => continue,
+			};
+			if start < i {
+				self.inner.write_str(&s[start..i])?;
+			}
+			if !escaped.is_empty() {
+				self.inner.write_str(escaped)?;
+			}
+			else {
+				write!(self.inner, "\\u{:04X}", chr as u32)?;
+			}
+			start = i + chr.len_utf8();
+		}
+		if start < s.len() {
+			self.inner.write_str(&s[start..])?;
+		}
+		Ok(())
+	}
+}
+
+/// Escapes values in stylesheets.
+///
+/// Writes every ASCII character other than letters, digits, space and `-_.,#%+!` as a CSS hex escape followed by a space, eg. `<` becomes `\3C `.
+/// The value cannot terminate a string, declaration, block or the style element, and the output contains no characters which need escaping in attributes.
+/// Non-ASCII characters are written as-is.
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut s = String::new();
+/// write!(format_xml::EscapeCss::wrap(&mut s), "{}", "red;}</style>").unwrap();
+/// assert_eq!(s, r"red\3B \7D \3C \2F style\3E ");
+/// ```
+#[repr(transparent)]
+pub struct EscapeCss<T: ?Sized> {
+	inner: T,
+}
+impl<T: ?Sized + fmt::Write> EscapeCss<T> {
+	#[inline]
+	pub fn wrap(v: &mut T) -> &mut EscapeCss<T> {
+		unsafe { mem::transmute(v) }
+	}
+}
+impl<T> From<T> for EscapeCss<T> {
+	#[inline]
+	fn from(inner: T) -> Self {
+		EscapeCss { inner }
+	}
+}
+// Forward Write calls
+#[doc(hidden)]
+impl<T: ?Sized + fmt::Write> EscapeCss<T> {
+	#[inline]
+	pub fn write_str(&mut self, s: &str) -> fmt::Result {
+		<Self as fmt::Write>::write_str(self, s)
+	}
+	#[inline]
+	pub fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
+		<Self as fmt::Write>::write_fmt(self, args)
+	}
+}
+impl<T: ?Sized + fmt::Write> fmt::Write for EscapeCss<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let mut start = 0;
+		for (i, chr) in s.bytes().enumerate() {
+			if !chr.is_ascii() || chr.is_ascii_alphanumeric() || b" -_.,#%+!".contains(&chr) {
+				continue;
+			}
+			if start < i {
+				self.inner.write_str(&s[start..i])?;
+			}
+			write!(self.inner, "\\{:X} ", chr)?;
+			start = i + 1;
+		}
+		if start < s.len() {
+			self.inner.write_str(&s[start..])?;
+		}
+		Ok(())
+	}
+}
+
+/// Escapes JSON string contents.
+///
+/// Escapes `"` and `\` with a backslash and writes control characters as `\n`, `\r`, `\t`, `\b`, `\f` or unicode escapes.
+/// Call [`escape_slash`](EscapeJsonString::escape_slash) to also write `/` as `\/`.
+///
+/// The output is the body of a JSON string, the surrounding quotes are not written.
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut s = String::new();
+/// let mut writer = format_xml::EscapeJsonString::new(&mut s).escape_slash();
+/// write!(writer, "{}", "\"a\\b\"</c>\n").unwrap();
+/// assert_eq!(s, r#"\"a\\b\"<\/c>\n"#);
+/// ```
+pub struct EscapeJsonString<T> {
+	inner: T,
+	slash: bool,
+}
+impl<T: fmt::Write> EscapeJsonString<T> {
+	#[inline]
+	pub fn new(inner: T) -> EscapeJsonString<T> {
+		EscapeJsonString { inner, slash: false }
+	}
+	/// Also escapes `/`.
+	#[inline]
+	pub fn escape_slash(self) -> EscapeJsonString<T> {
+		EscapeJsonString { slash: true, ..self }
+	}
+	/// Returns the inner writer.
+	#[inline]
+	pub fn into_inner(self) -> T {
+		self.inner
+	}
+}
+impl<T: fmt::Write> fmt::Write for EscapeJsonString<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let mut start = 0;
+		for (i, chr) in s.bytes().enumerate() {
+			let escaped = match chr {
+				b'"' => "\\\"",
+				b'\\' => "\\\\",
+				b'/' if self.slash => "\\/",
+				b'\n' => "\\n",
+				b'\r' => "\\r",
+				b'\t' => "\\t",
+				b'\x08' => "\\b",
+				b'\x0C' => "\\f",
+				b'\0'..=b'\x1F' => "",
+				_ => continue,
+			};
+			if start < i {
+				self.inner.write_str(&s[start..i])?;
+			}
+			if !escaped.is_empty() {
+				self.inner.write_str(escaped)?;
+			}
+			else {
+				write!(self.inner, "\\u{:04X}", chr)?;
+			}
+			start = i + 1;
+		}
+		if start < s.len() {
+			self.inner.write_str(&s[start..])?;
+		}
+		Ok(())
+	}
+}
+
+/// Escapes bytes written to an `std::io::Write` sink.
+///
+/// Escapes like [`EscapeText`] or [`EscapeAttrValue`], the special characters are ASCII so any bytes can be escaped without decoding them.
+/// Errors of the inner writer are returned as-is.
+///
+/// ```
+/// use std::io::Write;
+///
+/// let mut writer = format_xml::EscapeIo::text(Vec::new());
+/// writer.write_all(b"a < b").unwrap();
+/// write!(writer, " & {}", "c").unwrap();
+/// assert_eq!(writer.into_inner(), b"a &lt; b &amp; c");
+/// ```
+#[cfg(feature = "std")]
+pub struct EscapeIo<W> {
+	inner: W,
+	attr: bool,
+}
+#[cfg(feature = "std")]
+impl<W: std::io::Write> EscapeIo<W> {
+	/// Escapes text elements like [`EscapeText`].
+	#[inline]
+	pub fn text(inner: W) -> EscapeIo<W> {
+		EscapeIo { inner, attr: false }
+	}
+	/// Escapes attribute values like [`EscapeAttrValue`].
+	#[inline]
+	pub fn attr_value(inner: W) -> EscapeIo<W> {
+		EscapeIo { inner, attr: true }
+	}
+	/// Returns the inner writer.
+	#[inline]
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+}
+#[cfg(feature = "std")]
+impl<W: std::io::Write> std::io::Write for EscapeIo<W> {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.write_all(buf)?;
+		Ok(buf.len())
+	}
+	fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+		let mut bytes = buf;
+		while !bytes.is_empty() {
+			let i = if self.attr { split_attr(bytes) } else { split_text(bytes) };
+			if i > 0 {
+				self.inner.write_all(&bytes[..i])?;
+			}
+			if let Some(&chr) = bytes.get(i) {
+				let mut writer = crate::IoWriter::new(&mut self.inner);
+				let result = escape_chr(&mut writer, chr);
+				writer.finish(result)?;
+				bytes = &bytes[i + 1..];
+			}
+			else {
+				break;
+			}
+		}
+		Ok(())
+	}
+	#[inline]
+	fn flush(&mut self) -> std::io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+/// Escapes unquoted attribute values.
+///
+/// Follows the HTML5 rules for unquoted attribute values: whitespace, `"`, `'`, `` ` ``, `<`, `>`, `=` and `&` are written as character references.
+/// An unquoted value cannot be empty, check [`is_empty`](EscapeAttrUnquoted::is_empty) and write `""` instead.
+///
+/// The `name=~{value}` syntax in [`xfmt!`](crate::xfmt) uses this writer and falls back to `name=""` for empty values.
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut s = String::new();
+/// let mut writer = format_xml::EscapeAttrUnquoted::new(&mut s);
+/// write!(writer, "{}", "btn primary=\"1\"").unwrap();
+/// assert!(!writer.is_empty());
+/// assert_eq!(s, "btn&#32;primary&#61;&quot;1&quot;");
+/// ```
+pub struct EscapeAttrUnquoted<T> {
+	inner: T,
+	empty: bool,
+}
+impl<T: fmt::Write> EscapeAttrUnquoted<T> {
+	#[inline]
+	pub fn new(inner: T) -> EscapeAttrUnquoted<T> {
+		EscapeAttrUnquoted { inner, empty: true }
+	}
+	/// Returns true if nothing has been written yet.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.empty
+	}
+	/// Returns the inner writer.
+	#[inline]
+	pub fn into_inner(self) -> T {
+		self.inner
+	}
+}
+impl<T: fmt::Write> fmt::Write for EscapeAttrUnquoted<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		if !s.is_empty() {
+			self.empty = false;
+		}
+		let mut start = 0;
+		for (i, chr) in s.bytes().enumerate() {
+			let escaped = match chr {
+				b' ' => "&#32;",
+				b'\t' => "&#9;",
+				b'\n' => "&#10;",
+				b'\x0C' => "&#12;",
+				b'\r' => "&#13;",
+				b'"' => "&quot;",
+				b'\'' => "&#39;",
+				b'`' => "&#96;",
+				b'<' => "&lt;",
+				b'>' => "&gt;",
+				b'=' => "&#61;",
+				b'&' => "&amp;",
+				_ => continue,
+			};
+			if start < i {
+				self.inner.write_str(&s[start..i])?;
+			}
+			self.inner.write_str(escaped)?;
+			start = i + 1;
+		}
+		if start < s.len() {
+			self.inner.write_str(&s[start..])?;
+		}
+		Ok(())
+	}
+}
+
+/// Writes element and attribute names from formatted values.
+///
+/// Names have no character references, characters which cannot appear in a name are replaced with `_`.
+/// ASCII letters, digits, `-`, `_`, `.` and `:` are kept, as well as characters outside ASCII except whitespace and control characters.
+/// A name cannot start with a digit, `-`, `.` or a combining character, such names are prefixed with `_`.
+/// A name cannot be empty, check [`is_empty`](EscapeAttrName::is_empty) and write `_` instead.
+///
+/// The `<{name}>` and `{name}={value}` syntax in [`xfmt!`](crate::xfmt) uses this writer and falls back to `_` for empty names.
+/// Names ending in a formatted value such as `data-{key}` use [`suffix`](EscapeAttrName::suffix) instead.
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut s = String::new();
+/// let mut writer = format_xml::EscapeAttrName::new(&mut s);
+/// write!(writer, "{}", "data-a b=\"c\"").unwrap();
+/// assert!(!writer.is_empty());
+/// assert_eq!(s, "data-a_b__c_");
+///
+/// s.clear();
+/// write!(format_xml::EscapeAttrName::new(&mut s), "{}", "1st").unwrap();
+/// assert_eq!(s, "_1st");
+/// ```
+pub struct EscapeAttrName<T> {
+	inner: T,
+	empty: bool,
+	start: bool,
+}
+impl<T: fmt::Write> EscapeAttrName<T> {
+	#[inline]
+	pub fn new(inner: T) -> EscapeAttrName<T> {
+		EscapeAttrName { inner, empty: true, start: true }
+	}
+	/// Writes the remainder of a name after a literal prefix, the first character is not checked as the start of the name.
+	#[inline]
+	pub fn suffix(inner: T) -> EscapeAttrName<T> {
+		EscapeAttrName { inner, empty: true, start: false }
+	}
+	/// Returns true if nothing has been written yet.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.empty
+	}
+	/// Returns the inner writer.
+	#[inline]
+	pub fn into_inner(self) -> T {
+		self.inner
+	}
+}
+impl<T: fmt::Write> fmt::Write for EscapeAttrName<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		if !s.is_empty() {
+			self.empty = false;
+		}
+		if self.start {
+			if let Some(chr) = s.chars().next() {
+				self.start = false;
+				if let '0'..='9' | '-' | '.' | '\u{B7}' | '\u{300}'..='\u{36F}' | '\u{203F}'..='\u{2040}' = chr {
+					self.inner.write_str("_")?;
+				}
+			}
+		}
+		let mut start = 0;
+		for (i, chr) in s.char_indices() {
+			let valid = match chr {
+				'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' | ':' => true,
+				_ => !chr.is_ascii() && !chr.is_whitespace() && !chr.is_control(),
+			};
+			if valid {
+				continue;
+			}
+			if start < i {
+				self.inner.write_str(&s[start..i])?;
+			}
+			self.inner.write_str("_")?;
+			start = i + chr.len_utf8();
+		}
+		if start < s.len() {
+			self.inner.write_str(&s[start..])?;
+		}
+		Ok(())
+	}
+}
+
+/// Escapes the value for use in a text element.
+///
+/// Escapes `<`, `&`, `>` by streaming the value through [`EscapeText`].
+/// The result is not escaped again when used as `{value}` in text elements, in attribute values it is escaped again.
+///
+/// ```
+/// let value = "'quoted' <b>&</b>";
+/// assert_eq!(format_xml::escape_text(value).to_string(), "'quoted' &lt;b&gt;&amp;&lt;/b&gt;");
+/// ```
+///
+/// The width, fill, alignment and precision flags are honored, the value is buffered when any of them are present.
+/// The precision truncates the value before escaping, the width pads the escaped string:
+///
+/// ```
+/// assert_eq!(format!("[{:>12}]", format_xml::escape_text("a&b")), "[     a&amp;b]");
+/// assert_eq!(format!("[{:*^9.2}]", format_xml::escape_text("<b>")), "[**&lt;b**]");
+/// ```
+#[inline]
+pub fn escape_text<T: fmt::Display>(value: T) -> EscapedText<impl fmt::Display + fmt::Debug> {
+	EscapedText(crate::fmt(move |f| {
+		#[cfg(feature = "std")]
+		if f.width().is_some() || f.precision().is_some() {
+			return escape_padded(f, &value, |escaped, raw| fmt::Write::write_str(EscapeText::wrap(escaped), raw));
+		}
+		write!(EscapeText::wrap(f), "{}", value)
+	}))
+}
+
+/// Escapes the value for use in an attribute value.
+///
+/// Escapes `<`, `&`, `>`, `'`, `"`, `\n`, `\t`, `\r` by streaming the value through [`EscapeAttrValue`].
+/// This is a superset of [`escape_text`] and is safe to use in text elements as well.
+/// The result is not escaped again when used as `{value}` in text elements and attribute values.
+///
+/// ```
+/// let value = "'quoted' <b>&</b>";
+/// assert_eq!(format_xml::escape_attr(value).to_string(), "&apos;quoted&apos; &lt;b&gt;&amp;&lt;/b&gt;");
+/// ```
+#[inline]
+pub fn escape_attr<T: fmt::Display>(value: T) -> EscapedAttr<impl fmt::Display + fmt::Debug> {
+	EscapedAttr(crate::fmt(move |f| {
+		#[cfg(feature = "std")]
+		if f.width().is_some() || f.precision().is_some() {
+			return escape_padded(f, &value, |escaped, raw| fmt::Write::write_str(EscapeAttrValue::wrap(escaped), raw));
+		}
+		write!(EscapeAttrValue::wrap(f), "{}", value)
+	}))
+}
+
+// Buffers the escaped value to apply the formatting flags
+// The precision truncates the value before escaping, the width pads the escaped string
+#[cfg(feature = "std")]
+fn escape_padded(f: &mut fmt::Formatter, value: &dyn fmt::Display, escape: fn(&mut String, &str) -> fmt::Result) -> fmt::Result {
+	use fmt::Write;
+	let mut raw = String::new();
+	write!(raw, "{}", value)?;
+	if let Some((end, _)) = f.precision().and_then(|precision| raw.char_indices().nth(precision)) {
+		raw.truncate(end);
+	}
+	let mut escaped = String::with_capacity(raw.len());
+	escape(&mut escaped, &raw)?;
+	let pad = f.width().unwrap_or(0).saturating_sub(escaped.chars().count());
+	let (pre, post) = match f.align() {
+		Some(fmt::Alignment::Right) => (pad, 0),
+		Some(fmt::Alignment::Center) => (pad / 2, pad - pad / 2),
+		_ => (0, pad),
+	};
+	// The fill character is escaped as well
+	let mut fill = String::new();
+	escape(&mut fill, f.fill().encode_utf8(&mut [0; 4]))?;
+	for _ in 0..pre {
+		f.write_str(&fill)?;
+	}
+	f.write_str(&escaped)?;
+	for _ in 0..post {
+		f.write_str(&fill)?;
+	}
+	Ok(())
+}
+
+/// Appends the string escaped for text elements.
+///
+/// Escapes like [`EscapeText`] and reserves the space for the escaped string up front, see [`escaped_len_exact`].
+///
+/// ```
+/// let mut html = String::from("<p>");
+/// format_xml::escape_into(&mut html, "Tom & Jerry <3");
+/// html.push_str("</p>");
+/// assert_eq!(html, "<p>Tom &amp; Jerry &lt;3</p>");
+/// ```
+#[cfg(feature = "std")]
+#[inline]
+pub fn escape_into(dst: &mut String, src: &str) {
+	// Text elements escape fewer characters than attribute values, the exact length for attribute values is an upper bound
+	dst.reserve(escaped_len_exact(src));
+	let _ = fmt::Write::write_str(EscapeText::wrap(dst), src);
+}
+
+/// Appends the string escaped for attribute values.
+///
+/// Escapes like [`EscapeAttrValue`] and reserves the space for the escaped string up front, see [`escaped_len_exact`].
+///
+/// ```
+/// let mut html = String::from("<p title=\"");
+/// format_xml::escape_attr_into(&mut html, "\"Hi\" & 'bye'");
+/// html.push_str("\"></p>");
+/// assert_eq!(html, "<p title=\"&quot;Hi&quot; &amp; &apos;bye&apos;\"></p>");
+/// ```
+#[cfg(feature = "std")]
+#[inline]
+pub fn escape_attr_into(dst: &mut String, src: &str) {
+	dst.reserve(escaped_len_exact(src));
+	let _ = fmt::Write::write_str(EscapeAttrValue::wrap(dst), src);
+}
+
+/// Escapes the string for text elements, borrowing it if nothing needs escaping.
+///
+/// Escapes like [`EscapeText`], the string is scanned once when it is returned as-is.
+///
+/// ```
+/// use std::borrow::Cow;
+///
+/// assert!(matches!(format_xml::escape_cow("plain text"), Cow::Borrowed("plain text")));
+/// assert_eq!(format_xml::escape_cow("a < b"), "a &lt; b");
+/// ```
+#[cfg(feature = "std")]
+#[inline]
+pub fn escape_cow(s: &str) -> std::borrow::Cow<'_, str> {
+	escape_cow_with(s, split_text, escape_into)
+}
+
+/// Escapes the string for attribute values, borrowing it if nothing needs escaping.
+///
+/// Escapes like [`EscapeAttrValue`], the string is scanned once when it is returned as-is.
+///
+/// ```
+/// use std::borrow::Cow;
+///
+/// assert!(matches!(format_xml::escape_attr_cow("plain text"), Cow::Borrowed("plain text")));
+/// assert_eq!(format_xml::escape_attr_cow("\"a\"\n"), "&quot;a&quot;&#10;");
+/// ```
+#[cfg(feature = "std")]
+#[inline]
+pub fn escape_attr_cow(s: &str) -> std::borrow::Cow<'_, str> {
+	escape_cow_with(s, split_attr, escape_attr_into)
+}
+
+#[cfg(feature = "std")]
+fn escape_cow_with<'a>(s: &'a str, split: fn(&[u8]) -> usize, escape: fn(&mut String, &str)) -> std::borrow::Cow<'a, str> {
+	let i = split(s.as_bytes());
+	if i == s.len() {
+		return std::borrow::Cow::Borrowed(s);
+	}
+	let mut escaped = String::with_capacity(i + escaped_len_exact(&s[i..]));
+	escaped.push_str(&s[..i]);
+	escape(&mut escaped, &s[i..]);
+	std::borrow::Cow::Owned(escaped)
+}
+
+/// Escapes the value for use in a string literal inside a `<script>` element.
+///
+/// Streams the value through [`EscapeJs`], the result contains no markup characters and is not escaped again when used as `{value}` in text elements such as `<script>`.
+/// In attribute values such as event handlers the result is escaped for the attribute as well.
+///
+/// ```
+/// let name = "</script><script>alert(1)</script>";
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<script>"var name = \""{format_xml::escape_js(name)}"\";"</script>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<script>var name = "\u003C/script\u003E\u003Cscript\u003Ealert(1)\u003C/script\u003E";</script>"#);
+/// ```
+///
+/// The resulting string is `<script>var name = "\u003C/script\u003E\u003Cscript\u003Ealert(1)\u003C/script\u003E";</script>`.
+#[inline]
+pub fn escape_js<T: fmt::Display>(value: T) -> EscapedText<impl fmt::Display + fmt::Debug> {
+	EscapedText(crate::fmt(move |f| write!(EscapeJs::wrap(f), "{}", value)))
+}
+
+/// Escapes the value for use in a `style` attribute or `<style>` element.
+///
+/// Streams the value through [`EscapeCss`].
+/// The output contains no markup characters, in templates it is escaped again for the surrounding text or attribute without changing the result.
+///
+/// ```
+/// let color = "red; background: url(x)";
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<p style={format_args!("color: {}", format_xml::escape_css(color))}>"Hello"</p>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<p style="color: red\3B  background\3A  url\28 x\29 ">Hello</p>"#);
+/// ```
+///
+/// The resulting string is `<p style="color: red\3B  background\3A  url\28 x\29 ">Hello</p>`.
+#[inline]
+pub fn escape_css<T: fmt::Display>(value: T) -> impl fmt::Display + fmt::Debug {
+	crate::fmt(move |f| write!(EscapeCss::wrap(f), "{}", value))
+}
+
+/// Escapes the value for use in a JSON string.
+///
+/// Streams the value through [`EscapeJsonString`], the surrounding quotes are not written.
+/// In templates the result is escaped again for the surrounding text or attribute, eg. to embed JSON in `data-*` attributes.
+///
+/// ```
+/// let name = "Tom \"&\" Jerry";
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<div data-user={format_args!("{{\"name\":\"{}\"}}", format_xml::escape_json_str(name))}></div>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<div data-user="{&quot;name&quot;:&quot;Tom \&quot;&amp;\&quot; Jerry&quot;}"></div>"#);
+/// ```
+///
+/// The resulting string is `<div data-user="{&quot;name&quot;:&quot;Tom \&quot;&amp;\&quot; Jerry&quot;}"></div>`.
+#[inline]
+pub fn escape_json_str<T: fmt::Display>(value: T) -> impl fmt::Display + fmt::Debug {
+	use fmt::Write;
+	crate::fmt(move |f| write!(EscapeJsonString::new(f), "{}", value))
+}
+
+/// Escapes the value for use in a comment.
+///
+/// Removes `--` by streaming the value through the comment escaper, also when it is split across multiple writes.
+/// A trailing `-` is followed by a space so the value can be placed right before `-->`.
+///
+/// ```
+/// assert_eq!(format_xml::escape_comment("a--b").to_string(), "ab");
+/// assert_eq!(format_xml::escape_comment("a---b").to_string(), "a-b");
+/// assert_eq!(format_xml::escape_comment("trailing-").to_string(), "trailing- ");
+/// assert_eq!(format_xml::escape_comment("<b>&</b>").to_string(), "<b>&</b>");
+/// ```
+#[inline]
+pub fn escape_comment<T: fmt::Display>(value: T) -> impl fmt::Display + fmt::Debug {
+	crate::fmt(move |f| {
+		let mut writer = EscapeComment::wrap(f);
+		write!(writer, "{}", value)?;
+		writer.finish()?;
+		Ok(())
+	})
+}
+
+/// Escapes the value for use in a CDATA section.
+///
+/// Splits `]]>` by ending the CDATA section and starting a new one.
+///
+/// ```
+/// assert_eq!(format_xml::escape_cdata("a]]>b").to_string(), "a]]]]><![CDATA[>b");
+/// assert_eq!(format_xml::escape_cdata("]]]>").to_string(), "]]]]]><![CDATA[>");
+/// assert_eq!(format_xml::escape_cdata("<b>&</b>").to_string(), "<b>&</b>");
+/// ```
+#[inline]
+pub fn escape_cdata<T: fmt::Display>(value: T) -> impl fmt::Display + fmt::Debug {
+	crate::fmt(move |f| write!(EscapeCharData::wrap(f), "{}", value))
+}
+
+/// Escapes the value with all five special characters regardless of context.
+///
+/// Streams the value through [`EscapeFull`], the result is not escaped again when used as `{value}` in text elements and attribute values.
+///
+/// ```
+/// let value = "\"it's\" <b>&</b>";
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<p>{value}</p>
+/// 	<p>{format_xml::escape_full(value)}</p>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, "<p>\"it's\" &lt;b&gt;&amp;&lt;/b&gt;</p><p>&quot;it&apos;s&quot; &lt;b&gt;&amp;&lt;/b&gt;</p>");
+/// ```
+///
+/// The resulting string is `<p>"it's" &lt;b&gt;&amp;&lt;/b&gt;</p><p>&quot;it&apos;s&quot; &lt;b&gt;&amp;&lt;/b&gt;</p>`.
+#[inline]
+pub fn escape_full<T: fmt::Display>(value: T) -> EscapedAttr<impl fmt::Display + fmt::Debug> {
+	EscapedAttr(crate::fmt(move |f| write!(EscapeFull::wrap(f), "{}", value)))
+}
+
+/// Escapes the value for use in a text element with ASCII-only output.
+///
+/// Streams the value through [`EscapeTextAscii`], the result is not escaped again when used as `{value}` in text elements.
+/// Quotes are not escaped, in attribute values the result is escaped again.
+///
+/// ```
+/// let name = "Zoë & Chloé";
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<p>"Hello "{format_xml::escape_ascii(name)}</p>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, "<p>Hello Zo&#xEB; &amp; Chlo&#xE9;</p>");
+/// ```
+///
+/// The resulting string is `<p>Hello Zo&#xEB; &amp; Chlo&#xE9;</p>`.
+#[inline]
+pub fn escape_ascii<T: fmt::Display>(value: T) -> EscapedText<impl fmt::Display + fmt::Debug> {
+	EscapedText(crate::fmt(move |f| write!(EscapeTextAscii::wrap(f), "{}", value)))
+}
+
+/// Escapes the value for use in a text element with a table of additional replacements.
+///
+/// Streams the value through [`EscapeCustom`], the result is not escaped again when used as `{value}` in text elements.
+/// In attribute values the result is escaped again.
+///
+/// ```
+/// const TABLE: &[(char, &str)] = &[('\u{A0}', "&#160;"), ('\u{AD}', "")];
+///
+/// let name = "Tom\u{A0}&\u{A0}Jer\u{AD}ry";
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<p>{format_xml::escape_with(TABLE, name)}</p>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, "<p>Tom&#160;&amp;&#160;Jerry</p>");
+/// ```
+///
+/// The resulting string is `<p>Tom&#160;&amp;&#160;Jerry</p>`.
+#[inline]
+pub fn escape_with<'t, T: fmt::Display + 't>(table: &'t [(char, &'t str)], value: T) -> EscapedText<impl fmt::Display + fmt::Debug + 't> {
+	EscapedText(crate::fmt(move |f| write!(EscapeCustom::wrap(f, table), "{}", value)))
+}
+
+/// Escapes every item of the iterable for use in a text element.
+///
+/// Each item is streamed through [`escape_text`], compose with [`join`](crate::join) to escape only the values and not the separator.
+///
+/// ```
+/// let names = ["Tom & Jerry", "<Bob>"];
+/// let list = format_xml::join(" & ", format_xml::escape_each(&names));
+/// assert_eq!(list.to_string(), "Tom &amp; Jerry & &lt;Bob&gt;");
+/// ```
+#[inline]
+pub fn escape_each<I>(iter: I) -> impl Iterator<Item = EscapedText<impl fmt::Display + fmt::Debug>> + Clone
+	where I: IntoIterator,
+		I::Item: fmt::Display,
+		I::IntoIter: Clone
+{
+	iter.into_iter().map(escape_text)
+}
+
+/// Escapes every item of the iterable for use in an attribute value.
+///
+/// Each item is streamed through [`escape_attr`].
+///
+/// ```
+/// let classes = ["a\"b", "c'd"];
+/// let value = format_xml::join(" ", format_xml::escape_attr_each(&classes));
+/// assert_eq!(value.to_string(), "a&quot;b c&apos;d");
+/// ```
+#[inline]
+pub fn escape_attr_each<I>(iter: I) -> impl Iterator<Item = EscapedAttr<impl fmt::Display + fmt::Debug>> + Clone
+	where I: IntoIterator,
+		I::Item: fmt::Display,
+		I::IntoIter: Clone
+{
+	iter.into_iter().map(escape_attr)
+}
+
+/// Normalizes line endings when it appears in the formatted string.
+///
+/// Converts `\r\n` and lone `\r` to `\n`, or when preserving line endings writes every `\r` as `&#13;` instead.
+/// A `\r\n` pair split across multiple `write_str` calls is handled correctly.
+///
+/// Layer [`EscapeText`] or [`EscapeAttrValue`] on top to escape the text as well.
+/// Templates normalize the line endings of their values with the `newlines:` and `preserve_newlines:` policies, see [`xfmt!`](crate::xfmt).
+///
+/// # Examples
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut buf = String::new();
+/// let mut writer = format_xml::NormalizeNewlines::new(&mut buf);
+/// write!(writer, "{}", "a\r\nb\rc\n").unwrap();
+/// assert_eq!(buf, "a\nb\nc\n");
+///
+/// let mut buf = String::new();
+/// let mut writer = format_xml::NormalizeNewlines::preserve(&mut buf);
+/// write!(writer, "{}", "a\r\nb\rc\n").unwrap();
+/// assert_eq!(buf, "a&#13;\nb&#13;c\n");
+/// ```
+pub struct NormalizeNewlines<T> {
+	inner: T,
+	preserve: bool,
+	cr: bool,
+}
+impl<T: fmt::Write> NormalizeNewlines<T> {
+	/// Converts `\r\n` and `\r` to `\n`.
+	#[inline]
+	pub fn new(inner: T) -> NormalizeNewlines<T> {
+		NormalizeNewlines { inner, preserve: false, cr: false }
+	}
+	/// Writes `\r` as `&#13;` so xml parsers do not normalize it away.
+	#[inline]
+	pub fn preserve(inner: T) -> NormalizeNewlines<T> {
+		NormalizeNewlines { inner, preserve: true, cr: false }
+	}
+	/// Returns the inner writer.
+	#[inline]
+	pub fn into_inner(self) -> T {
+		self.inner
+	}
+}
+impl<T: fmt::Write> fmt::Write for NormalizeNewlines<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let mut bytes = s.as_bytes();
+		// Skip the `\n` of a `\r\n` pair split across calls
+		if let Some(&chr) = bytes.first() {
+			if self.cr && chr == b'\n' {
+				bytes = &bytes[1..];
+			}
+			self.cr = false;
+		}
+		while !bytes.is_empty() {
+			let i = bytes.iter().position(|&chr| chr == b'\r').unwrap_or(bytes.len());
+			let (prefix, next) = split_ascii(bytes, i);
+			if !prefix.is_empty() {
+				self.inner.write_str(prefix)?;
+			}
+			let rest = match next {
+				Some((_, rest)) => rest,
+				None => break,
+			};
+
+			if self.preserve {
+				self.inner.write_str(crate::obfstr!("&#13;"))?;
+				bytes = rest;
+			}
+			else {
+				self.inner.write_str("\n")?;
+				match rest.first() {
+					Some(&b'\n') => bytes = &rest[1..],
+					Some(_) => bytes = rest,
+					None => {
+						self.cr = true;
+						break;
+					},
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Escapes `--` in comments by not writing it at all.
+///
+/// A trailing `-` is held back until the next write as it may be the start of `--` split across `write_str` calls.
+/// Call [`finish`](EscapeComment::finish) to write it when done, followed by a space as a comment must not end with `-` before `-->`.
+#[doc(hidden)]
+pub struct EscapeComment<T> {
+	inner: T,
+	dash: bool,
+}
+impl<T: fmt::Write> EscapeComment<T> {
+	#[inline]
+	pub fn new(inner: T) -> EscapeComment<T> {
+		EscapeComment { inner, dash: false }
+	}
+	/// Writes the held back `-` if any followed by a space and returns the inner writer.
+	///
+	/// The output never ends with `-` and can be followed by `-->` directly.
+	#[inline]
+	pub fn finish(mut self) -> Result<T, fmt::Error> {
+		if self.dash {
+			self.inner.write_str("- ")?;
+		}
+		Ok(self.inner)
+	}
+	// Writes the held back `-` if any and closes the comment with ` -->`
+	#[doc(hidden)]
+	#[inline]
+	pub fn close(mut self) -> fmt::Result {
+		if self.dash {
+			self.inner.write_str("-")?;
+		}
+		self.inner.write_str(" -->")
+	}
+}
+impl<'a, T: ?Sized + fmt::Write> EscapeComment<&'a mut T> {
+	#[inline]
+	pub fn wrap(v: &'a mut T) -> EscapeComment<&'a mut T> {
+		EscapeComment::new(v)
+	}
+}
+// Forward Write calls
+#[doc(hidden)]
+impl<T: fmt::Write> EscapeComment<T> {
+	#[inline]
+	pub fn write_str(&mut self, s: &str) -> fmt::Result {
+		<Self as fmt::Write>::write_str(self, s)
+	}
+	#[inline]
+	pub fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
+		<Self as fmt::Write>::write_fmt(self, args)
+	}
+}
+impl<T: fmt::Write> fmt::Write for EscapeComment<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let bytes = s.as_bytes();
+		let mut i = 0;
+		while i < bytes.len() {
+			if self.dash {
+				self.dash = false;
+				if bytes[i] == b'-' {
+					i += 1;
+					continue;
+				}
+				self.inner.write_str("-")?;
+			}
+			let end = s[i..].find('-').map(|j| i + j).unwrap_or(s.len());
+			if end > i {
+				self.inner.write_str(&s[i..end])?;
+			}
+			self.dash = end < s.len();
+			i = end + self.dash as usize;
+		}
+		Ok(())
+	}
+	fn write_char(&mut self, chr: char) -> fmt::Result {
+		if self.dash {
+			self.dash = false;
+			if chr == '-' {
+				return Ok(());
+			}
+			self.inner.write_str("-")?;
+		}
+		if chr == '-' {
+			self.dash = true;
+			return Ok(());
+		}
+		self.inner.write_char(chr)
+	}
+}
+
+/// Escapes `]]>` in CDATA sections.
+///
+/// Ends the CDATA section after `]]` and reopens it before `>`, also when `]]>` is split across `write_str` calls.
+/// Nothing is held back, no flushing is required when done.
+#[doc(hidden)]
+pub struct EscapeCharData<T> {
+	inner: T,
+	brackets: u8,
+}
+impl<T: fmt::Write> EscapeCharData<T> {
+	#[inline]
+	pub fn new(inner: T) -> EscapeCharData<T> {
+		EscapeCharData { inner, brackets: 0 }
+	}
+	/// Returns the inner writer.
+	#[inline]
+	pub fn into_inner(self) -> T {
+		self.inner
+	}
+}
+impl<'a, T: ?Sized + fmt::Write> EscapeCharData<&'a mut T> {
+	#[inline]
+	pub fn wrap(v: &'a mut T) -> EscapeCharData<&'a mut T> {
+		EscapeCharData::new(v)
+	}
+}
+// Forward Write calls
+#[doc(hidden)]
+impl<T: fmt::Write> EscapeCharData<T> {
+	#[inline]
+	pub fn write_str(&mut self, s: &str) -> fmt::Result {
+		<Self as fmt::Write>::write_str(self, s)
+	}
+	#[inline]
+	pub fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
+		<Self as fmt::Write>::write_fmt(self, args)
+	}
+}
+impl<T: fmt::Write> fmt::Write for EscapeCharData<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let mut start = 0;
+		for (i, chr) in s.bytes().enumerate() {
+			match chr {
+				b']' => self.brackets = u8::min(self.brackets + 1, 2),
+				b'>' if self.brackets == 2 => {
+					self.inner.write_str(&s[start..i])?;
+					self.inner.write_str(crate::obfstr!("]]><![CDATA["))?;
+					start = i;
+					self.brackets = 0;
+				},
+				_ => self.brackets = 0,
+			}
+		}
+		if start < s.len() {
+			self.inner.write_str(&s[start..])?;
+		}
+		Ok(())
+	}
+	fn write_char(&mut self, chr: char) -> fmt::Result {
+		match chr {
+			']' => self.brackets = u8::min(self.brackets + 1, 2),
+			'>' if self.brackets == 2 => {
+				self.inner.write_str(crate::obfstr!("]]><![CDATA["))?;
+				self.brackets = 0;
+			},
+			_ => self.brackets = 0,
+		}
+		self.inner.write_char(chr)
+	}
+}
+
+/// Escapes `?>` in processing instructions.
+///
+/// Writes a space between `?` and `>`, also when `?>` is split across `write_str` calls.
+/// Nothing is held back, no flushing is required when done.
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut s = String::new();
+/// let mut writer = format_xml::EscapePi::wrap(&mut s);
+/// write!(writer, "{}", "a?>b?").unwrap();
+/// write!(writer, "{}", ">").unwrap();
+/// assert_eq!(s, "a? >b? >");
+/// ```
+pub struct EscapePi<T> {
+	inner: T,
+	question: bool,
+}
+impl<T: fmt::Write> EscapePi<T> {
+	#[inline]
+	pub fn new(inner: T) -> EscapePi<T> {
+		EscapePi { inner, question: false }
+	}
+	/// Returns the inner writer.
+	#[inline]
+	pub fn into_inner(self) -> T {
+		self.inner
+	}
+}
+impl<'a, T: ?Sized + fmt::Write> EscapePi<&'a mut T> {
+	#[inline]
+	pub fn wrap(v: &'a mut T) -> EscapePi<&'a mut T> {
+		EscapePi::new(v)
+	}
+}
+// Forward Write calls
+#[doc(hidden)]
+impl<T: fmt::Write> EscapePi<T> {
+	#[inline]
+	pub fn write_str(&mut self, s: &str) -> fmt::Result {
+		<Self as fmt::Write>::write_str(self, s)
+	}
+	#[inline]
+	pub fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
+		<Self as fmt::Write>::write_fmt(self, args)
+	}
+}
+impl<T: fmt::Write> fmt::Write for EscapePi<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let mut start = 0;
+		for (i, chr) in s.bytes().enumerate() {
+			if chr == b'>' && self.question {
+				self.inner.write_str(&s[start..i])?;
+				self.inner.write_str(" ")?;
+				start = i;
+			}
+			self.question = chr == b'?';
+		}
+		if start < s.len() {
+			self.inner.write_str(&s[start..])?;
+		}
+		Ok(())
+	}
+}
+
+/// Escapes `"` in the quoted public and system identifiers of `<!DOCTYPE>`.
+///
+/// References are not recognized in these literals, the double quote is written percent-encoded as `%22`.
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut s = String::new();
+/// write!(format_xml::EscapeDoctypeLiteral::wrap(&mut s), "{}", "a \"b\" & <c>").unwrap();
+/// assert_eq!(s, "a %22b%22 & <c>");
+/// ```
+#[repr(transparent)]
+pub struct EscapeDoctypeLiteral<T: ?Sized> {
+	inner: T,
+}
+impl<T: ?Sized + fmt::Write> EscapeDoctypeLiteral<T> {
+	#[inline]
+	pub fn wrap(v: &mut T) -> &mut EscapeDoctypeLiteral<T> {
+		unsafe { mem::transmute(v) }
+	}
+}
+impl<T> From<T> for EscapeDoctypeLiteral<T> {
+	#[inline]
+	fn from(inner: T) -> Self {
+		EscapeDoctypeLiteral { inner }
+	}
+}
+// Forward Write calls
+#[doc(hidden)]
+impl<T: ?Sized + fmt::Write> EscapeDoctypeLiteral<T> {
+	#[inline]
+	pub fn write_str(&mut self, s: &str) -> fmt::Result {
+		<Self as fmt::Write>::write_str(self, s)
+	}
+	#[inline]
+	pub fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
+		<Self as fmt::Write>::write_fmt(self, args)
+	}
+}
+impl<T: ?Sized + fmt::Write> fmt::Write for EscapeDoctypeLiteral<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let mut start = 0;
+		for (i, chr) in s.bytes().enumerate() {
+			if chr == b'"' {
+				self.inner.write_str(&s[start..i])?;
+				self.inner.write_str("%22")?;
+				start = i + 1;
+			}
+		}
+		if start < s.len() {
+			self.inner.write_str(&s[start..])?;
+		}
+		Ok(())
+	}
+}
+
+#[test]
+fn test_comment() {
+	#[track_caller]
+	fn check(input: &str, escaped: &str) {
+		let mut writer = EscapeComment::new(String::new());
+		writer.write_str(input).unwrap();
+		assert_eq!(writer.finish().unwrap(), escaped);
+
+		// Byte at a time
+		let mut writer = EscapeComment::new(String::new());
+		for i in 0..input.len() {
+			writer.write_str(&input[i..i + 1]).unwrap();
+		}
+		assert_eq!(writer.finish().unwrap(), escaped);
+	}
+
+	check("", "");
+	check(" ", " ");
+	check("--", "");
+	check(" --", " ");
+	check("-- ", " ");
+	check("-", "- ");
+	check("---", "- ");
+	check("a-b--c", "a-bc");
+	check("a-", "a- ");
+	check("a--", "a");
+
+	let dash = '-';
+	let mut writer = EscapeComment::new(String::new());
+	write!(writer, "{}{}", dash, dash).unwrap();
+	write!(writer, "a{}", dash).unwrap();
+	writer.write_str("").unwrap();
+	write!(writer, "{}b-", dash).unwrap();
+	assert_eq!(writer.finish().unwrap(), "ab- ");
+
+	struct Dashes;
+	impl fmt::Display for Dashes {
+		fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+			for _ in 0..5 {
+				f.write_str("-")?;
+			}
+			Ok(())
+		}
+	}
+	assert_eq!(crate::xfmt!{<!-- {Dashes} -->}.to_string(), "<!-- - -->");
+	assert_eq!(crate::xfmt!{<!-- "-" {"-"} -->}.to_string(), "<!--  -->");
+
+	// Never ends with a dash before the closing `-->`
+	for value in ["", "-", "--", "---", "a-", "a--"] {
+		assert!(!escape_comment(value).to_string().ends_with('-'));
+		assert!(!crate::xfmt!{<!-- {value} -->}.to_string().contains("--->"));
+	}
+	assert_eq!(format!("<!--{}-->", escape_comment("-")), "<!--- -->");
+	assert_eq!(crate::xfmt!{<!-- "a" {"-"} -->}.to_string(), "<!-- a- -->");
+	assert_eq!(crate::xfmt!{<!-- {""} -->}.to_string(), "<!--  -->");
+
+	// Markup inside comments is text, the values are not escaped
+	let value = "a -- b < c";
+	let disabled = crate::xfmt! { <ul><!-- <li class="old" data-note={value}>{value}</li> --></ul> };
+	assert_eq!(disabled.to_string(), r#"<ul><!-- <li class="old" data-note="a  b < c">a  b < c</li> --></ul>"#);
+	let doc = disabled.to_string();
+	let doc = roxmltree::Document::parse(&doc).unwrap();
+	assert!(doc.root_element().first_child().unwrap().is_comment());
 }
 
 #[test]
 fn test_cdata() {
 	#[track_caller]
 	fn check(input: &str, escaped: &str) {
-		let mut writer = EscapeCharData { inner: String::new() };
+		let mut writer = EscapeCharData::new(String::new());
 		writer.write_str(input).unwrap();
-		assert_eq!(writer.inner, escaped);
+		assert_eq!(writer.into_inner(), escaped);
+
+		// Byte at a time
+		let mut writer = EscapeCharData::new(String::new());
+		for i in 0..input.len() {
+			writer.write_str(&input[i..i + 1]).unwrap();
+		}
+		assert_eq!(writer.into_inner(), escaped);
 	}
 
 	check("", "");
@@ -322,4 +2355,675 @@ fn test_cdata() {
 	check("]]>", "]]]]><![CDATA[>");
 	check(" ]]>", " ]]]]><![CDATA[>");
 	check("]]> ", "]]]]><![CDATA[> ");
+	check("]]]>", "]]]]]><![CDATA[>");
+	check("] ]>]>", "] ]>]>");
+	check("]]>]]>", "]]]]><![CDATA[>]]]]><![CDATA[>");
+
+	let mut writer = EscapeCharData::new(String::new());
+	for s in ["a]", "", "]", ">b]", "]", "c>", "]]", ">"] {
+		writer.write_str(s).unwrap();
+	}
+	assert_eq!(writer.into_inner(), "a]]]]><![CDATA[>b]]c>]]]]><![CDATA[>");
+
+	let (a, b) = ("]]", ">");
+	assert_eq!(crate::xfmt!{<![CDATA[{a}{b}]]>}.to_string(), "<![CDATA[]]]]><![CDATA[>]]>");
+
+	// Markup inside CDATA is text, the values are not escaped
+	let value = "1 < 2 ]]>";
+	let item = crate::xfmt! {
+		<description><![CDATA[<p class="x">"Example: "<b title={value}>{value}</b><br /></p>]]></description>
+	};
+	assert_eq!(item.to_string(), r#"<description><![CDATA[<p class="x">Example: <b title="1 < 2 ]]]]><![CDATA[>">1 < 2 ]]]]><![CDATA[></b><br /></p>]]></description>"#);
+	let doc = item.to_string();
+	let doc = roxmltree::Document::parse(&doc).unwrap();
+	assert_eq!(doc.root_element().text(), Some(r#"<p class="x">Example: <b title="1 < 2 ]]>">1 < 2 ]]></b><br /></p>"#));
+}
+
+#[test]
+fn test_newlines() {
+	use fmt::Write;
+
+	#[track_caller]
+	fn check(input: &[&str], normalized: &str, preserved: &str) {
+		let mut writer = NormalizeNewlines::new(String::new());
+		let mut preserve = NormalizeNewlines::preserve(String::new());
+		for s in input {
+			writer.write_str(s).unwrap();
+			preserve.write_str(s).unwrap();
+		}
+		assert_eq!(writer.inner, normalized);
+		assert_eq!(preserve.inner, preserved);
+	}
+
+	check(&[""], "", "");
+	check(&["\r"], "\n", "&#13;");
+	check(&["a\r"], "a\n", "a&#13;");
+	check(&["a\r\nb"], "a\nb", "a&#13;\nb");
+	check(&["a\r", "\nb"], "a\nb", "a&#13;\nb");
+	check(&["a\r", "", "\nb"], "a\nb", "a&#13;\nb");
+	check(&["\r\r\n\n"], "\n\n\n", "&#13;&#13;\n\n");
+	check(&["\r", "\r", "\n"], "\n\n", "&#13;&#13;\n");
+}
+
+#[test]
+fn test_no_escape() {
+	// Autoref specialization requires the concrete type at the call site
+	macro_rules! check {
+		($value:expr, $needs_escape:expr) => {{
+			#[allow(unused_imports)]
+			use crate::{EscapeKind as _, NoEscapeKind as _, EscapeAttrKind as _, NoEscapeAttrKind as _};
+			let value = $value;
+			assert_eq!((&EscapeSpec(&value)).__xfmt_escape(), $needs_escape);
+			assert_eq!((&EscapeSpec(&value)).__xfmt_escape_attr(), $needs_escape);
+
+			let mut slow = String::new();
+			write!(EscapeText::wrap(&mut slow), "{}", value).unwrap();
+			assert_eq!(crate::xfmt!{{value}}.to_string(), slow);
+
+			let mut slow = String::new();
+			write!(EscapeAttrValue::wrap(&mut slow), "{}", value).unwrap();
+			assert_eq!(crate::xfmt!{<a b={value}/>}.to_string(), format!("<a b=\"{}\" />", slow));
+		}};
+	}
+
+	check!(42, false);
+	check!(-1i8, false);
+	check!(u128::MAX, false);
+	check!(1.5f32, false);
+	check!(f64::NAN, false);
+	check!(true, false);
+	check!(&7usize, false);
+	check!('<', true);
+	check!('\'', true);
+	check!('\n', true);
+	check!('a', false);
+	check!('é', false);
+	check!(&'a', true);
+	check!("<&>", true);
+	check!(String::from("a"), true);
+
+	// Trusted markup is written as is in text elements only
+	let markup = crate::slot("\" onmouseover=\"alert(1)");
+	assert_eq!(crate::xfmt!{<a title={markup}>{markup}</a>}.to_string(), "<a title=\"&quot; onmouseover=&quot;alert(1)\">\" onmouseover=\"alert(1)</a>");
+
+	// Escaping helpers are not escaped again where their escaping is sufficient for the location
+	let value = "\"a\" & <b>";
+	let text = escape_text(value);
+	assert_eq!(crate::xfmt!{<a title={text}>{text}</a>}.to_string(), "<a title=\"&quot;a&quot; &amp;amp; &amp;lt;b&amp;gt;\">\"a\" &amp; &lt;b&gt;</a>");
+	let attr = escape_attr(value);
+	assert_eq!(crate::xfmt!{<a title={attr}>{attr}</a>}.to_string(), "<a title=\"&quot;a&quot; &amp; &lt;b&gt;\">&quot;a&quot; &amp; &lt;b&gt;</a>");
+	let full = escape_full(value);
+	assert_eq!(crate::xfmt!{<a title={full}>{full}</a>}.to_string(), "<a title=\"&quot;a&quot; &amp; &lt;b&gt;\">&quot;a&quot; &amp; &lt;b&gt;</a>");
+	let custom = escape_with(&[('a', "&#97;")], value);
+	assert_eq!(crate::xfmt!{<a title={custom}>{custom}</a>}.to_string(), "<a title=\"&quot;&amp;#97;&quot; &amp;amp; &amp;lt;b&amp;gt;\">\"&#97;\" &amp; &lt;b&gt;</a>");
+}
+
+#[test]
+fn test_escaped_len() {
+	for s in ["", "hello", "<", "&&", "'\"", "<script>alert('&')</script>", "🦀 & 🦀", "\"quote\"", "a\r\n\tb"] {
+		let mut attr = String::new();
+		EscapeAttrValue::wrap(&mut attr).write_str(s).unwrap();
+		let mut text = String::new();
+		EscapeText::wrap(&mut text).write_str(s).unwrap();
+		assert_eq!(escaped_len_exact(s), attr.len(), "{:?}", s);
+		assert!(escaped_len_bound(s) >= attr.len(), "{:?}", s);
+		assert!(escaped_len_bound(s) >= text.len(), "{:?}", s);
+	}
+}
+
+#[test]
+fn test_attr_unquoted() {
+	#[track_caller]
+	fn check(value: &str, expected: &str) {
+		assert_eq!(crate::xfmt!{<a class=~{value} href="/">}.to_string(), expected);
+	}
+	check("btn", "<a class=btn href=\"/\">");
+	check("", "<a class=\"\" href=\"/\">");
+	check("btn primary", "<a class=btn&#32;primary href=\"/\">");
+	check("a\tb\nc\rd\x0Ce", "<a class=a&#9;b&#10;c&#13;d&#12;e href=\"/\">");
+	check("\"'`<>=&", "<a class=&quot;&#39;&#96;&lt;&gt;&#61;&amp; href=\"/\">");
+	check("ünï", "<a class=ünï href=\"/\">");
+}
+
+#[test]
+fn test_scan() {
+	let mut text = [b'a'; 40];
+	assert_eq!(split_text(&text), 40);
+	assert_eq!(split_attr(&text), 40);
+	for i in 0..text.len() {
+		for &chr in b"<&>'\"\x80\xbc\xa6" {
+			text[i] = chr;
+			let special = b"<&>".contains(&chr);
+			assert_eq!(split_text(&text), if special { i } else { 40 });
+			let special = b"<&>'\"".contains(&chr);
+			assert_eq!(split_attr(&text), if special { i } else { 40 });
+			assert_eq!(split_text(&text[i + 1..]), 40 - i - 1);
+		}
+		text[i] = b'a';
+	}
+	// Only the first match counts
+	assert_eq!(split_text(b"0123456>89abc<ef"), 7);
+	assert_eq!(split_attr(b"0123'5\"7"), 4);
+}
+
+#[test]
+fn test_ascii() {
+	#[track_caller]
+	fn check(input: &[&str], escaped: &str) {
+		let mut s = String::new();
+		for chunk in input {
+			EscapeTextAscii::wrap(&mut s).write_str(chunk).unwrap();
+		}
+		assert_eq!(s, escaped);
+	}
+
+	check(&[""], "");
+	check(&["plain 'text'"], "plain 'text'");
+	check(&["é"], "&#xE9;");
+	check(&["a", "é", "b"], "a&#xE9;b");
+	check(&["€", "😀", "<"], "&#x20AC;&#x1F600;&lt;");
+	check(&["x\u{7F}\u{80}\u{7FF}\u{800}\u{FFFF}\u{10000}\u{10FFFF}"], "x\u{7F}&#x80;&#x7FF;&#x800;&#xFFFF;&#x10000;&#x10FFFF;");
+
+	struct Chars(&'static str);
+	impl fmt::Display for Chars {
+		fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+			for chr in self.0.chars() {
+				fmt::Display::fmt(&chr, f)?;
+			}
+			Ok(())
+		}
+	}
+	assert_eq!(escape_ascii(Chars("日本&語")).to_string(), "&#x65E5;&#x672C;&amp;&#x8A9E;");
+
+	// The quotes are escaped again in attribute values
+	let value = "\" onmouseover=\"alert(1)";
+	assert_eq!(crate::xfmt!{<a title={escape_ascii(value)}>{escape_ascii(value)}</a>}.to_string(), "<a title=\"&quot; onmouseover=&quot;alert(1)\">\" onmouseover=\"alert(1)</a>");
+}
+
+#[test]
+fn test_attr_whitespace() {
+	// Parses the attribute value back the way an xml parser would after normalization
+	fn parse(attr: &str) -> String {
+		let normalized: String = attr.chars().map(|chr| if matches!(chr, '\n' | '\t' | '\r') { ' ' } else { chr }).collect();
+		normalized
+			.replace("&#10;", "\n").replace("&#9;", "\t").replace("&#13;", "\r")
+			.replace("&lt;", "<").replace("&gt;", ">").replace("&apos;", "'").replace("&quot;", "\"").replace("&amp;", "&")
+	}
+
+	for value in ["line 1\nline 2", "\ttabbed\t", "crlf\r\n", "\n\r\t<&>'\"", "plain"] {
+		let rendered = crate::xfmt!{<tag data={value} />}.to_string();
+		let attr = rendered.strip_prefix("<tag data=\"").unwrap().strip_suffix("\" />").unwrap();
+		assert!(!attr.contains(['\n', '\t', '\r']), "{:?}", rendered);
+		assert_eq!(parse(attr), value);
+	}
+
+	// Text elements are not affected
+	assert_eq!(crate::xfmt!{<p>{"a\nb"}</p>}.to_string(), "<p>a\nb</p>");
+
+	// EscapeFull is trusted in attribute values and escapes the whitespace too
+	assert_eq!(escape_full("a\tb").to_string(), "a&#9;b");
+	assert_eq!(crate::xfmt!{<p title={escape_full("a\nb\r")}>{escape_full("a\nb")}</p>}.to_string(), "<p title=\"a&#10;b&#13;\">a&#10;b</p>");
+}
+
+#[test]
+fn test_escape_streaming() {
+	// Records the writes reaching the output
+	struct Writes(Vec<String>);
+	impl fmt::Write for Writes {
+		fn write_str(&mut self, s: &str) -> fmt::Result {
+			self.0.push(s.into());
+			Ok(())
+		}
+	}
+
+	struct Chunks;
+	impl fmt::Display for Chunks {
+		fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+			for _ in 0..100 {
+				f.write_str("ab")?;
+				f.write_str("'&")?;
+			}
+			Ok(())
+		}
+	}
+
+	// The chunks are escaped as they are written without collecting the value in a String first
+	let mut writes = Writes(Vec::new());
+	fmt::write(&mut writes, format_args!("{}", escape_text(Chunks))).unwrap();
+	assert_eq!(writes.0.len(), 300);
+	assert_eq!(writes.0[..4], ["ab", "'", "&amp;", "ab"]);
+
+	let mut writes = Writes(Vec::new());
+	fmt::write(&mut writes, format_args!("{}", escape_attr(Chunks))).unwrap();
+	assert_eq!(writes.0.len(), 300);
+	assert_eq!(writes.0[..4], ["ab", "&apos;", "&amp;", "ab"]);
+}
+
+#[test]
+fn test_escape_flags() {
+	let value = "<Tom & Jerry>";
+	assert_eq!(format!("{}", escape_text(value)), "&lt;Tom &amp; Jerry&gt;");
+	assert_eq!(format!("[{:25}]", escape_text(value)), "[&lt;Tom &amp; Jerry&gt;  ]");
+	assert_eq!(format!("[{:>25}]", escape_text(value)), "[  &lt;Tom &amp; Jerry&gt;]");
+	assert_eq!(format!("[{:-^26}]", escape_text(value)), "[-&lt;Tom &amp; Jerry&gt;--]");
+	assert_eq!(format!("[{:5}]", escape_text(value)), "[&lt;Tom &amp; Jerry&gt;]");
+	assert_eq!(format!("[{:.4}]", escape_text(value)), "[&lt;Tom]");
+	assert_eq!(format!("[{:_>10.5}]", escape_text(value)), "[__&lt;Tom ]");
+	assert_eq!(format!("[{:.2}]", escape_text("🦀&🦀")), "[🦀&amp;]");
+	assert_eq!(format!("[{:>8}]", escape_attr("'a'")), "[&apos;a&apos;]");
+	assert_eq!(format!("[{:>16}]", escape_attr("'a'")), "[   &apos;a&apos;]");
+
+	// The fill character is escaped like the value
+	assert_eq!(format!("[{:<^5}]", escape_text("a")), "[&lt;&lt;a&lt;&lt;]");
+	assert_eq!(format!("[{:&>5}]", escape_text("<")), "[&amp;&lt;]");
+	assert_eq!(format!("[{:\"<3}]", escape_attr("a")), "[a&quot;&quot;]");
+	assert_eq!(format!("[{:\"<3}]", escape_text("a")), "[a\"\"]");
+}
+
+#[test]
+fn test_js() {
+	#[track_caller]
+	fn check(input: &str, escaped: &str) {
+		assert_eq!(escape_js(input).to_string(), escaped);
+	}
+
+	check("", "");
+	check("plain & simple", r#"plain \u0026 simple"#);
+	check("\\ \" ' `", r#"\\ \" \' \`"#);
+	check("a\nb\rc\td\0e\x1Bf\x7F", r#"a\nb\rc\td\u0000e\u001Bf\u007F"#);
+	check("\u{2028}\u{2029}é🦀", r#"\u2028\u2029é🦀"#);
+	check("<!-- </SCRIPT -->", r#"\u003C!-- \u003C/SCRIPT --\u003E"#);
+
+	let payload = "</script><script>alert(1)</script><!--";
+	let page = crate::xfmt! { <script>"var a = '"{escape_js(payload)}"';"</script> }.to_string();
+	let body = page.strip_prefix("<script>").unwrap().strip_suffix("</script>").unwrap();
+	assert!(!body.to_ascii_lowercase().contains("</script"));
+	assert!(!body.contains("<!--"));
+	assert_eq!(body, r#"var a = '\u003C/script\u003E\u003Cscript\u003Ealert(1)\u003C/script\u003E\u003C!--';"#);
+
+	// Event handler attributes escape the result again for the attribute
+	let value = "\" onmouseover=\"alert(1)";
+	let link = crate::xfmt! { <a onclick={format_args!("say('{}')", escape_js(value))}>"Say"</a> }.to_string();
+	assert_eq!(link, r#"<a onclick="say(&apos;\&quot; onmouseover=\&quot;alert(1)&apos;)">Say</a>"#);
+	let link = crate::xfmt! { <a onclick={escape_js(value)}>"Say"</a> }.to_string();
+	assert_eq!(link, r#"<a onclick="\&quot; onmouseover=\&quot;alert(1)">Say</a>"#);
+
+	// The result is well-formed in text elements outside of scripts
+	assert_eq!(crate::xfmt! { <p>{escape_js("a & b")}</p> }.to_string(), r#"<p>a \u0026 b</p>"#);
+}
+
+#[test]
+fn test_css() {
+	#[track_caller]
+	fn check(input: &str, escaped: &str) {
+		assert_eq!(escape_css(input).to_string(), escaped);
+	}
+
+	check("", "");
+	check("Open Sans, sans-serif", "Open Sans, sans-serif");
+	check("#f00 50% !important", "#f00 50% !important");
+	check("\\ \" '", r"\5C  \22  \27 ");
+	check("a\nb\r\0", r"a\A b\D \0 ");
+	check("é🦀", "é🦀");
+
+	let payload = "red}</style><script>alert(1)</script>";
+	let page = crate::xfmt! { <style>"p { color: "{escape_css(payload)}"; }"</style> }.to_string();
+	assert_eq!(page, r"<style>p { color: red\7D \3C \2F style\3E \3C script\3E alert\28 1\29 \3C \2F script\3E ; }</style>");
+
+	let value = "x\"onclick='alert(1)'";
+	let page = crate::xfmt! { <p style={format_args!("font-family: \"{}\"", escape_css(value))}></p> }.to_string();
+	assert_eq!(page, r#"<p style="font-family: &quot;x\22 onclick\3D \27 alert\28 1\29 \27 &quot;"></p>"#);
+}
+
+#[test]
+fn test_json_str() {
+	use fmt::Write;
+
+	#[track_caller]
+	fn check(input: &str, escaped: &str) {
+		assert_eq!(escape_json_str(input).to_string(), escaped);
+		let json = format!("\"{}\"", escaped);
+		assert_eq!(serde_json::from_str::<String>(&json).unwrap(), input);
+	}
+
+	check("", "");
+	check("plain </text> & 🦀", "plain </text> & 🦀");
+	check("\"quote\" \\", r#"\"quote\" \\"#);
+	check("a\nb\rc\td\x08e\x0Cf\0g\x1Fh", r#"a\nb\rc\td\be\ff\u0000g\u001Fh"#);
+	check("\x7F", "\x7F");
+
+	let tag = "</script>";
+	let mut s = String::new();
+	write!(EscapeJsonString::new(&mut s).escape_slash(), "{}", tag).unwrap();
+	assert_eq!(s, r"<\/script>");
+
+	let input = "He said \"<b>&amp;</b>\"\n\t'bye' \\o/";
+	let page = crate::xfmt! { <div data-json={format_args!("{{\"text\":\"{}\"}}", escape_json_str(input))}></div> }.to_string();
+	let attr = page.strip_prefix("<div data-json=\"").unwrap().strip_suffix("\"></div>").unwrap();
+	assert!(!attr.contains(['"', '<', '>', '\'', '\n']));
+	let json: serde_json::Value = serde_json::from_str(&crate::unescape(attr)).unwrap();
+	assert_eq!(json["text"], input);
+}
+
+#[test]
+fn test_preserve_entities() {
+	#[track_caller]
+	fn check(parts: &[&str], text: &str, attr: &str) {
+		let mut s = String::new();
+		let mut writer = EscapePreserveEntities::wrap_text(&mut s);
+		for part in parts {
+			writer.write_str(part).unwrap();
+		}
+		writer.finish().unwrap();
+		assert_eq!(s, text);
+
+		let mut s = String::new();
+		let mut writer = EscapePreserveEntities::wrap_attr_value(&mut s);
+		for part in parts {
+			writer.write_str(part).unwrap();
+		}
+		writer.finish().unwrap();
+		assert_eq!(s, attr);
+	}
+
+	check(&[""], "", "");
+	check(&["&amp; &lt; &gt; &quot; &apos;"], "&amp; &lt; &gt; &quot; &apos;", "&amp; &lt; &gt; &quot; &apos;");
+	check(&["&#39; &#x2019; &#X41;"], "&#39; &#x2019; &amp;#X41;", "&#39; &#x2019; &amp;#X41;");
+	check(&["a & b", " <'c'>"], "a &amp; b &lt;'c'&gt;", "a &amp; b &lt;&apos;c&apos;&gt;");
+
+	// Split across writes
+	check(&["&", "amp;"], "&amp;", "&amp;");
+	check(&["x&a", "m", "p", ";y"], "x&amp;y", "x&amp;y");
+	check(&["&#x20", "19;"], "&#x2019;", "&#x2019;");
+	check(&["&amp", ""], "&amp;amp", "&amp;amp");
+	check(&["&amp"], "&amp;amp", "&amp;amp");
+	check(&["&", "&", "lt;"], "&amp;&lt;", "&amp;&lt;");
+
+	// Malformed references
+	check(&["&notanentity;"], "&amp;notanentity;", "&amp;notanentity;");
+	check(&["&nbsp; &AMP; &amp &;"], "&amp;nbsp; &amp;AMP; &amp;amp &amp;;", "&amp;nbsp; &amp;AMP; &amp;amp &amp;;");
+	check(&["&#; &#x; &#0; &#xD800; &#1114112; &#12a;"], "&amp;#; &amp;#x; &amp;#0; &amp;#xD800; &amp;#1114112; &amp;#12a;", "&amp;#; &amp;#x; &amp;#0; &amp;#xD800; &amp;#1114112; &amp;#12a;");
+	check(&["&#00000000065;"], "&amp;#00000000065;", "&amp;#00000000065;");
+	check(&["&amp\n<"], "&amp;amp\n&lt;", "&amp;amp&#10;&lt;");
+
+	// Dropping the writer writes the held back fragment
+	let mut s = String::new();
+	EscapePreserveEntities::wrap_text(&mut s).write_str("a &am").unwrap();
+	assert_eq!(s, "a &amp;am");
+
+	// The default writers are unchanged
+	let mut s = String::new();
+	EscapeText::wrap(&mut s).write_str("&amp;").unwrap();
+	assert_eq!(s, "&amp;amp;");
+}
+
+#[test]
+fn test_write_char() {
+	// Writes every char separately and compares with writing the whole string
+	struct Chars<'a>(&'a str);
+	impl fmt::Display for Chars<'_> {
+		fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+			use fmt::Write;
+			self.0.chars().try_for_each(|chr| f.write_char(chr))
+		}
+	}
+
+	let inputs = ["", "plain", "<a href=\"x\">'é' & \n\t\r🦀</a>", "a--b---c-", "--", "]]> ]]]>] ]>", "-]]-->"];
+	for input in inputs {
+		assert_eq!(escape_text(Chars(input)).to_string(), escape_text(input).to_string());
+		assert_eq!(escape_attr(Chars(input)).to_string(), escape_attr(input).to_string());
+		assert_eq!(escape_comment(Chars(input)).to_string(), escape_comment(input).to_string());
+		assert_eq!(escape_cdata(Chars(input)).to_string(), escape_cdata(input).to_string());
+	}
+
+	// Mixed char and str writes share the state
+	assert_eq!(escape_comment(format_args!("a-{}", Chars("-b-"))).to_string(), "ab- ");
+	assert_eq!(escape_cdata(format_args!("]{}", Chars("]>"))).to_string(), "]]]]><![CDATA[>");
+}
+
+#[test]
+fn test_pi() {
+	#[track_caller]
+	fn check(input: &str, escaped: &str) {
+		let mut writer = EscapePi::new(String::new());
+		writer.write_str(input).unwrap();
+		assert_eq!(writer.into_inner(), escaped);
+
+		// Byte at a time
+		let mut writer = EscapePi::new(String::new());
+		for i in 0..input.len() {
+			writer.write_str(&input[i..i + 1]).unwrap();
+		}
+		assert_eq!(writer.into_inner(), escaped);
+	}
+
+	check("", "");
+	check("?", "?");
+	check(">", ">");
+	check("?>", "? >");
+	check("??>>", "?? >>");
+	check("? >?x>", "? >?x>");
+
+	let url = "style.css?v=1&x=\"?>\"";
+	let pi = crate::xfmt! { <?xml-stylesheet type="text/css" href={url}?><root/> }.to_string();
+	assert_eq!(pi, r#"<?xml-stylesheet type="text/css" href="style.css?v=1&amp;x=&quot;?&gt;&quot;"?><root />"#);
+	assert_eq!(pi.matches("?>").count(), 1);
+}
+
+#[test]
+fn test_escape_into() {
+	let inputs = ["", "plain", "<a href=\"x\">'é' & \n\t\r🦀</a>", "&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&&"];
+	for input in inputs {
+		let mut text = String::from("prefix");
+		escape_into(&mut text, input);
+		let mut expected = String::from("prefix");
+		EscapeText::wrap(&mut expected).write_str(input).unwrap();
+		assert_eq!(text, expected);
+
+		let mut attr = String::from("prefix");
+		escape_attr_into(&mut attr, input);
+		let mut expected = String::from("prefix");
+		EscapeAttrValue::wrap(&mut expected).write_str(input).unwrap();
+		assert_eq!(attr, expected);
+	}
+
+	// The capacity is reserved once up front, the buffer is not reallocated while escaping
+	let large = "Lorem ipsum & dolor <sit> \"amet\"\n".repeat(10_000);
+	let mut text = String::from("<p>");
+	escape_into(&mut text, &large);
+	let ptr = text.as_ptr();
+	assert!(text.capacity() >= text.len());
+	text.truncate(3);
+	escape_into(&mut text, &large);
+	assert_eq!(text.as_ptr(), ptr);
+	let mut attr = String::from("<p title=\"");
+	escape_attr_into(&mut attr, &large);
+	let ptr = attr.as_ptr();
+	assert!(attr.capacity() >= attr.len());
+	attr.truncate(10);
+	escape_attr_into(&mut attr, &large);
+	assert_eq!(attr.as_ptr(), ptr);
+	assert_eq!(attr.len(), 10 + escaped_len_exact(&large));
+}
+
+#[test]
+fn test_escape_cow() {
+	use std::borrow::Cow;
+
+	for input in ["", "plain text", "é🦀", "'quotes' \"stay\" in text"] {
+		match escape_cow(input) {
+			Cow::Borrowed(s) => assert_eq!(s.as_ptr(), input.as_ptr()),
+			Cow::Owned(_) => panic!("expected borrowed: {:?}", input),
+		}
+	}
+	assert!(matches!(escape_attr_cow("'quotes'"), Cow::Owned(_)));
+	assert!(matches!(escape_attr_cow("plain text"), Cow::Borrowed("plain text")));
+
+	for input in ["a&b", "<a href=\"x\">'é' & \n\t\r🦀</a>", "clean prefix then &"] {
+		assert_eq!(escape_cow(input), escape_text(input).to_string());
+		assert_eq!(escape_attr_cow(input), escape_attr(input).to_string());
+	}
+}
+
+#[test]
+fn test_utf8() {
+	let cases = [
+		("😀&中<é>", "😀&amp;中&lt;é&gt;", "😀&amp;中&lt;é&gt;"),
+		("&😀<", "&amp;😀&lt;", "&amp;😀&lt;"),
+		("中中中中中中中中&", "中中中中中中中中&amp;", "中中中中中中中中&amp;"),
+		("\"😀\"\n中'", "\"😀\"\n中'", "&quot;😀&quot;&#10;中&apos;"),
+		("\u{2028}<\u{10FFFF}", "\u{2028}&lt;\u{10FFFF}", "\u{2028}&lt;\u{10FFFF}"),
+	];
+	for &(src, text, attr) in &cases {
+		// Write the source in two pieces split at every char boundary
+		for (i, _) in src.char_indices() {
+			let mut s = String::new();
+			let w = EscapeText::wrap(&mut s);
+			w.write_str(&src[..i]).unwrap();
+			w.write_str(&src[i..]).unwrap();
+			assert_eq!(s, text);
+
+			let mut s = String::new();
+			let w = EscapeAttrValue::wrap(&mut s);
+			w.write_str(&src[..i]).unwrap();
+			w.write_str(&src[i..]).unwrap();
+			assert_eq!(s, attr);
+		}
+	}
+}
+
+// Compares the escaping writers against escaping a char at a time on random strings written in random chunks
+#[test]
+fn test_utf8_random() {
+	fn naive(s: &str, special: &str) -> String {
+		let mut out = String::new();
+		for chr in s.chars() {
+			if special.contains(chr) {
+				let mut buf = [0; 4];
+				escape_chr(&mut out, chr.encode_utf8(&mut buf).as_bytes()[0]).unwrap();
+			}
+			else {
+				out.push(chr);
+			}
+		}
+		out
+	}
+
+	// Writes the string in random chunks split at char boundaries
+	fn chunked(w: &mut dyn fmt::Write, s: &str, rng: &mut u64) {
+		let mut rest = s;
+		while !rest.is_empty() {
+			let mut i = (next(rng) as usize % (rest.len() + 1)).max(1);
+			while !rest.is_char_boundary(i) {
+				i += 1;
+			}
+			w.write_str(&rest[..i]).unwrap();
+			rest = &rest[i..];
+		}
+	}
+
+	fn next(state: &mut u64) -> u64 {
+		*state ^= *state << 13;
+		*state ^= *state >> 7;
+		*state ^= *state << 17;
+		*state
+	}
+
+	const ALPHABET: [char; 15] = ['a', 'z', ' ', '<', '&', '>', '\'', '"', '\n', '\t', '\r', 'é', '中', '😀', '\u{2028}'];
+	let mut rng = 0x2545F4914F6CDD1D;
+	for _ in 0..2000 {
+		let len = next(&mut rng) as usize % 40;
+		let src: String = (0..len).map(|_| ALPHABET[next(&mut rng) as usize % ALPHABET.len()]).collect();
+
+		let mut s = String::new();
+		chunked(EscapeText::wrap(&mut s), &src, &mut rng);
+		assert_eq!(s, naive(&src, "<&>"), "{:?}", src);
+
+		let mut s = String::new();
+		chunked(EscapeAttrValue::wrap(&mut s), &src, &mut rng);
+		assert_eq!(s, naive(&src, "<&>'\"\n\t\r"), "{:?}", src);
+
+		let mut s = String::new();
+		chunked(EscapeFull::wrap(&mut s), &src, &mut rng);
+		assert_eq!(s, naive(&src, "<&>'\"\n\t\r"), "{:?}", src);
+
+		let mut s = String::new();
+		let mut w = EscapePreserveEntities::wrap_attr_value(&mut s);
+		chunked(&mut w, &src, &mut rng);
+		w.finish().unwrap();
+		assert_eq!(s, naive(&src, "<&>'\"\n\t\r"), "{:?}", src);
+
+		let mut s = String::new();
+		chunked(&mut NormalizeNewlines::preserve(&mut s), &src, &mut rng);
+		assert_eq!(s, src.replace('\r', "&#13;"), "{:?}", src);
+	}
+}
+
+#[test]
+fn test_custom() {
+	const TABLE: &[(char, &str)] = &[('\u{A0}', "&#160;"), ('\u{AD}', ""), ('\'', "&#39;"), ('>', ">"), ('\'', "first wins")];
+
+	// The table is applied on top of the standard entities and may override them
+	let src = "<a\u{A0}&\u{AD}'b'>";
+	let mut s = String::new();
+	write!(EscapeCustom::wrap(&mut s, TABLE), "{}", src).unwrap();
+	assert_eq!(s, "&lt;a&#160;&amp;&#39;b&#39;>");
+
+	// Unmapped characters pass through untouched
+	let text = "Café 中文 😀 \u{2028} \"quoted\"";
+	let mut s = String::new();
+	write!(EscapeCustom::wrap(&mut s, TABLE), "{}", text).unwrap();
+	assert_eq!(s, text);
+	let mut s = String::new();
+	write!(EscapeCustom::wrap(&mut s, &[('x', "y")]), "{}", text).unwrap();
+	assert_eq!(s, text);
+
+	// Only the table is applied
+	let mut s = String::new();
+	write!(EscapeCustom::table_only(&mut s, TABLE), "{}", src).unwrap();
+	assert_eq!(s, "<a&#160;&&#39;b&#39;>");
+
+	// Replacing a character removed by an earlier split
+	let mut writer = EscapeCustom::table_only(String::new(), &[('é', "e"), ('😀', ":)")]);
+	writer.write_str("Caf").unwrap();
+	writer.write_str("é 😀").unwrap();
+	assert_eq!(writer.into_inner(), "Cafe :)");
+
+	assert_eq!(escape_with(TABLE, "a\u{A0}<b>").to_string(), "a&#160;&lt;b>");
+}
+
+#[test]
+fn test_attr_name() {
+	let check = |name: &str, expected: &str| {
+		let mut s = String::new();
+		let mut writer = EscapeAttrName::new(&mut s);
+		fmt::Write::write_str(&mut writer, name).unwrap();
+		assert_eq!(writer.is_empty(), name.is_empty());
+		assert_eq!(s, expected);
+	};
+	check("", "");
+	check("data-a", "data-a");
+	check("xlink:href", "xlink:href");
+	check("a b\tc\n", "a_b_c_");
+	check("<x>=\"'/&", "_x______");
+	check("naïve-日本", "naïve-日本");
+	check("a\u{85}\u{3000}b", "a__b");
+	check("1abc", "_1abc");
+	check("-x", "_-x");
+	check(".x", "_.x");
+	check("\u{301}x", "_\u{301}x");
+	check("a1-.", "a1-.");
+
+	// Only the first character of the name is checked as the start of the name
+	let mut s = String::new();
+	let mut writer = EscapeAttrName::new(&mut s);
+	fmt::Write::write_str(&mut writer, "").unwrap();
+	fmt::Write::write_str(&mut writer, "x").unwrap();
+	fmt::Write::write_str(&mut writer, "1").unwrap();
+	assert_eq!(s, "x1");
+	let mut s = String::from("data-");
+	fmt::Write::write_str(&mut EscapeAttrName::suffix(&mut s), "1").unwrap();
+	assert_eq!(s, "data-1");
+
+	assert_eq!(crate::xfmt! { <{"1abc"} {"-x"}="1" data-{1}="2"/> }.to_string(), r#"<_1abc _-x="1" data-1="2" />"#);
 }
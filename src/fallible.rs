@@ -0,0 +1,154 @@
+// This module implements propagating errors other than fmt::Error out of xfmt! templates
+
+use std::cell::Cell;
+use std::fmt::{self, Write};
+
+/// Captures the error of a fallible operation inside a template.
+///
+/// Formatting can only fail with [`fmt::Error`], which carries no information.
+/// Pass the result of a fallible operation through [`check`](Fallible::check) inside the template,
+/// the error is stored and rendering is aborted with `?`.
+/// [`render`](Fallible::render) then returns the original error.
+///
+/// # Examples
+///
+/// ```
+/// #[derive(Debug, PartialEq)]
+/// struct MissingKey(&'static str);
+///
+/// fn translate(key: &'static str) -> Result<&'static str, MissingKey> {
+/// 	match key {
+/// 		"title" => Ok("Welcome"),
+/// 		"body" => Ok("Hello World"),
+/// 		_ => Err(MissingKey(key)),
+/// 	}
+/// }
+///
+/// let cx = format_xml::Fallible::new();
+/// let keys = ["title", "body", "footer"];
+///
+/// let page = format_xml::xfmt! {
+/// 	for &key in (&keys[..2]) {
+/// 		<p>{cx.check(translate(key))?}</p>
+/// 	}
+/// };
+/// assert_eq!(cx.render(&page), Ok(String::from("<p>Welcome</p><p>Hello World</p>")));
+///
+/// let page = format_xml::xfmt! {
+/// 	for &key in (&keys) {
+/// 		<p>{cx.check(translate(key))?}</p>
+/// 	}
+/// };
+/// assert_eq!(cx.render(&page), Err(MissingKey("footer")));
+/// ```
+pub struct Fallible<E> {
+	error: Cell<Option<E>>,
+}
+
+impl<E> Fallible<E> {
+	/// Creates a new instance without an error.
+	#[inline]
+	pub const fn new() -> Fallible<E> {
+		Fallible { error: Cell::new(None) }
+	}
+
+	/// Stores the error and returns [`fmt::Error`] to abort rendering.
+	///
+	/// The first error is kept if rendering continues after an error.
+	#[inline]
+	pub fn check<T>(&self, result: Result<T, E>) -> Result<T, fmt::Error> {
+		result.map_err(|error| self.fail(error))
+	}
+
+	/// Stores the error and returns [`fmt::Error`] to abort rendering.
+	pub fn fail(&self, error: E) -> fmt::Error {
+		let first = self.error.take();
+		self.error.set(Some(first.unwrap_or(error)));
+		fmt::Error
+	}
+
+	/// Takes the stored error.
+	#[inline]
+	pub fn take(&self) -> Option<E> {
+		self.error.take()
+	}
+
+	/// Renders the template to a string.
+	///
+	/// # Panics
+	///
+	/// Panics if the template fails without storing an error, like [`ToString`].
+	pub fn render<T: ?Sized + fmt::Display>(&self, template: &T) -> Result<String, E> {
+		let mut buf = String::new();
+		self.render_into(&mut buf, template)?;
+		Ok(buf)
+	}
+
+	/// Renders the template appending to the string.
+	///
+	/// The partially rendered output is removed if the template fails.
+	///
+	/// # Panics
+	///
+	/// Panics if the template fails without storing an error, like [`ToString`].
+	pub fn render_into<T: ?Sized + fmt::Display>(&self, buf: &mut String, template: &T) -> Result<(), E> {
+		self.error.set(None);
+		let len = buf.len();
+		if write!(buf, "{}", template).is_ok() {
+			return Ok(());
+		}
+		buf.truncate(len);
+		match self.error.take() {
+			Some(error) => Err(error),
+			None => ::core::panic!("a Display implementation returned an error unexpectedly"),
+		}
+	}
+}
+
+impl<E> Default for Fallible<E> {
+	#[inline]
+	fn default() -> Fallible<E> {
+		Fallible::new()
+	}
+}
+
+impl<E> fmt::Debug for Fallible<E> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let error = self.error.take();
+		let is_err = error.is_some();
+		self.error.set(error);
+		f.debug_struct("Fallible").field("is_err", &is_err).finish()
+	}
+}
+
+#[test]
+fn test_fallible() {
+	let cx = &Fallible::new();
+	let items = ["1", "2", "x", "4"];
+	let list = |n: usize| crate::xfmt! { move
+		<ul>
+		for item in (&items[..n]) {
+			let n: i32 = cx.check(item.parse::<i32>())?;
+			<li>{n * 2}</li>
+		}
+		</ul>
+	};
+	assert_eq!(cx.render(&list(2)).unwrap(), "<ul><li>2</li><li>4</li></ul>");
+
+	let mut buf = String::from("<body>");
+	let err = cx.render_into(&mut buf, &list(4)).unwrap_err();
+	assert_eq!(err, "x".parse::<i32>().unwrap_err());
+	assert_eq!(buf, "<body>");
+	assert!(cx.take().is_none());
+
+	// Errors propagate out of embedded templates, the first error is kept
+	let cx = Fallible::new();
+	let lookup = |key: &'static str| if key.len() > 3 { Err(key) } else { Ok(key) };
+	let inner = crate::xfmt! { {cx.check(lookup("inner"))?} };
+	let outer = crate::xfmt! { <div>{@inner}</div>{cx.check(lookup("outer"))?} };
+	assert_eq!(cx.render(&outer), Err("inner"));
+	assert_eq!(cx.fail("a"), fmt::Error);
+	assert_eq!(cx.fail("b"), fmt::Error);
+	assert_eq!(format!("{:?}", cx), "Fallible { is_err: true }");
+	assert_eq!(cx.take(), Some("a"));
+}
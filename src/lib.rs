@@ -15,17 +15,81 @@ Features include:
 * Escape hatch to inject custom formatting code
 
 See [`xfmt!`] for more information.
+
+### Cargo features
+
+* `std` (default): helpers which allocate or use `std::io`.
+* `obfstr`: obfuscates every literal and escape entity with [obfstr](https://crates.io/crates/obfstr).
+  Without it the literals are written as plain strings, there is no runtime decoding and no extra code.
+* `obf`: obfuscates only the literals marked with `obf("...")`.
+* `hash`, `metrics`, `bytes`, `testing` and `lint-lowercase` enable the optional helpers documented with them.
 */
 
 mod xfmt;
-mod prelude;
+mod cached;
+pub mod prelude;
 
 mod escape;
 pub use self::escape::*;
 
+mod context;
+pub use self::context::*;
+
+mod policy;
+pub use self::policy::*;
+
+mod render;
+pub use self::render::*;
+
+mod sanitize;
+pub use self::sanitize::*;
+
+mod url;
+pub use self::url::*;
+
+#[cfg(feature = "std")]
+mod fallible;
+#[cfg(feature = "std")]
+pub use self::fallible::*;
+
+#[cfg(feature = "std")]
+mod pretty;
+#[cfg(feature = "std")]
+pub use self::pretty::*;
+
+#[cfg(feature = "std")]
+mod unescape;
+#[cfg(feature = "std")]
+pub use self::unescape::*;
+
+mod util;
+pub use self::util::*;
+
+mod writers;
+pub use self::writers::*;
+
+mod lint;
+pub use self::lint::*;
+
+#[cfg(feature = "testing")]
+mod testing;
+#[cfg(feature = "testing")]
+pub use self::testing::*;
+
 #[doc(hidden)]
 pub use fmtools::{__fmt, obfstr};
 
+#[cfg(any(feature = "obf", feature = "obfstr"))]
+#[doc(hidden)]
+pub use obfstr::obfstr as __obf;
+
+#[cfg(not(any(feature = "obf", feature = "obfstr")))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __obf {
+	($s:expr) => { $s };
+}
+
 pub use fmtools::{fmt, join};
 
 #[cfg(doc)]
@@ -0,0 +1,421 @@
+/*!
+Expansion time checks for element and attribute names and strict templates.
+*/
+
+// Mixed case names which are part of the SVG vocabulary
+const ALLOWED: &[&str] = &[
+	// Elements
+	"altGlyph", "altGlyphDef", "altGlyphItem", "animateMotion", "animateTransform", "clipPath", "feBlend",
+	"feColorMatrix", "feComponentTransfer", "feComposite", "feConvolveMatrix", "feDiffuseLighting",
+	"feDisplacementMap", "feDistantLight", "feDropShadow", "feFlood", "feFuncA", "feFuncB", "feFuncG", "feFuncR",
+	"feGaussianBlur", "feImage", "feMerge", "feMergeNode", "feMorphology", "feOffset", "fePointLight",
+	"feSpecularLighting", "feSpotLight", "feTile", "feTurbulence", "foreignObject", "glyphRef", "linearGradient",
+	"radialGradient", "textPath",
+	// Attributes
+	"attributeName", "attributeType", "baseFrequency", "baseProfile", "calcMode", "clipPathUnits",
+	"diffuseConstant", "edgeMode", "filterUnits", "glyphRef", "gradientTransform", "gradientUnits",
+	"kernelMatrix", "kernelUnitLength", "keyPoints", "keySplines", "keyTimes", "lengthAdjust",
+	"limitingConeAngle", "markerHeight", "markerUnits", "markerWidth", "maskContentUnits", "maskUnits",
+	"numOctaves", "pathLength", "patternContentUnits", "patternTransform", "patternUnits", "pointsAtX",
+	"pointsAtY", "pointsAtZ", "preserveAlpha", "preserveAspectRatio", "primitiveUnits", "refX", "refY",
+	"repeatCount", "repeatDur", "requiredExtensions", "requiredFeatures", "specularConstant",
+	"specularExponent", "spreadMethod", "startOffset", "stdDeviation", "stitchTiles", "surfaceScale",
+	"systemLanguage", "tableValues", "targetX", "targetY", "textLength", "viewBox", "xChannelSelector",
+	"yChannelSelector", "zoomAndPan",
+];
+
+const fn str_eq(a: &str, b: &str) -> bool {
+	let (a, b) = (a.as_bytes(), b.as_bytes());
+	if a.len() != b.len() {
+		return false;
+	}
+	let mut i = 0;
+	while i < a.len() {
+		if a[i] != b[i] {
+			return false;
+		}
+		i += 1;
+	}
+	true
+}
+
+#[doc(hidden)]
+#[inline]
+pub const fn __tag_eq(open: &str, close: &str) -> bool {
+	str_eq(open, close)
+}
+
+#[doc(hidden)]
+pub const fn __doctype_literal(s: &str) -> bool {
+	let bytes = s.as_bytes();
+	let mut i = 0;
+	while i < bytes.len() {
+		if bytes[i] == b'"' {
+			return false;
+		}
+		i += 1;
+	}
+	true
+}
+
+/// Returns true if the name has no uppercase ASCII letters or is a known mixed case SVG name.
+///
+/// Namespace prefixes are checked separately, eg. `xlink:href` and `svg:viewBox` are accepted.
+#[doc(hidden)]
+pub const fn __lowercase_name(name: &str) -> bool {
+	let bytes = name.as_bytes();
+	let mut start = 0;
+	let mut i = 0;
+	while i <= bytes.len() {
+		if i == bytes.len() || bytes[i] == b':' {
+			let (_, part) = bytes.split_at(start);
+			let (part, _) = part.split_at(i - start);
+			if !lowercase_part(part) {
+				return false;
+			}
+			start = i + 1;
+		}
+		i += 1;
+	}
+	true
+}
+
+const fn lowercase_part(part: &[u8]) -> bool {
+	let mut i = 0;
+	while i < part.len() {
+		if part[i].is_ascii_uppercase() {
+			break;
+		}
+		i += 1;
+	}
+	if i == part.len() {
+		return true;
+	}
+	let part = match core::str::from_utf8(part) {
+		Ok(part) => part,
+		Err(_) => return false,
+	};
+	let mut j = 0;
+	while j < ALLOWED.len() {
+		if str_eq(ALLOWED[j], part) {
+			return true;
+		}
+		j += 1;
+	}
+	false
+}
+
+// Emits a constant assertion for every statically known name
+#[cfg(feature = "lint-lowercase")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __xfmt_lint {
+	($kind:literal $name:expr) => {
+		const _: () = if !$crate::__lowercase_name($name) {
+			::core::panic!("{}", ::core::concat!("uppercase letters in ", $kind, " name `", $name, "`"));
+		};
+	};
+}
+
+#[cfg(not(feature = "lint-lowercase"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __xfmt_lint {
+	($kind:literal $name:expr) => {};
+}
+
+// Checks the open and close tags of a strict template are balanced
+// The mode is `xml`, `html` or `foreign` for svg and math content in html, the mode of the parent is restored when the tag is closed
+// The stack holds the open tags as `[tag name, mode]`, dynamic names as `[{} mode]` and fragments as `[<> mode]`
+// Bodies of control flow are checked separately, they must close the tags they open
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __xfmt_strict {
+	// fragment
+	($mode:tt [$($stack:tt)*] < > $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$mode [[<> $mode] $($stack)*] $($tail)*}
+	};
+	($mode:tt [[<> $m:tt] $($stack:tt)*] </ > $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$m [$($stack)*] $($tail)*}
+	};
+	($mode:tt [[{} $m:tt] $($stack:tt)*] </ > $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$m [$($stack)*] $($tail)*}
+	};
+	($mode:tt [[tag $open:expr, $m:tt] $($stack:tt)*] </ > $($tail:tt)*) => {
+		compile_error!(concat!("mismatched tags: `<", $open, ">` closed by `</>`"));
+	};
+	($mode:tt [] </ > $($tail:tt)*) => {
+		compile_error!("unexpected `</>` without a matching `<>`");
+	};
+
+	// void elements have no close tag in html
+	(html [$($stack:tt)*] </ area > $($tail:tt)*) => { $crate::__xfmt_strict!{@void_close area} };
+	(html [$($stack:tt)*] </ base > $($tail:tt)*) => { $crate::__xfmt_strict!{@void_close base} };
+	(html [$($stack:tt)*] </ br > $($tail:tt)*) => { $crate::__xfmt_strict!{@void_close br} };
+	(html [$($stack:tt)*] </ col > $($tail:tt)*) => { $crate::__xfmt_strict!{@void_close col} };
+	(html [$($stack:tt)*] </ embed > $($tail:tt)*) => { $crate::__xfmt_strict!{@void_close embed} };
+	(html [$($stack:tt)*] </ hr > $($tail:tt)*) => { $crate::__xfmt_strict!{@void_close hr} };
+	(html [$($stack:tt)*] </ img > $($tail:tt)*) => { $crate::__xfmt_strict!{@void_close img} };
+	(html [$($stack:tt)*] </ input > $($tail:tt)*) => { $crate::__xfmt_strict!{@void_close input} };
+	(html [$($stack:tt)*] </ link > $($tail:tt)*) => { $crate::__xfmt_strict!{@void_close link} };
+	(html [$($stack:tt)*] </ meta > $($tail:tt)*) => { $crate::__xfmt_strict!{@void_close meta} };
+	(html [$($stack:tt)*] </ source > $($tail:tt)*) => { $crate::__xfmt_strict!{@void_close source} };
+	(html [$($stack:tt)*] </ track > $($tail:tt)*) => { $crate::__xfmt_strict!{@void_close track} };
+	(html [$($stack:tt)*] </ wbr > $($tail:tt)*) => { $crate::__xfmt_strict!{@void_close wbr} };
+	(@void_close $void:ident) => {
+		compile_error!(concat!("the void element `<", stringify!($void), ">` cannot have content or a close tag `</", stringify!($void), ">`"));
+	};
+
+	// tag close
+	($mode:tt [$($stack:tt)*] </ {$($e:tt)*} > $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@pop [$($stack)*] $($tail)*}
+	};
+	($mode:tt [$($stack:tt)*] </ $($tail:tt)*) => {
+		$crate::__xfmt_ident!{__xfmt_strict! [@close [$($stack)*]] $($tail)*}
+	};
+	(@close [$($stack:tt)*] @ident_fmt([$($name:expr,)*] {$($e:tt)*}) > $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@pop [$($stack)*] $($tail)*}
+	};
+	(@close [[{} $m:tt] $($stack:tt)*] @ident($close:expr) > $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$m [$($stack)*] $($tail)*}
+	};
+	(@close [[<> $m:tt] $($stack:tt)*] @ident($close:expr) > $($tail:tt)*) => {
+		compile_error!(concat!("mismatched tags: `<>` closed by `</", $close, ">`"));
+	};
+	(@close [[tag $open:expr, $m:tt] $($stack:tt)*] @ident($close:expr) > $($tail:tt)*) => {
+		const _: () = if !$crate::__tag_eq($open, $close) {
+			::core::panic!("{}", ::core::concat!("mismatched tags: `<", $open, ">` closed by `</", $close, ">`"));
+		};
+		$crate::__xfmt_strict!{$m [$($stack)*] $($tail)*}
+	};
+	(@close [] @ident($close:expr) > $($tail:tt)*) => {
+		compile_error!(concat!("unexpected `</", $close, ">` without a matching open tag"));
+	};
+	(@close [$($stack:tt)*] $($tail:tt)*) => {
+		compile_error!("expected a tag name followed by `>` in the close tag");
+	};
+	(@pop [[<> $m:tt] $($stack:tt)*] $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$m [$($stack)*] $($tail)*}
+	};
+	(@pop [[{} $m:tt] $($stack:tt)*] $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$m [$($stack)*] $($tail)*}
+	};
+	(@pop [[tag $open:expr, $m:tt] $($stack:tt)*] $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$m [$($stack)*] $($tail)*}
+	};
+	(@pop [] $($tail:tt)*) => {
+		compile_error!("unexpected close tag without a matching open tag");
+	};
+
+	// markup without a close tag
+	($mode:tt [$($stack:tt)*] <!-- $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@skip $mode [$($stack)*] [-->] $($tail)*}
+	};
+	($mode:tt [$($stack:tt)*] <![CDATA[ $($tt:tt)* ]]> $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$mode [$($stack)*] $($tail)*}
+	};
+	($mode:tt [$($stack:tt)*] <! $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@skip $mode [$($stack)*] [>] $($tail)*}
+	};
+	($mode:tt [$($stack:tt)*] <? $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@skip $mode [$($stack)*] [? >] $($tail)*}
+	};
+	(@skip $mode:tt [$($stack:tt)*] [-->] --> $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$mode [$($stack)*] $($tail)*}
+	};
+	(@skip $mode:tt [$($stack:tt)*] [? >] ? > $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$mode [$($stack)*] $($tail)*}
+	};
+	(@skip $mode:tt [$($stack:tt)*] [>] > $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$mode [$($stack)*] $($tail)*}
+	};
+	(@skip $mode:tt [$($stack:tt)*] [$($end:tt)*] $skip:tt $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@skip $mode [$($stack)*] [$($end)*] $($tail)*}
+	};
+	(@skip $mode:tt [$($stack:tt)*] [$($end:tt)*]) => {};
+
+	// void elements in html
+	(html [$($stack:tt)*] < area $($tail:tt)*) => { $crate::__xfmt_strict!{@void [$($stack)*] area $($tail)*} };
+	(html [$($stack:tt)*] < base $($tail:tt)*) => { $crate::__xfmt_strict!{@void [$($stack)*] base $($tail)*} };
+	(html [$($stack:tt)*] < br $($tail:tt)*) => { $crate::__xfmt_strict!{@void [$($stack)*] br $($tail)*} };
+	(html [$($stack:tt)*] < col $($tail:tt)*) => { $crate::__xfmt_strict!{@void [$($stack)*] col $($tail)*} };
+	(html [$($stack:tt)*] < embed $($tail:tt)*) => { $crate::__xfmt_strict!{@void [$($stack)*] embed $($tail)*} };
+	(html [$($stack:tt)*] < hr $($tail:tt)*) => { $crate::__xfmt_strict!{@void [$($stack)*] hr $($tail)*} };
+	(html [$($stack:tt)*] < img $($tail:tt)*) => { $crate::__xfmt_strict!{@void [$($stack)*] img $($tail)*} };
+	(html [$($stack:tt)*] < input $($tail:tt)*) => { $crate::__xfmt_strict!{@void [$($stack)*] input $($tail)*} };
+	(html [$($stack:tt)*] < link $($tail:tt)*) => { $crate::__xfmt_strict!{@void [$($stack)*] link $($tail)*} };
+	(html [$($stack:tt)*] < meta $($tail:tt)*) => { $crate::__xfmt_strict!{@void [$($stack)*] meta $($tail)*} };
+	(html [$($stack:tt)*] < source $($tail:tt)*) => { $crate::__xfmt_strict!{@void [$($stack)*] source $($tail)*} };
+	(html [$($stack:tt)*] < track $($tail:tt)*) => { $crate::__xfmt_strict!{@void [$($stack)*] track $($tail)*} };
+	(html [$($stack:tt)*] < wbr $($tail:tt)*) => { $crate::__xfmt_strict!{@void [$($stack)*] wbr $($tail)*} };
+	(@void [$($stack:tt)*] $void:ident - $($tail:tt)*) => {
+		$crate::__xfmt_ident!{__xfmt_strict! [@open html [$($stack)*]] $void - $($tail)*}
+	};
+	(@void [$($stack:tt)*] $void:ident : $($tail:tt)*) => {
+		$crate::__xfmt_ident!{__xfmt_strict! [@open html [$($stack)*]] $void : $($tail)*}
+	};
+	(@void [$($stack:tt)*] $void:ident . $($tail:tt)*) => {
+		$crate::__xfmt_ident!{__xfmt_strict! [@open html [$($stack)*]] $void . $($tail)*}
+	};
+	(@void [$($stack:tt)*] $void:ident $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@attrs html [$($stack)*] [] html $($tail)*}
+	};
+
+	// svg and math content in html may use self-closing tags
+	(html [$($stack:tt)*] < svg $($tail:tt)*) => { $crate::__xfmt_strict!{@foreign [$($stack)*] svg $($tail)*} };
+	(html [$($stack:tt)*] < math $($tail:tt)*) => { $crate::__xfmt_strict!{@foreign [$($stack)*] math $($tail)*} };
+	(@foreign [$($stack:tt)*] $name:ident - $($tail:tt)*) => {
+		$crate::__xfmt_ident!{__xfmt_strict! [@open html [$($stack)*]] $name - $($tail)*}
+	};
+	(@foreign [$($stack:tt)*] $name:ident : $($tail:tt)*) => {
+		$crate::__xfmt_ident!{__xfmt_strict! [@open html [$($stack)*]] $name : $($tail)*}
+	};
+	(@foreign [$($stack:tt)*] $name:ident . $($tail:tt)*) => {
+		$crate::__xfmt_ident!{__xfmt_strict! [@open html [$($stack)*]] $name . $($tail)*}
+	};
+	(@foreign [$($stack:tt)*] $name:ident $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@attrs html [$($stack)*] [tag stringify!($name), html] foreign $($tail)*}
+	};
+
+	// tag open
+	($mode:tt [$($stack:tt)*] < {$($e:tt)*} $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@attrs $mode [$($stack)*] [{} $mode] $mode $($tail)*}
+	};
+	($mode:tt [$($stack:tt)*] < $($tail:tt)*) => {
+		$crate::__xfmt_ident!{__xfmt_strict! [@open $mode [$($stack)*]] $($tail)*}
+	};
+	(@open $mode:tt [$($stack:tt)*] @ident_fmt([$($name:expr,)*] {$($e:tt)*}) $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@attrs $mode [$($stack)*] [{} $mode] $mode $($tail)*}
+	};
+	(@open $mode:tt [$($stack:tt)*] @ident($open:expr) $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@attrs $mode [$($stack)*] [tag $open, $mode] $mode $($tail)*}
+	};
+	(@attrs html [$($stack:tt)*] [tag $open:expr, $m:tt] html / > $($tail:tt)*) => {
+		compile_error!(concat!("the html element `<", $open, " />` cannot be self-closing, write `<", $open, "></", $open, ">`"));
+	};
+	(@attrs $mode:tt [$($stack:tt)*] [$($open:tt)*] $next:tt / > $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$mode [$($stack)*] $($tail)*}
+	};
+	(@attrs $mode:tt [$($stack:tt)*] [] $next:tt > $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$mode [$($stack)*] $($tail)*}
+	};
+	(@attrs $mode:tt [$($stack:tt)*] [$($open:tt)*] $next:tt > $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$next [[$($open)*] $($stack)*] $($tail)*}
+	};
+	(@attrs $mode:tt [$($stack:tt)*] [$($open:tt)*] $next:tt $name:ident = $value:literal $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@attrs $mode [$($stack)*] [$($open)*] $next $($tail)*}
+	};
+	(@attrs $mode:tt [$($stack:tt)*] [$($open:tt)*] $next:tt $name:ident = {$($value:tt)*} $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@attrs $mode [$($stack)*] [$($open)*] $next $($tail)*}
+	};
+	(@attrs $mode:tt [$($stack:tt)*] [$($open:tt)*] $next:tt $skip:tt $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@attrs $mode [$($stack)*] [$($open)*] $next $($tail)*}
+	};
+	(@attrs $mode:tt [$($stack:tt)*] [$($open:tt)*] $next:tt) => {};
+
+	// control flow bodies
+	($mode:tt [$($stack:tt)*] if $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@body $mode [$($stack)*] $($tail)*}
+	};
+	($mode:tt [$($stack:tt)*] else $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@body $mode [$($stack)*] $($tail)*}
+	};
+	($mode:tt [$($stack:tt)*] for $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@body $mode [$($stack)*] $($tail)*}
+	};
+	($mode:tt [$($stack:tt)*] while $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@body $mode [$($stack)*] $($tail)*}
+	};
+	($mode:tt [$($stack:tt)*] loop $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@body $mode [$($stack)*] $($tail)*}
+	};
+	($mode:tt [$($stack:tt)*] sep {$($body:tt)*} $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$mode [] $($body)*}
+		$crate::__xfmt_strict!{$mode [$($stack)*] $($tail)*}
+	};
+	(@body $mode:tt [$($stack:tt)*] {$($body:tt)*} $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$mode [] $($body)*}
+		$crate::__xfmt_strict!{$mode [$($stack)*] $($tail)*}
+	};
+	(@body $mode:tt [$($stack:tt)*] $skip:tt $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@body $mode [$($stack)*] $($tail)*}
+	};
+	(@body $mode:tt [$($stack:tt)*]) => {};
+
+	// The arms of match expressions are not checked
+	($mode:tt [$($stack:tt)*] match $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@skip_body $mode [$($stack)*] $($tail)*}
+	};
+	(@skip_body $mode:tt [$($stack:tt)*] {$($arms:tt)*} $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$mode [$($stack)*] $($tail)*}
+	};
+	(@skip_body $mode:tt [$($stack:tt)*] $skip:tt $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@skip_body $mode [$($stack)*] $($tail)*}
+	};
+	(@skip_body $mode:tt [$($stack:tt)*]) => {};
+
+	// let statements and their else block
+	// The else block replaces the remainder of the enclosing block, it must close the tags which are open in the block
+	($mode:tt [$($stack:tt)*] let $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@let $mode [$($stack)*] $($tail)*}
+	};
+	(@let $mode:tt [$($stack:tt)*] else {$($body:tt)*} $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$mode [$($stack)*] $($body)*}
+		$crate::__xfmt_strict!{@let $mode [$($stack)*] $($tail)*}
+	};
+	(@let $mode:tt [$($stack:tt)*] ; $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$mode [$($stack)*] $($tail)*}
+	};
+	(@let $mode:tt [$($stack:tt)*] $skip:tt $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@let $mode [$($stack)*] $($tail)*}
+	};
+	(@let $mode:tt [$($stack:tt)*]) => {};
+
+	// escape hatch
+	($mode:tt [$($stack:tt)*] | $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@hatch $mode [$($stack)*] $($tail)*}
+	};
+	(@hatch $mode:tt [$($stack:tt)*] | {$($block:tt)*} $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$mode [$($stack)*] $($tail)*}
+	};
+	(@hatch $mode:tt [$($stack:tt)*] | $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@let $mode [$($stack)*] $($tail)*}
+	};
+	(@hatch $mode:tt [$($stack:tt)*] $skip:tt $($tail:tt)*) => {
+		$crate::__xfmt_strict!{@hatch $mode [$($stack)*] $($tail)*}
+	};
+	(@hatch $mode:tt [$($stack:tt)*]) => {};
+
+	// end of the template
+	($mode:tt []) => {};
+	($mode:tt [[<> $m:tt] $($stack:tt)*]) => {
+		compile_error!("unclosed `<>` fragment");
+	};
+	($mode:tt [[{} $m:tt] $($stack:tt)*]) => {
+		compile_error!("unclosed element with a dynamic name");
+	};
+	($mode:tt [[tag $open:expr, $m:tt] $($stack:tt)*]) => {
+		compile_error!(concat!("unclosed tag `<", $open, ">`"));
+	};
+
+	// text, values and anything else
+	($mode:tt [$($stack:tt)*] $text1:literal $text2:literal $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$mode [$($stack)*] $($tail)*}
+	};
+	($mode:tt [$($stack:tt)*] $skip:tt $($tail:tt)*) => {
+		$crate::__xfmt_strict!{$mode [$($stack)*] $($tail)*}
+	};
+}
+
+#[test]
+fn test_lowercase_name() {
+	assert!(__lowercase_name("div"));
+	assert!(__lowercase_name("data-id"));
+	assert!(__lowercase_name("viewBox"));
+	assert!(__lowercase_name("xlink:href"));
+	assert!(__lowercase_name("svg:linearGradient"));
+	assert!(!__lowercase_name("Div"));
+	assert!(!__lowercase_name("onClick"));
+	assert!(!__lowercase_name("XLINK:href"));
+	assert!(!__lowercase_name("data-viewBox"));
+}
@@ -0,0 +1,338 @@
+// This module implements selecting the escaping policy per xfmt! invocation
+
+use core::{fmt, marker::PhantomData, ops};
+use crate::{EscapeAttrValue, EscapeAttrValueMinimal, EscapeAttrValueNumeric, EscapeCharData, EscapeComment, EscapePi, EscapeText, EscapeTextMinimal, NormalizeNewlines};
+
+/// Escaping policy for the values in [`xfmt!`](crate::xfmt) templates.
+///
+/// The literals are never escaped, the policy escapes the values inside formatting braces.
+pub trait EscapePolicy {
+	/// Writes the value escaped for text elements.
+	fn write_text<W: fmt::Write, A: XfmtArgs>(f: &mut W, args: A) -> fmt::Result;
+	/// Writes the value escaped for double quoted attribute values.
+	fn write_attr<W: fmt::Write, A: XfmtArgs>(f: &mut W, args: A) -> fmt::Result;
+}
+
+/// The default policy, escapes with [`EscapeText`] and [`EscapeAttrValue`].
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Standard;
+impl EscapePolicy for Standard {
+	#[inline]
+	fn write_text<W: fmt::Write, A: XfmtArgs>(f: &mut W, args: A) -> fmt::Result {
+		args.write_args(EscapeText::wrap(f))
+	}
+	#[inline]
+	fn write_attr<W: fmt::Write, A: XfmtArgs>(f: &mut W, args: A) -> fmt::Result {
+		args.write_args(EscapeAttrValue::wrap(f))
+	}
+}
+
+/// The `minimal:` policy, escapes with [`EscapeTextMinimal`] and [`EscapeAttrValueMinimal`].
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Minimal;
+impl EscapePolicy for Minimal {
+	#[inline]
+	fn write_text<W: fmt::Write, A: XfmtArgs>(f: &mut W, args: A) -> fmt::Result {
+		args.write_args(&mut EscapeTextMinimal::new(f))
+	}
+	#[inline]
+	fn write_attr<W: fmt::Write, A: XfmtArgs>(f: &mut W, args: A) -> fmt::Result {
+		args.write_args(EscapeAttrValueMinimal::wrap(f))
+	}
+}
+
+/// The `html:` policy, escapes with [`EscapeText`] and [`EscapeAttrValueNumeric`].
+///
+/// The quotes in attribute values are written as `&#39;` and `&#34;` instead of the xml entities `&apos;` and `&quot;`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Html;
+impl EscapePolicy for Html {
+	#[inline]
+	fn write_text<W: fmt::Write, A: XfmtArgs>(f: &mut W, args: A) -> fmt::Result {
+		args.write_args(EscapeText::wrap(f))
+	}
+	#[inline]
+	fn write_attr<W: fmt::Write, A: XfmtArgs>(f: &mut W, args: A) -> fmt::Result {
+		args.write_args(EscapeAttrValueNumeric::wrap(f))
+	}
+}
+
+/// The `newlines:` policy, escapes like [`Standard`] and converts `\r\n` and `\r` in the values to `\n` with [`NormalizeNewlines`].
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Newlines;
+impl EscapePolicy for Newlines {
+	#[inline]
+	fn write_text<W: fmt::Write, A: XfmtArgs>(f: &mut W, args: A) -> fmt::Result {
+		args.write_args(EscapeText::wrap(&mut NormalizeNewlines::new(f)))
+	}
+	#[inline]
+	fn write_attr<W: fmt::Write, A: XfmtArgs>(f: &mut W, args: A) -> fmt::Result {
+		args.write_args(&mut NormalizeNewlines::new(EscapeAttrValue::wrap(f)))
+	}
+}
+
+/// The `preserve_newlines:` policy, escapes like [`Standard`] and writes `\r` in the values as `&#13;` so xml parsers keep it.
+///
+/// Attribute values already escape `\r` as `&#13;`, text elements use [`NormalizeNewlines::preserve`].
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PreserveNewlines;
+impl EscapePolicy for PreserveNewlines {
+	#[inline]
+	fn write_text<W: fmt::Write, A: XfmtArgs>(f: &mut W, args: A) -> fmt::Result {
+		args.write_args(EscapeText::wrap(&mut NormalizeNewlines::preserve(f)))
+	}
+	#[inline]
+	fn write_attr<W: fmt::Write, A: XfmtArgs>(f: &mut W, args: A) -> fmt::Result {
+		args.write_args(EscapeAttrValue::wrap(f))
+	}
+}
+
+/// The `trusted:` policy, writes the values as is without escaping.
+///
+/// For templates whose values are all program controlled or already escaped, eg. containing entity references.
+/// Values containing markup characters produce malformed or unsafe output, never use this with user controlled values.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Trusted;
+impl EscapePolicy for Trusted {
+	#[inline]
+	fn write_text<W: fmt::Write, A: XfmtArgs>(f: &mut W, args: A) -> fmt::Result {
+		args.write_args(f)
+	}
+	#[inline]
+	fn write_attr<W: fmt::Write, A: XfmtArgs>(f: &mut W, args: A) -> fmt::Result {
+		args.write_args(f)
+	}
+}
+
+/// Formatter which carries the escaping policy of the template in its type.
+///
+/// Dereferences to the formatter, the escape hatch of a template with a policy receives this type.
+#[doc(hidden)]
+pub struct PolicyWriter<'a, 'b, P> {
+	f: &'a mut fmt::Formatter<'b>,
+	policy: PhantomData<P>,
+}
+impl<'a, 'b, P: EscapePolicy> PolicyWriter<'a, 'b, P> {
+	#[inline]
+	pub fn new(f: &'a mut fmt::Formatter<'b>) -> PolicyWriter<'a, 'b, P> {
+		PolicyWriter { f, policy: PhantomData }
+	}
+}
+impl<'a, 'b, P> ops::Deref for PolicyWriter<'a, 'b, P> {
+	type Target = fmt::Formatter<'b>;
+	#[inline]
+	fn deref(&self) -> &fmt::Formatter<'b> {
+		self.f
+	}
+}
+impl<'a, 'b, P> ops::DerefMut for PolicyWriter<'a, 'b, P> {
+	#[inline]
+	fn deref_mut(&mut self) -> &mut fmt::Formatter<'b> {
+		self.f
+	}
+}
+// Forward Write calls
+impl<'a, 'b, P> PolicyWriter<'a, 'b, P> {
+	#[inline]
+	pub fn write_str(&mut self, s: &str) -> fmt::Result {
+		self.f.write_str(s)
+	}
+	#[inline]
+	pub fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
+		self.f.write_fmt(args)
+	}
+}
+impl<'a, 'b, P> fmt::Write for PolicyWriter<'a, 'b, P> {
+	#[inline]
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		self.f.write_str(s)
+	}
+}
+
+// The value to escape, either formatting arguments or a string written directly
+#[doc(hidden)]
+pub trait XfmtArgs {
+	fn write_args<W: ?Sized + fmt::Write>(self, w: &mut W) -> fmt::Result;
+}
+impl XfmtArgs for fmt::Arguments<'_> {
+	#[inline]
+	fn write_args<W: ?Sized + fmt::Write>(self, w: &mut W) -> fmt::Result {
+		match self.as_str() {
+			Some(s) => w.write_str(s),
+			None => w.write_fmt(self),
+		}
+	}
+}
+impl XfmtArgs for &str {
+	#[inline]
+	fn write_args<W: ?Sized + fmt::Write>(self, w: &mut W) -> fmt::Result {
+		w.write_str(self)
+	}
+}
+
+// Selects the escaping policy from the type of the formatter
+#[doc(hidden)]
+pub trait XfmtWrite {
+	fn __xfmt_text<A: XfmtArgs>(&mut self, args: A) -> fmt::Result;
+	fn __xfmt_attr<A: XfmtArgs>(&mut self, args: A) -> fmt::Result;
+	fn __xfmt_pi<A: XfmtArgs>(&mut self, args: A) -> fmt::Result;
+	fn __xfmt_raw<A: XfmtArgs>(&mut self, args: A) -> fmt::Result;
+}
+impl XfmtWrite for fmt::Formatter<'_> {
+	#[inline]
+	fn __xfmt_text<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		Standard::write_text(self, args)
+	}
+	#[inline]
+	fn __xfmt_attr<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		Standard::write_attr(self, args)
+	}
+	#[inline]
+	fn __xfmt_pi<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		Standard::write_attr(&mut EscapePi::new(self), args)
+	}
+	#[inline]
+	fn __xfmt_raw<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		args.write_args(self)
+	}
+}
+impl<P: EscapePolicy> XfmtWrite for PolicyWriter<'_, '_, P> {
+	#[inline]
+	fn __xfmt_text<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		P::write_text(self.f, args)
+	}
+	#[inline]
+	fn __xfmt_attr<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		P::write_attr(self.f, args)
+	}
+	#[inline]
+	fn __xfmt_pi<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		P::write_attr(&mut EscapePi::new(&mut *self.f), args)
+	}
+	#[inline]
+	fn __xfmt_raw<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		args.write_args(self.f)
+	}
+}
+// The markup inside CDATA sections is text, the values are written as is and only `]]>` is escaped
+impl<T: fmt::Write> XfmtWrite for EscapeCharData<T> {
+	#[inline]
+	fn __xfmt_text<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		args.write_args(self)
+	}
+	#[inline]
+	fn __xfmt_attr<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		args.write_args(self)
+	}
+	#[inline]
+	fn __xfmt_pi<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		args.write_args(self)
+	}
+	#[inline]
+	fn __xfmt_raw<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		args.write_args(self)
+	}
+}
+// The markup inside comments is text, the values are written as is and only `--` is escaped
+impl<T: fmt::Write> XfmtWrite for EscapeComment<T> {
+	#[inline]
+	fn __xfmt_text<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		args.write_args(self)
+	}
+	#[inline]
+	fn __xfmt_attr<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		args.write_args(self)
+	}
+	#[inline]
+	fn __xfmt_pi<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		args.write_args(self)
+	}
+	#[inline]
+	fn __xfmt_raw<A: XfmtArgs>(&mut self, args: A) -> fmt::Result {
+		args.write_args(self)
+	}
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __xfmt_policy {
+	(minimal) => { $crate::Minimal };
+	(html) => { $crate::Html };
+	(trusted) => { $crate::Trusted };
+	(newlines) => { $crate::Newlines };
+	(preserve_newlines) => { $crate::PreserveNewlines };
+	($policy:ident) => { compile_error!(concat!("unknown escaping policy `", stringify!($policy), "`, expected `minimal`, `html`, `trusted`, `newlines` or `preserve_newlines`")) };
+}
+
+#[test]
+fn test_minimal() {
+	let text = "Tom & Jerry > \"Itchy\" & 'Scratchy' <3 ]]>";
+	let render = || crate::xfmt! {
+		<doc><item name={text}>{text}</item>|f| f.write_str("<raw/>")?;</doc>
+	};
+	let minimal = crate::xfmt! { minimal:
+		<doc><item name={text}>{text}</item>|f| f.write_str("<raw/>")?;</doc>
+	}.to_string();
+	let standard = render().to_string();
+	assert_eq!(minimal, r#"<doc><item name="Tom &amp; Jerry > &quot;Itchy&quot; &amp; 'Scratchy' &lt;3 ]]>">Tom &amp; Jerry > "Itchy" &amp; 'Scratchy' &lt;3 ]]&gt;</item><raw/></doc>"#);
+	assert!(minimal.len() < standard.len());
+
+	for xml in [&minimal, &standard] {
+		let doc = roxmltree::Document::parse(xml).unwrap();
+		let item = doc.root_element().first_child().unwrap();
+		assert_eq!(item.attribute("name"), Some(text));
+		assert_eq!(item.text(), Some(text));
+	}
+
+	let href = "style.css?>";
+	let pi = crate::xfmt! { minimal: <?xml-stylesheet href={href}?> }.to_string();
+	assert_eq!(pi, r#"<?xml-stylesheet href="style.css? >"?>"#);
+
+	let value = 5;
+	let moved = crate::xfmt! { move minimal: <i>{value}</i> };
+	assert_eq!(moved.to_string(), "<i>5</i>");
+}
+
+#[test]
+fn test_html() {
+	let title = "'quoted' \"title\" & <more>";
+	let render = |html: bool| if html {
+		crate::xfmt! { html: <p title={title}>{title}</p> }.to_string()
+	}
+	else {
+		crate::xfmt! { <p title={title}>{title}</p> }.to_string()
+	};
+	assert_eq!(render(false), "<p title=\"&apos;quoted&apos; &quot;title&quot; &amp; &lt;more&gt;\">'quoted' \"title\" &amp; &lt;more&gt;</p>");
+	assert_eq!(render(true), "<p title=\"&#39;quoted&#39; &#34;title&#34; &amp; &lt;more&gt;\">'quoted' \"title\" &amp; &lt;more&gt;</p>");
+
+	// Processing instruction attribute values follow the policy too
+	assert_eq!(crate::xfmt! { html: <?target a={title}?> }.to_string(), "<?target a=\"&#39;quoted&#39; &#34;title&#34; &amp; &lt;more&gt;\"?>");
+}
+
+#[test]
+fn test_trusted() {
+	let name = "caf&#233;";
+	let limit = "a < b";
+	let escaped = crate::xfmt! { <config name={name}><limit>{limit}</limit></config> };
+	let trusted = crate::xfmt! { trusted: <config name={name}><limit>{limit}</limit></config> };
+	assert_eq!(escaped.to_string(), "<config name=\"caf&amp;#233;\"><limit>a &lt; b</limit></config>");
+	assert_eq!(trusted.to_string(), "<config name=\"caf&#233;\"><limit>a < b</limit></config>");
+
+	let value = "&amp;";
+	let moved = crate::xfmt! { move trusted: <i title={value}>{value}</i> };
+	assert_eq!(moved.to_string(), "<i title=\"&amp;\">&amp;</i>");
+}
+
+#[test]
+fn test_newlines() {
+	let text = "a\r\nb\rc\n";
+	let normalized = crate::xfmt! { newlines: <p title={text}>{text}</p> };
+	assert_eq!(normalized.to_string(), "<p title=\"a&#10;b&#10;c&#10;\">a\nb\nc\n</p>");
+	let preserved = crate::xfmt! { preserve_newlines: <p title={text}>{text}</p> };
+	assert_eq!(preserved.to_string(), "<p title=\"a&#13;&#10;b&#13;c&#10;\">a&#13;\nb&#13;c\n</p>");
+
+	// A `\r\n` pair split across writes of a single value
+	let split = crate::fmt(|f| { f.write_str("a\r")?; f.write_str("\nb\r") });
+	assert_eq!(crate::xfmt! { newlines: <p>{split}</p> }.to_string(), "<p>a\nb\n</p>");
+	assert_eq!(crate::xfmt! { preserve_newlines: <p>{split}</p> }.to_string(), "<p>a&#13;\nb&#13;</p>");
+}
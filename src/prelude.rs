@@ -1,11 +1,35 @@
 /*!
 Replace the standard formatting macros using [xfmt syntax](crate::xfmt!).
+
+These macros are only available through this module, import them by name to shadow the standard macros:
+
+```
+use format_xml::prelude::{format, println};
+
+println!(<a>"x"</a>);
+let string = format!(<a href="/">"link"</a>);
+assert_eq!(string, r#"<a href="/">link</a>"#);
+```
+
+A glob import of this module is ambiguous with the standard macros and is rejected by the compiler.
+
+Crates which do not import this module are unaffected:
+
+```
+let string = format!("{}", 1);
+assert_eq!(string, "1");
+```
 */
 
+#[cfg(feature = "std")]
+pub use crate::{__print as print, __println as println, __eprint as eprint, __eprintln as eprintln, __format as format};
+pub use crate::{__write as write, __writeln as writeln, __format_args as format_args, __panic as panic};
+
 /// Replaces `print!` using [xfmt syntax](crate::xfmt!).
 #[cfg(feature = "std")]
+#[doc(hidden)]
 #[macro_export]
-macro_rules! print {
+macro_rules! __print {
 	($($tt:tt)*) => {
 		::std::print!("{}", $crate::fmt(|_f| {
 			$crate::__xfmt!{_f concat() $($tt)*}
@@ -16,8 +40,9 @@ macro_rules! print {
 
 /// Replaces `println!` using [xfmt syntax](crate::xfmt!).
 #[cfg(feature = "std")]
+#[doc(hidden)]
 #[macro_export]
-macro_rules! println {
+macro_rules! __println {
 	($($tt:tt)*) => {
 		::std::print!("{}", $crate::fmt(|_f| {
 			$crate::__xfmt!{_f concat() $($tt)* "\n"}
@@ -28,8 +53,9 @@ macro_rules! println {
 
 /// Replaces `eprint!` using [xfmt syntax](crate::xfmt!).
 #[cfg(feature = "std")]
+#[doc(hidden)]
 #[macro_export]
-macro_rules! eprint {
+macro_rules! __eprint {
 	($($tt:tt)*) => {
 		::std::eprint!("{}", $crate::fmt(|_f| {
 			$crate::__xfmt!{_f concat() $($tt)*}
@@ -40,8 +66,9 @@ macro_rules! eprint {
 
 /// Replaces `eprintln!` using [xfmt syntax](crate::xfmt!).
 #[cfg(feature = "std")]
+#[doc(hidden)]
 #[macro_export]
-macro_rules! eprintln {
+macro_rules! __eprintln {
 	($($tt:tt)*) => {
 		::std::eprint!("{}", $crate::fmt(|_f| {
 			$crate::__xfmt!{_f concat() $($tt)* "\n"}
@@ -51,8 +78,9 @@ macro_rules! eprintln {
 }
 
 /// Replaces `write!` using [xfmt syntax](crate::xfmt!).
+#[doc(hidden)]
 #[macro_export]
-macro_rules! write {
+macro_rules! __write {
 	($dst:expr, $($tt:tt)*) => {
 		::core::write!($dst, "{}", $crate::fmt(|_f| {
 			$crate::__xfmt!{_f concat() $($tt)*}
@@ -62,8 +90,9 @@ macro_rules! write {
 }
 
 /// Replaces `writeln!` using [xfmt syntax](crate::xfmt!).
+#[doc(hidden)]
 #[macro_export]
-macro_rules! writeln {
+macro_rules! __writeln {
 	($dst:expr, $($tt:tt)*) => {
 		::core::write!($dst, "{}", $crate::fmt(|_f| {
 			$crate::__xfmt!{_f concat() $($tt)* "\n"}
@@ -74,8 +103,9 @@ macro_rules! writeln {
 
 /// Replaces `format!` using [xfmt syntax](crate::xfmt!).
 #[cfg(feature = "std")]
+#[doc(hidden)]
 #[macro_export]
-macro_rules! format {
+macro_rules! __format {
 	($($tt:tt)*) => {
 		::std::format!("{}", $crate::fmt(|_f| {
 			$crate::__xfmt!{_f concat() $($tt)*}
@@ -85,8 +115,9 @@ macro_rules! format {
 }
 
 /// Replaces `format_args!` using [xfmt syntax](crate::xfmt!).
+#[doc(hidden)]
 #[macro_export]
-macro_rules! format_args {
+macro_rules! __format_args {
 	($($tt:tt)*) => {
 		::core::format_args!("{}", $crate::fmt(|_f| {
 			$crate::__xfmt!{_f concat() $($tt)*}
@@ -96,8 +127,9 @@ macro_rules! format_args {
 }
 
 /// Replaces `panic!` using [xfmt syntax](crate::xfmt!).
+#[doc(hidden)]
 #[macro_export]
-macro_rules! panic {
+macro_rules! __panic {
 	($($tt:tt)*) => {
 		::core::panic!("{}", $crate::fmt(|_f| {
 			$crate::__xfmt!{_f concat() $($tt)*}
@@ -106,16 +138,175 @@ macro_rules! panic {
 	};
 }
 
+// Deprecated root aliases, the macros have moved to the prelude module
+
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[deprecated(since = "0.3.1", note = "use `format_xml::prelude::print!` instead")]
+#[macro_export]
+macro_rules! print {
+	($($tt:tt)*) => { $crate::__print!{$($tt)*} };
+}
+
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[deprecated(since = "0.3.1", note = "use `format_xml::prelude::println!` instead")]
+#[macro_export]
+macro_rules! println {
+	($($tt:tt)*) => { $crate::__println!{$($tt)*} };
+}
+
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[deprecated(since = "0.3.1", note = "use `format_xml::prelude::eprint!` instead")]
+#[macro_export]
+macro_rules! eprint {
+	($($tt:tt)*) => { $crate::__eprint!{$($tt)*} };
+}
+
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[deprecated(since = "0.3.1", note = "use `format_xml::prelude::eprintln!` instead")]
+#[macro_export]
+macro_rules! eprintln {
+	($($tt:tt)*) => { $crate::__eprintln!{$($tt)*} };
+}
+
+#[doc(hidden)]
+#[deprecated(since = "0.3.1", note = "use `format_xml::prelude::write!` instead")]
+#[macro_export]
+macro_rules! write {
+	($($tt:tt)*) => { $crate::__write!{$($tt)*} };
+}
+
+#[doc(hidden)]
+#[deprecated(since = "0.3.1", note = "use `format_xml::prelude::writeln!` instead")]
+#[macro_export]
+macro_rules! writeln {
+	($($tt:tt)*) => { $crate::__writeln!{$($tt)*} };
+}
+
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[deprecated(since = "0.3.1", note = "use `format_xml::prelude::format!` instead")]
+#[macro_export]
+macro_rules! format {
+	($($tt:tt)*) => { $crate::__format!{$($tt)*} };
+}
+
+#[doc(hidden)]
+#[deprecated(since = "0.3.1", note = "use `format_xml::prelude::format_args!` instead")]
+#[macro_export]
+macro_rules! format_args {
+	($($tt:tt)*) => { $crate::__format_args!{$($tt)*} };
+}
+
+#[doc(hidden)]
+#[deprecated(since = "0.3.1", note = "use `format_xml::prelude::panic!` instead")]
+#[macro_export]
+macro_rules! panic {
+	($($tt:tt)*) => { $crate::__panic!{$($tt)*} };
+}
+
+/// Prints a labeled [xfmt fragment](crate::xfmt!) to stderr, similar to `dbg!`.
+///
+/// The rendered fragment is prefixed with the file and line of the invocation.
+///
+/// ```
+/// let user = "Casper";
+/// let items = [1, 2, 3];
+/// format_xml::xdbg!(<state user={user} items={items.len()} />);
+/// // Prints `[src/main.rs:4] <state user="Casper" items="3" />`
+/// ```
+///
+/// When the fragment is preceded by an expression and `;` the value of the expression is returned after printing, just like `dbg!`:
+///
+/// ```
+/// let items = [1, 2, 3];
+/// let total = format_xml::xdbg!(items.iter().sum::<i32>(); <total>{items.len()}" items"</total>);
+/// assert_eq!(total, 6);
+/// ```
+///
+/// The expression must not start with a token that also starts a fragment: `<`, a literal, `{`, `|`, `(` or one of the control flow keywords.
+///
+/// Like `dbg!` this prints in both debug and release builds.
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! xdbg {
+	() => {
+		::std::eprintln!("{}", $crate::__xdbg_label!())
+	};
+	(< $($tt:tt)*) => { $crate::__xdbg!{< $($tt)*} };
+	($text:literal $($tt:tt)*) => { $crate::__xdbg!{$text $($tt)*} };
+	({$($e:tt)*} $($tt:tt)*) => { $crate::__xdbg!{{$($e)*} $($tt)*} };
+	(| $($tt:tt)*) => { $crate::__xdbg!{| $($tt)*} };
+	(($($g:tt)*) $($tt:tt)*) => { $crate::__xdbg!{($($g)*) $($tt)*} };
+	(let $($tt:tt)*) => { $crate::__xdbg!{let $($tt)*} };
+	(if $($tt:tt)*) => { $crate::__xdbg!{if $($tt)*} };
+	(for $($tt:tt)*) => { $crate::__xdbg!{for $($tt)*} };
+	(match $($tt:tt)*) => { $crate::__xdbg!{match $($tt)*} };
+	($e:expr; $($tt:tt)*) => {
+		match $e {
+			tmp => {
+				$crate::__xdbg!{$($tt)*}
+				tmp
+			}
+		}
+	};
+}
+
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __xdbg {
+	($($tt:tt)*) => {
+		::std::eprintln!("{}", $crate::__xdbg_label!($($tt)*))
+	};
+}
+
+// Renders the fragment prefixed with the file and line of the invocation
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __xdbg_label {
+	() => {
+		::core::format_args!("[{}:{}]", ::core::file!(), ::core::line!())
+	};
+	($($tt:tt)*) => {
+		::core::format_args!("[{}:{}] {}", ::core::file!(), ::core::line!(), $crate::fmt(|_f| {
+			$crate::__xfmt!{_f concat() $($tt)*}
+			Ok(())
+		}))
+	};
+}
+
 #[test]
 fn test_prelude() {
 	use std::fmt::Write;
-	crate::print!(<a>"print"</a>);
-	crate::println!(<a>"println"</a>);
-	crate::eprint!(<a>"eprint"</a>);
-	crate::eprintln!(<a>"eprintln"</a>);
-	let mut s = crate::format!(<a>"format"</a>);
-	let _ = crate::write!(s, <a>"write"</a>);
-	let _ = crate::writeln!(s, <a>"writeln"</a>);
+	crate::prelude::print!(<a>"print"</a>);
+	crate::prelude::println!(<a>"println"</a>);
+	crate::prelude::eprint!(<a>"eprint"</a>);
+	crate::prelude::eprintln!(<a>"eprintln"</a>);
+	let mut s = crate::prelude::format!(<a>"format"</a>);
+	let _ = crate::prelude::write!(s, <a>"write"</a>);
+	let _ = crate::prelude::writeln!(s, <a>"writeln"</a>);
 	assert_eq!(s, "<a>format</a><a>write</a><a>writeln</a>\n");
 	// panic!(<a>"panic"</a>);
 }
+
+#[test]
+fn test_xdbg() {
+	let items = [1, 2, 3];
+	crate::xdbg!();
+	crate::xdbg!(<state items={items.len()} />);
+	crate::xdbg!("text" {items[0]});
+	crate::xdbg!(for i in (&items) { <i>{i}</i> });
+	let len = crate::xdbg!(items.len(); <len>{items.len()}</len>);
+	assert_eq!(len, 3);
+
+	// The label is the file and line of the invocation
+	let (label, line) = (crate::__xdbg_label!(<state items={items.len()} />).to_string(), line!());
+	assert_eq!(label, std::format!("[src/prelude.rs:{}] <state items=\"3\" />", line));
+	let (label, line) = (crate::__xdbg_label!().to_string(), line!());
+	assert_eq!(label, std::format!("[src/prelude.rs:{}]", line));
+}
@@ -0,0 +1,281 @@
+// This module implements indenting the rendered markup for debugging
+
+use std::fmt::{self, Write};
+
+/// The elements whose content is written as is by default.
+pub const PRETTY_PRESERVE: &[&str] = &["pre", "textarea", "script", "style"];
+
+// The html elements which have no closing tag
+const VOID_ELEMENTS: &[&str] = &["area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr"];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Token<'a> {
+	Open(&'a str, &'a str),
+	Close(&'a str, &'a str),
+	Empty(&'a str),
+	Text(&'a str),
+}
+
+// Splits the markup in tags and text, the tokens are slices of the markup including the angle brackets
+fn next_token(s: &str) -> Option<(Token<'_>, &str)> {
+	if s.is_empty() {
+		return None;
+	}
+	if !s.starts_with('<') {
+		let end = s.find('<').unwrap_or(s.len());
+		return Some((Token::Text(&s[..end]), &s[end..]));
+	}
+	let terminator = if s.starts_with("<!--") { "-->" } else if s.starts_with("<![CDATA[") { "]]>" } else if s.starts_with("<?") { "?>" } else if s.starts_with("<!") { ">" } else { "" };
+	if !terminator.is_empty() {
+		let end = s[2..].find(terminator).map(|j| 2 + j + terminator.len()).unwrap_or(s.len());
+		// CDATA sections are text content
+		let token = if s.starts_with("<![CDATA[") { Token::Text(&s[..end]) } else { Token::Empty(&s[..end]) };
+		return Some((token, &s[end..]));
+	}
+	// Find the end of the tag skipping over quoted attribute values
+	let bytes = s.as_bytes();
+	let mut quote = 0;
+	let mut end = s.len();
+	for (i, &byte) in bytes.iter().enumerate().skip(1) {
+		if quote != 0 {
+			if byte == quote {
+				quote = 0;
+			}
+		}
+		else if byte == b'"' || byte == b'\'' {
+			quote = byte;
+		}
+		else if byte == b'>' {
+			end = i + 1;
+			break;
+		}
+	}
+	let tag = &s[..end];
+	let close = tag.starts_with("</");
+	let start = if close { 2 } else { 1 };
+	let name_end = tag[start..].find(|c: char| c.is_whitespace() || c == '>' || c == '/').map(|j| start + j).unwrap_or(tag.len());
+	let name = &tag[start..name_end];
+	let token = if close {
+		Token::Close(name, tag)
+	}
+	else if tag.ends_with("/>") || VOID_ELEMENTS.iter().any(|void| void.eq_ignore_ascii_case(name)) {
+		Token::Empty(tag)
+	}
+	else {
+		Token::Open(name, tag)
+	};
+	Some((token, &s[end..]))
+}
+
+/// Indents the markup of a template for debugging.
+///
+/// See [`pretty`] for more information.
+#[derive(Copy, Clone, Debug)]
+pub struct Pretty<'a, T> {
+	template: T,
+	indent: &'a str,
+	preserve: &'a [&'a str],
+}
+
+impl<'a, T> Pretty<'a, T> {
+	/// Sets the elements whose content is written as is, replacing [`PRETTY_PRESERVE`].
+	#[inline]
+	pub fn preserve(self, preserve: &'a [&'a str]) -> Pretty<'a, T> {
+		Pretty { preserve, ..self }
+	}
+
+	fn write_line(&self, f: &mut fmt::Formatter, first: &mut bool, depth: usize, line: &str) -> fmt::Result {
+		if !*first {
+			f.write_char('\n')?;
+		}
+		*first = false;
+		for _ in 0..depth {
+			f.write_str(self.indent)?;
+		}
+		f.write_str(line)
+	}
+}
+
+impl<'a, T: fmt::Display> fmt::Display for Pretty<'a, T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut markup = String::new();
+		write!(markup, "{}", self.template)?;
+		self.write_pretty(f, &markup)
+	}
+}
+
+impl<'a, T: crate::Render> crate::Render for Pretty<'a, T> {
+	fn render(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut markup = String::new();
+		write!(markup, "{}", crate::fmt(|f| self.template.render(f)))?;
+		self.write_pretty(f, &markup)
+	}
+}
+
+impl<'a, T> Pretty<'a, T> {
+	fn write_pretty(&self, f: &mut fmt::Formatter, markup: &str) -> fmt::Result {
+		let mut first = true;
+		let mut depth = 0usize;
+		let mut rest = markup;
+		while let Some((token, tail)) = next_token(rest) {
+			rest = tail;
+			match token {
+				Token::Open(name, tag) => {
+					// The content of preserved elements is written as is up to the matching close tag
+					if self.preserve.iter().any(|pre| pre.eq_ignore_ascii_case(name)) {
+						let mut nested = 0;
+						let mut end = rest.len();
+						let mut scan = rest;
+						while let Some((token, tail)) = next_token(scan) {
+							match token {
+								Token::Open(other, _) if other == name => nested += 1,
+								Token::Close(other, _) if other == name => {
+									if nested == 0 {
+										end = rest.len() - tail.len();
+										break;
+									}
+									nested -= 1;
+								},
+								_ => (),
+							}
+							scan = tail;
+						}
+						self.write_line(f, &mut first, depth, tag)?;
+						f.write_str(&rest[..end])?;
+						rest = &rest[end..];
+						continue;
+					}
+					// Elements containing only text are kept on a single line
+					let mut scan = rest;
+					let mut inline = None;
+					while let Some((token, tail)) = next_token(scan) {
+						match token {
+							Token::Text(_) => scan = tail,
+							Token::Close(other, _) if other == name => {
+								inline = Some(rest.len() - tail.len());
+								break;
+							},
+							_ => break,
+						}
+					}
+					if let Some(end) = inline {
+						self.write_line(f, &mut first, depth, tag)?;
+						f.write_str(&rest[..end])?;
+						rest = &rest[end..];
+					}
+					else {
+						self.write_line(f, &mut first, depth, tag)?;
+						depth += 1;
+					}
+				},
+				Token::Close(_, tag) => {
+					depth = depth.saturating_sub(1);
+					self.write_line(f, &mut first, depth, tag)?;
+				},
+				Token::Empty(tag) => {
+					self.write_line(f, &mut first, depth, tag)?;
+				},
+				Token::Text(text) => {
+					// Whitespace between the elements is replaced by the indentation
+					if !text.trim().is_empty() {
+						self.write_line(f, &mut first, depth, text)?;
+					}
+				},
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Indents the markup of a template for debugging.
+///
+/// Every element, comment and other markup starts on a new line, indented by its depth.
+/// The text nodes and attribute values are written as is.
+/// Elements containing only text stay on a single line.
+/// The content of the [`PRETTY_PRESERVE`] elements such as `<pre>` is written as is, see [`Pretty::preserve`].
+///
+/// The template is rendered to a buffer when formatted and then indented.
+/// When the template implements [`Render`](crate::Render) the result can be embedded in another template as `{@pretty(..)}`.
+/// Whitespace-only text between elements is dropped and text in mixed content is moved to its own line,
+/// the resulting whitespace may change how html is displayed. Use this for diffing and debugging only.
+///
+/// # Examples
+///
+/// ```
+/// let items = ["a", "b"];
+/// let list = format_xml::xfmt! {
+/// 	<config version="1"><!-- "generated" --><items>for item in (&items) { <item>{item}</item> }</items><empty/></config>
+/// };
+///
+/// assert_eq!(format_xml::pretty(&list, "  ").to_string(), "\
+/// <config version=\"1\">
+///   <!-- generated -->
+///   <items>
+///     <item>a</item>
+///     <item>b</item>
+///   </items>
+///   <empty />
+/// </config>");
+/// ```
+#[inline]
+pub fn pretty<T>(template: T, indent: &str) -> Pretty<'_, T> {
+	Pretty { template, indent, preserve: PRETTY_PRESERVE }
+}
+
+#[test]
+fn test_pretty() {
+	let code = "fn main() {\n\tlet x = 1 < 2;\n}";
+	let page = crate::xfmt! {
+		<!doctype html>
+		<html>
+			<head><meta charset="utf-8"><title>"Title"</title></head>
+			<body class="a>b">
+				<p>"Hello"<b>"World"</b>"!"</p>
+				<br>
+				<pre><code>{code}</code></pre>
+				<p></p>
+			</body>
+		</html>
+	};
+	let compact = page.to_string();
+	let indented = pretty(&page, "\t").to_string();
+	assert_eq!(indented, "\
+<!doctype html>
+<html>
+\t<head>
+\t\t<meta charset=\"utf-8\">
+\t\t<title>Title</title>
+\t</head>
+\t<body class=\"a>b\">
+\t\t<p>
+\t\t\tHello
+\t\t\t<b>World</b>
+\t\t\t!
+\t\t</p>
+\t\t<br>
+\t\t<pre><code>fn main() {
+\tlet x = 1 &lt; 2;
+}</code></pre>
+\t\t<p></p>
+\t</body>
+</html>");
+
+	// Removing the added whitespace gives the compact output
+	let strip = |s: &str| s.replace(['\n', '\t'], "");
+	assert_eq!(strip(&indented), strip(&compact));
+
+	// Preserving no elements indents the content of pre as well
+	let pre = crate::xfmt! { <pre><b>"x"</b><i>"y"</i></pre> };
+	assert_eq!(pretty(&pre, " ").preserve(&[]).to_string(), "<pre>\n <b>x</b>\n <i>y</i>\n</pre>");
+	assert_eq!(pretty(&pre, " ").to_string(), "<pre><b>x</b><i>y</i></pre>");
+	assert_eq!(crate::xfmt! { <div>{@pretty(&pre, " ")}</div> }.to_string(), "<div><pre><b>x</b><i>y</i></pre></div>");
+
+	// Embedding renders the template through Render
+	struct Raw(&'static str);
+	impl crate::Render for Raw {
+		fn render(&self, f: &mut fmt::Formatter) -> fmt::Result {
+			f.write_str(self.0)
+		}
+	}
+	assert_eq!(crate::xfmt! { <div>{@pretty(Raw("<p><b>&amp;</b></p>"), " ")}</div> }.to_string(), "<div><p>\n <b>&amp;</b>\n</p></div>");
+}
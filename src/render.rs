@@ -0,0 +1,105 @@
+// This module implements embedding already escaped markup in xfmt! templates
+
+use core::fmt;
+
+/// Markup which is embedded in templates as is.
+///
+/// Formatting a template as `{inner}` inside another template escapes its markup again.
+/// Embed it as `{@inner}` instead, which calls [`render`](Render::render) with the underlying formatter.
+///
+/// The [`xfmt!`](crate::xfmt) results implement this trait, plain [`Display`](fmt::Display) values do not.
+/// Implement it for types which render trusted markup, such as components of a page.
+///
+/// # Examples
+///
+/// ```
+/// use format_xml::Render;
+///
+/// fn header(title: &str) -> impl Render + '_ {
+/// 	format_xml::xfmt! { move <header><h1>{title}</h1></header> }
+/// }
+///
+/// let title = "Fish & Chips";
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<body>{@header(title)}<main>"..."</main></body>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, "<body><header><h1>Fish &amp; Chips</h1></header><main>...</main></body>");
+/// ```
+///
+/// The resulting string is `<body><header><h1>Fish &amp; Chips</h1></header><main>...</main></body>`.
+///
+/// Optional markup renders nothing when `None`.
+pub trait Render {
+	fn render(&self, f: &mut fmt::Formatter) -> fmt::Result;
+}
+
+impl<F: Fn(&mut fmt::Formatter) -> fmt::Result> Render for crate::fmt<F> {
+	#[inline]
+	fn render(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		fmt::Display::fmt(self, f)
+	}
+}
+impl<T: ?Sized + Render> Render for &T {
+	#[inline]
+	fn render(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		(**self).render(f)
+	}
+}
+impl<T: ?Sized + Render> Render for &mut T {
+	#[inline]
+	fn render(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		(**self).render(f)
+	}
+}
+impl<T: ?Sized + Render> Render for Box<T> {
+	#[inline]
+	fn render(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		(**self).render(f)
+	}
+}
+impl<T: Render> Render for Option<T> {
+	#[inline]
+	fn render(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Some(markup) => markup.render(f),
+			None => Ok(()),
+		}
+	}
+}
+
+// Renders through any writer, such as the escaping writers of CDATA sections and comments
+#[doc(hidden)]
+pub struct RenderDisplay<'a, T: ?Sized>(pub &'a T);
+impl<T: ?Sized + Render> fmt::Display for RenderDisplay<'_, T> {
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.render(f)
+	}
+}
+
+#[test]
+fn test_render() {
+	fn avatar(name: &str) -> impl Render + '_ {
+		crate::xfmt! { move <img alt={name} /> }
+	}
+	fn card(name: &str) -> impl Render + '_ {
+		crate::xfmt! { move <div class="card">{@avatar(name)}<span>{name}</span></div> }
+	}
+	fn page<'a>(name: &'a str, footer: Option<&'a dyn Render>) -> impl Render + 'a {
+		crate::xfmt! { move <main>{@card(name)}</main>{@footer} }
+	}
+
+	let user = "<Tom & Jerry>";
+	let footer = crate::xfmt! { <footer>"&copy;"</footer> };
+	assert_eq!(crate::xfmt! { {@page(user, None)} }.to_string(), r#"<main><div class="card"><img alt="&lt;Tom &amp; Jerry&gt;" /><span>&lt;Tom &amp; Jerry&gt;</span></div></main>"#);
+	assert!(crate::xfmt! { {@page(user, Some(&footer))} }.to_string().ends_with("</main><footer>&copy;</footer>"));
+
+	let boxed: Vec<Box<dyn Render>> = vec![Box::new(avatar("a")), Box::new(crate::xfmt! { <br/> })];
+	assert_eq!(crate::xfmt! { minimal: for c in (&boxed) { {@c} } }.to_string(), r#"<img alt="a" /><br />"#);
+
+	let code = crate::xfmt! { <code>"a]]>b"</code> };
+	assert_eq!(crate::xfmt! { <![CDATA[{@code}]]> }.to_string(), "<![CDATA[<code>a]]]]><![CDATA[>b</code>]]>");
+	assert_eq!(crate::xfmt! { <!-- {@footer} --> }.to_string(), "<!-- <footer>&copy;</footer> -->");
+}
@@ -0,0 +1,176 @@
+// This module implements stripping markup from untrusted rich text
+
+use core::fmt;
+
+const ENTITIES: &[(&str, &str)] = &[("&amp;", "&"), ("&lt;", "<"), ("&gt;", ">"), ("&quot;", "\""), ("&apos;", "'"), ("&#39;", "'")];
+
+/// Strips markup from untrusted rich text, see [`sanitize_text`].
+#[derive(Copy, Clone, Debug)]
+pub struct SanitizeText<'a> {
+	input: &'a str,
+	collapse: bool,
+}
+
+/// Strips markup and keeps the text.
+///
+/// Removes tags, comments and the contents of `<script>` and `<style>` elements.
+/// The basic entities `&amp;`, `&lt;`, `&gt;`, `&quot;`, `&apos;` and `&#39;` are decoded and other entities are kept as is.
+/// The result is plain text which is escaped when used as `{value}` in templates, wrap it in [`escape_text`](crate::escape_text) when formatting it elsewhere.
+///
+/// A `<` which is not followed by a letter, `/`, `!` or `?` is kept as text.
+/// Quoted attribute values may contain `>` and unterminated tags or comments are dropped until the end of the input.
+///
+/// This is not an HTML parser, use it for previews and descriptions and not to preserve content.
+///
+/// # Examples
+///
+/// ```
+/// let comment = "<p>I <b>love</b> <i>a < b</i> &amp; cake!</p><script>alert('hi')</script>";
+/// assert_eq!(format_xml::sanitize_text(comment).to_string(), "I love a < b & cake!");
+/// assert_eq!(format_xml::xfmt! { <p>{format_xml::sanitize_text(comment)}</p> }.to_string(), "<p>I love a &lt; b &amp; cake!</p>");
+///
+/// let render = |text: &str| format_xml::xfmt! {
+/// 	<meta name="description" content={format_xml::sanitize_text(text).collapse_whitespace()} />
+/// }.to_string();
+/// assert_eq!(render("\n<h1 title=\"a > b\">Hello</h1>\n<!-- note -->\n<p>world</p>\n"), r#"<meta name="description" content="Hello world" />"#);
+/// ```
+#[inline]
+pub fn sanitize_text(input: &str) -> SanitizeText<'_> {
+	SanitizeText { input, collapse: false }
+}
+
+impl<'a> SanitizeText<'a> {
+	/// Collapses runs of whitespace into a single space and trims leading and trailing whitespace.
+	#[inline]
+	pub fn collapse_whitespace(self) -> SanitizeText<'a> {
+		SanitizeText { collapse: true, ..self }
+	}
+}
+
+impl<'a> fmt::Display for SanitizeText<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut sink = Sink { out: f, collapse: self.collapse, started: false, space: false };
+		let s = self.input;
+		let bytes = s.as_bytes();
+		let mut text_start = 0;
+		let mut i = 0;
+		while i < bytes.len() {
+			if bytes[i] != b'<' || !matches!(bytes.get(i + 1), Some(c) if c.is_ascii_alphabetic() || matches!(c, b'/' | b'!' | b'?')) {
+				i += 1;
+				continue;
+			}
+			sink.text(&s[text_start..i])?;
+			if s[i..].starts_with("<!--") {
+				i = s[i + 4..].find("-->").map(|j| i + 4 + j + 3).unwrap_or(s.len());
+			}
+			else {
+				let name_len = bytes[i + 1..].iter().take_while(|c| c.is_ascii_alphanumeric()).count();
+				let name = &s[i + 1..i + 1 + name_len];
+				i = tag_end(bytes, i + 1);
+				if name.eq_ignore_ascii_case("script") || name.eq_ignore_ascii_case("style") {
+					i = raw_text_end(s, i, name);
+				}
+			}
+			text_start = i;
+		}
+		sink.text(&s[text_start..])
+	}
+}
+
+// Returns the index after the closing `>` of the tag, skipping over quoted attribute values
+fn tag_end(bytes: &[u8], mut i: usize) -> usize {
+	while i < bytes.len() {
+		match bytes[i] {
+			b'>' => return i + 1,
+			quote @ (b'"' | b'\'') => {
+				i += 1;
+				while i < bytes.len() && bytes[i] != quote {
+					i += 1;
+				}
+			},
+			_ => (),
+		}
+		i += 1;
+	}
+	bytes.len()
+}
+
+// Returns the index after the closing tag of a script or style element
+fn raw_text_end(s: &str, mut i: usize, name: &str) -> usize {
+	let bytes = s.as_bytes();
+	while let Some(j) = s[i..].find("</") {
+		let start = i + j + 2;
+		let end = start + name.len();
+		if end <= s.len() && bytes[start..end].eq_ignore_ascii_case(name.as_bytes()) {
+			return tag_end(bytes, end);
+		}
+		i = start;
+	}
+	s.len()
+}
+
+struct Sink<'a, 'b> {
+	out: &'a mut fmt::Formatter<'b>,
+	collapse: bool,
+	started: bool,
+	space: bool,
+}
+impl<'a, 'b> Sink<'a, 'b> {
+	fn text(&mut self, mut s: &str) -> fmt::Result {
+		while let Some(pos) = s.find('&') {
+			self.write(&s[..pos])?;
+			let rest = &s[pos..];
+			let (decoded, len) = ENTITIES.iter()
+				.find(|(entity, _)| rest.starts_with(entity))
+				.map(|&(entity, decoded)| (decoded, entity.len()))
+				.unwrap_or(("&", 1));
+			self.write(decoded)?;
+			s = &rest[len..];
+		}
+		self.write(s)
+	}
+	fn write(&mut self, s: &str) -> fmt::Result {
+		if !self.collapse {
+			return self.out.write_str(s);
+		}
+		for (i, word) in s.split(char::is_whitespace).enumerate() {
+			if i != 0 {
+				self.space = true;
+			}
+			if !word.is_empty() {
+				if self.space && self.started {
+					self.out.write_str(" ")?;
+				}
+				self.space = false;
+				self.started = true;
+				self.out.write_str(word)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+#[test]
+fn test_sanitize() {
+	#[track_caller]
+	fn check(input: &str, expected: &str) {
+		assert_eq!(sanitize_text(input).to_string(), expected);
+	}
+	check("plain text, 100% untouched", "plain text, 100% untouched");
+	check("<div><p>nested <b><i>tags</i></b></p></div>", "nested tags");
+	check("a<script type=\"text/javascript\">if (a < b) { x = '</p>'; }</SCRIPT>b", "ab");
+	check("a<style>p > b { }</style >b", "ab");
+	check("<script>never closed", "");
+	check("x < y && y<3", "x < y && y<3");
+	check("<a title='1 > 0' href=\"#\">link</a>", "link");
+	check("a<!-- <b>hidden</b> -->b<!-- unterminated", "ab");
+	check("&lt;b&gt; &quot;&apos;&#39; &amp;amp; &nbsp;", "<b> \"'' &amp; &nbsp;");
+	check("unterminated <b", "unterminated ");
+	check("<?xml version=\"1.0\"?><!DOCTYPE html>text", "text");
+	assert_eq!(sanitize_text("  a \n\t<br> b<p>  </p>c ").collapse_whitespace().to_string(), "a b c");
+
+	// Escaped exactly once in templates
+	let input = "<b>a &lt; b</b> & \"c\"";
+	let page = crate::xfmt! { <p title={sanitize_text(input)}>{sanitize_text(input)}</p> }.to_string();
+	assert_eq!(page, "<p title=\"a &lt; b &amp; &quot;c&quot;\">a &lt; b &amp; \"c\"</p>");
+}
@@ -0,0 +1,224 @@
+/*!
+Helpers to compare rendered documents in tests.
+*/
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token<'a> {
+	Open(&'a str, Vec<(&'a str, &'a str)>),
+	Close(&'a str),
+	Text(&'a str),
+	Other(&'a str),
+}
+
+fn tokenize(s: &str) -> Vec<Token<'_>> {
+	let bytes = s.as_bytes();
+	let mut tokens = Vec::new();
+	let mut i = 0;
+	while i < bytes.len() {
+		if bytes[i] != b'<' {
+			let end = s[i..].find('<').map(|j| i + j).unwrap_or(s.len());
+			tokens.push(Token::Text(&s[i..end]));
+			i = end;
+			continue;
+		}
+		let rest = &s[i..];
+		let terminator = if rest.starts_with("<!--") { "-->" } else if rest.starts_with("<![CDATA[") { "]]>" } else if rest.starts_with("<!") || rest.starts_with("<?") { ">" } else { "" };
+		if !terminator.is_empty() {
+			let end = rest[2..].find(terminator).map(|j| i + 2 + j + terminator.len()).unwrap_or(s.len());
+			tokens.push(Token::Other(&s[i..end]));
+			i = end;
+			continue;
+		}
+		if rest.starts_with("</") {
+			let end = rest.find('>').map(|j| i + j + 1).unwrap_or(s.len());
+			tokens.push(Token::Close(s[i + 2..end].trim_end_matches('>').trim()));
+			i = end;
+			continue;
+		}
+		i += 1;
+		let name_end = s[i..].find(|c: char| c.is_whitespace() || c == '>' || c == '/').map(|j| i + j).unwrap_or(s.len());
+		let name = &s[i..name_end];
+		i = name_end;
+		let mut attrs = Vec::new();
+		let mut self_closing = false;
+		while i < bytes.len() {
+			match bytes[i] {
+				b'>' => { i += 1; break; },
+				b'/' => { self_closing = true; i += 1; },
+				chr if chr.is_ascii_whitespace() => i += 1,
+				_ => {
+					let key_end = s[i..].find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/').map(|j| i + j).unwrap_or(s.len());
+					let key = &s[i..key_end];
+					i = key_end;
+					let mut value = "";
+					if bytes.get(i) == Some(&b'=') {
+						i += 1;
+						match bytes.get(i) {
+							Some(&quote @ (b'"' | b'\'')) => {
+								let end = s[i + 1..].find(quote as char).map(|j| i + 1 + j).unwrap_or(s.len());
+								value = &s[i + 1..end];
+								i = usize::min(end + 1, s.len());
+							},
+							_ => {
+								let end = s[i..].find(|c: char| c.is_whitespace() || c == '>').map(|j| i + j).unwrap_or(s.len());
+								value = &s[i..end];
+								i = end;
+							},
+						}
+					}
+					attrs.push((key, value));
+				},
+			}
+		}
+		tokens.push(Token::Open(name, attrs));
+		if self_closing {
+			tokens.push(Token::Close(name));
+		}
+	}
+	tokens
+}
+
+/// Options for [`diff_xml_with`].
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DiffOptions {
+	/// Ignores text nodes which contain only whitespace.
+	pub ignore_whitespace: bool,
+}
+
+/// The first difference between two documents, see [`diff_xml`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XmlDiff {
+	path: String,
+	message: String,
+}
+impl XmlDiff {
+	/// Returns the path to the element where the documents differ, eg. `/svg/text[1]`.
+	#[inline]
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+	/// Returns the description of the difference.
+	#[inline]
+	pub fn message(&self) -> &str {
+		&self.message
+	}
+}
+impl fmt::Display for XmlDiff {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let path = if self.path.is_empty() { "/" } else { &self.path };
+		write!(f, "element {}: {}", path, self.message)
+	}
+}
+
+/// Compares two documents element by element and attribute by attribute.
+///
+/// Returns `None` if the documents are equivalent, otherwise describes the first difference.
+/// Attribute order, quote style and self-closing tags versus empty elements are not considered differences.
+///
+/// This is meant for tests and uses a lightweight tokenizer, it does not validate the documents.
+///
+/// # Examples
+///
+/// ```
+/// let actual = format_xml::xfmt! {
+/// 	<svg width="200"><line x1="0" /><text x="20" y="30">"Hello"</text></svg>
+/// }.to_string();
+///
+/// let expected = r#"<svg width="200"><line x1="0"></line><text y="30" x="30">Hello</text></svg>"#;
+/// let diff = format_xml::diff_xml(&actual, expected).unwrap();
+/// assert_eq!(diff.to_string(), r#"element /svg/text[1]: attribute x expected "30" got "20""#);
+///
+/// assert_eq!(format_xml::diff_xml(&actual, &actual), None);
+/// ```
+pub fn diff_xml(actual: &str, expected: &str) -> Option<XmlDiff> {
+	diff_xml_with(actual, expected, &DiffOptions::default())
+}
+
+/// Compares two documents with options, see [`diff_xml`].
+///
+/// # Examples
+///
+/// ```
+/// let options = format_xml::DiffOptions { ignore_whitespace: true };
+/// assert_eq!(format_xml::diff_xml_with("<ul>\n\t<li>a</li>\n</ul>", "<ul><li>a</li></ul>", &options), None);
+/// ```
+pub fn diff_xml_with(actual: &str, expected: &str, options: &DiffOptions) -> Option<XmlDiff> {
+	let keep = |token: &Token| !(options.ignore_whitespace && matches!(token, Token::Text(text) if text.trim().is_empty()));
+	let mut actual = tokenize(actual).into_iter().filter(keep);
+	let mut expected = tokenize(expected).into_iter().filter(keep);
+
+	let mut path: Vec<String> = Vec::new();
+	let mut counts: Vec<Vec<(&str, usize)>> = vec![Vec::new()];
+	let diff = |path: &[String], message: String| Some(XmlDiff { path: path.concat(), message });
+
+	loop {
+		let (a, e) = match (actual.next(), expected.next()) {
+			(None, None) => return None,
+			(Some(a), Some(e)) => (a, e),
+			(Some(a), None) => return diff(&path, format!("unexpected {}", describe(&a))),
+			(None, Some(e)) => return diff(&path, format!("missing {}", describe(&e))),
+		};
+		match (&a, &e) {
+			(Token::Open(a_name, a_attrs), Token::Open(e_name, e_attrs)) if a_name == e_name => {
+				let level = counts.last_mut().unwrap();
+				let index = match level.iter_mut().find(|(name, _)| name == e_name) {
+					Some((_, count)) => { *count += 1; *count },
+					None => { level.push((e_name, 1)); 1 },
+				};
+				path.push(if path.is_empty() { format!("/{}", e_name) } else { format!("/{}[{}]", e_name, index) });
+				counts.push(Vec::new());
+				for &(key, e_value) in e_attrs {
+					match a_attrs.iter().find(|&&(k, _)| k == key) {
+						None => return diff(&path, format!("attribute {} missing, expected \"{}\"", key, e_value)),
+						Some(&(_, a_value)) if a_value != e_value => return diff(&path, format!("attribute {} expected \"{}\" got \"{}\"", key, e_value, a_value)),
+						_ => (),
+					}
+				}
+				if let Some((key, value)) = a_attrs.iter().find(|&&(key, _)| !e_attrs.iter().any(|&(k, _)| k == key)) {
+					return diff(&path, format!("unexpected attribute {}=\"{}\"", key, value));
+				}
+			},
+			(Token::Close(a_name), Token::Close(e_name)) if a_name == e_name => {
+				path.pop();
+				counts.pop();
+			},
+			_ if a != e => return diff(&path, format!("expected {} got {}", describe(&e), describe(&a))),
+			_ => (),
+		}
+	}
+}
+
+fn describe(token: &Token) -> String {
+	match token {
+		Token::Open(name, _) => format!("<{}>", name),
+		Token::Close(name) => format!("</{}>", name),
+		Token::Text(text) => format!("text {:?}", text),
+		Token::Other(other) => format!("{:?}", other),
+	}
+}
+
+#[test]
+fn test_diff_xml() {
+	let doc = r#"<!doctype html><html lang="en"><body><p class='a'>x</p><p>y<br/></p></body></html>"#;
+	assert_eq!(diff_xml(doc, doc), None);
+	assert_eq!(diff_xml(r#"<a x="1" y='2'/>"#, r#"<a y="2" x="1"></a>"#), None);
+
+	let diff = diff_xml(r#"<html><body><p>x</p><p>y</p></body></html>"#, r#"<html><body><p>x</p><p>y</p><p>z</p></body></html>"#).unwrap();
+	assert_eq!(diff.to_string(), "element /html/body[1]: expected <p> got </body>");
+
+	let diff = diff_xml(r#"<html><body><p>x</p></body></html>"#, r#"<html><body><p>x</p></body><footer/></html>"#).unwrap();
+	assert_eq!(diff.path(), "/html");
+	assert_eq!(diff.message(), "expected <footer> got </html>");
+
+	let diff = diff_xml(r#"<root><p>x</p></root>"#, r#"<root><p>x</p></root><!-- end -->"#).unwrap();
+	assert_eq!(diff.to_string(), "element /: missing \"<!-- end -->\"");
+
+	let diff = diff_xml(r#"<a><b x="1" y="2"/></a>"#, r#"<a><b x="1"/></a>"#).unwrap();
+	assert_eq!(diff.to_string(), "element /a/b[1]: unexpected attribute y=\"2\"");
+
+	let diff = diff_xml("<p> </p>", "<p></p>").unwrap();
+	assert_eq!(diff.to_string(), "element /p: expected </p> got text \" \"");
+	assert_eq!(diff_xml_with("<p> </p>", "<p></p>", &DiffOptions { ignore_whitespace: true }), None);
+}
@@ -0,0 +1,166 @@
+// This module implements decoding xml entities
+
+use std::borrow::Cow;
+use std::{error, fmt};
+
+// Decodes the entity at the start of the input
+// Returns the decoded character and the length of the entity
+fn decode_entity(s: &str) -> Option<(char, usize)> {
+	// Stop at the first byte which cannot be part of an entity, searching for `;` in the whole input is quadratic
+	let end = s.bytes().skip(1).position(|b| !(b.is_ascii_alphanumeric() || b == b'#'))? + 1;
+	if s.as_bytes()[end] != b';' {
+		return None;
+	}
+	let name = &s[1..end];
+	let chr = match name {
+		"lt" => '<',
+		"gt" => '>',
+		"amp" => '&',
+		"apos" => '\'',
+		"quot" => '"',
+		_ => {
+			let num = name.strip_prefix('#')?;
+			let (digits, radix) = match num.strip_prefix('x').or_else(|| num.strip_prefix('X')) {
+				Some(hex) => (hex, 16),
+				None => (num, 10),
+			};
+			if digits.is_empty() || !digits.chars().all(|chr| chr.is_digit(radix)) {
+				return None;
+			}
+			let code = u32::from_str_radix(digits, radix).ok()?;
+			if code == 0 {
+				return None;
+			}
+			char::from_u32(code)?
+		},
+	};
+	Some((chr, end + 1))
+}
+
+fn unescape_with(input: &str, strict: bool) -> Result<Cow<'_, str>, UnescapeError> {
+	let mut pos = match input.find('&') {
+		Some(pos) => pos,
+		None => return Ok(Cow::Borrowed(input)),
+	};
+	let mut output = String::with_capacity(input.len());
+	output.push_str(&input[..pos]);
+	loop {
+		let rest = &input[pos..];
+		let len = match decode_entity(rest) {
+			Some((chr, len)) => {
+				output.push(chr);
+				len
+			},
+			None if strict => return Err(UnescapeError { offset: pos }),
+			None => {
+				output.push('&');
+				1
+			},
+		};
+		pos += len;
+		match input[pos..].find('&') {
+			Some(next) => {
+				output.push_str(&input[pos..pos + next]);
+				pos += next;
+			},
+			None => {
+				output.push_str(&input[pos..]);
+				return Ok(Cow::Owned(output));
+			},
+		}
+	}
+}
+
+/// Decodes xml entities.
+///
+/// Decodes `&lt;`, `&gt;`, `&amp;`, `&apos;`, `&quot;` and the decimal `&#NN;` and hexadecimal `&#xHH;` character references.
+/// Unknown or malformed entities are left untouched, see [`unescape_strict`] to reject them instead.
+///
+/// Returns the input borrowed if it contains no entities.
+///
+/// # Examples
+///
+/// ```
+/// use std::borrow::Cow;
+///
+/// assert_eq!(format_xml::unescape("&lt;b&gt;Tom &amp; Jerry&lt;/b&gt;"), "<b>Tom & Jerry</b>");
+/// assert_eq!(format_xml::unescape("caf&#233; &#x1F980;"), "café 🦀");
+/// assert_eq!(format_xml::unescape("R&D &nbsp; &#xZZ;"), "R&D &nbsp; &#xZZ;");
+/// assert!(matches!(format_xml::unescape("plain text"), Cow::Borrowed(_)));
+/// ```
+pub fn unescape(input: &str) -> Cow<'_, str> {
+	match unescape_with(input, false) {
+		Ok(output) => output,
+		Err(_) => unreachable!(),
+	}
+}
+
+/// Decodes xml entities, rejecting unknown or malformed entities.
+///
+/// See [`unescape`] for the supported entities.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(format_xml::unescape_strict("a &amp; b").unwrap(), "a & b");
+///
+/// let err = format_xml::unescape_strict("a & b").unwrap_err();
+/// assert_eq!(err.offset(), 2);
+/// ```
+pub fn unescape_strict(input: &str) -> Result<Cow<'_, str>, UnescapeError> {
+	unescape_with(input, true)
+}
+
+/// Error returned by [`unescape_strict`] for unknown or malformed entities.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UnescapeError {
+	offset: usize,
+}
+impl UnescapeError {
+	/// Returns the byte offset of the `&` starting the invalid entity.
+	#[inline]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+}
+impl fmt::Display for UnescapeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "invalid entity at offset {}", self.offset)
+	}
+}
+impl error::Error for UnescapeError {}
+
+#[test]
+fn test_unescape() {
+	#[track_caller]
+	fn check(input: &str, expected: &str) {
+		assert_eq!(unescape(input), expected);
+	}
+
+	check("", "");
+	check("&", "&");
+	check("&;", "&;");
+	check("a & b; c", "a & b; c");
+	check("&amp;amp;", "&amp;");
+	check("&lt;&gt;&amp;&apos;&quot;", "<>&'\"");
+	check("&LT; &unknown; &amp", "&LT; &unknown; &amp");
+	check("&#65;&#x42;&#X43;", "ABC");
+	check("&#xe9;&#x20AC;&#128512;", "é€😀");
+	check("&#; &#x; &#0; &#xD800; &#x110000; &#99999999999; &#-1; &#+1;", "&#; &#x; &#0; &#xD800; &#x110000; &#99999999999; &#-1; &#+1;");
+	check("<p title=\"&quot;&#10;\">&amp;&&lt;</p>", "<p title=\"\"\n\">&&<</p>");
+
+	let value = "line 1\n\t'<Tom & Jerry>'\r\n🦀";
+	let rendered = crate::escape_attr(value).to_string();
+	assert_eq!(unescape(&rendered), value);
+
+	assert!(matches!(unescape("no entities"), Cow::Borrowed("no entities")));
+	assert_eq!(unescape_strict("&lt;&#x41;").unwrap(), "<A");
+	assert_eq!(unescape_strict("ok &amp; &bad;"), Err(UnescapeError { offset: 9 }));
+	assert_eq!(unescape_strict("&").unwrap_err().to_string(), "invalid entity at offset 0");
+
+	// Many ampersands without a terminating `;` are decoded in linear time
+	let input = "&a".repeat(200_000);
+	assert!(matches!(unescape(&input), Cow::Owned(ref output) if *output == input));
+	let input = format!("{};", "&".repeat(200_000));
+	assert_eq!(unescape(&input), input);
+}
@@ -0,0 +1,359 @@
+// This module implements display helpers to compose with xfmt! templates
+
+use core::{fmt, iter};
+use core::convert::TryInto;
+
+/// Displays the items of an iterable in groups of `n`.
+///
+/// The `group` closure is called once per group with an iterator over the items in that group.
+/// The last group contains the remaining items if the number of items is not a multiple of `n`.
+///
+/// # Panics
+///
+/// Panics if `n` is zero.
+///
+/// # Examples
+///
+/// ```
+/// let items = [1, 2, 3, 4, 5, 6, 7, 8];
+///
+/// let rows = format_xml::chunked(3, &items, |f, row| {
+/// 	format_xml::prelude::write!(f, <tr> for item in (row.clone()) { <td>{item}</td> } </tr>)
+/// });
+///
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<table>|f| f.write_fmt(format_args!("{}", rows))?;</table>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, "<table><tr><td>1</td><td>2</td><td>3</td></tr><tr><td>4</td><td>5</td><td>6</td></tr><tr><td>7</td><td>8</td></tr></table>");
+/// ```
+///
+/// The resulting string is `<table><tr><td>1</td><td>2</td><td>3</td></tr><tr><td>4</td><td>5</td><td>6</td></tr><tr><td>7</td><td>8</td></tr></table>`.
+///
+/// The groups are written unescaped, use the [escape hatch](crate::xfmt!#escape-hatch) to compose them with templates.
+/// The groups are written through a [`BufferedFmt`](crate::BufferedFmt) and the formatter passed to `group` does not carry the outer width or precision.
+#[inline]
+pub fn chunked<T, F>(n: usize, collection: T, group: F) -> impl fmt::Display
+	where T: IntoIterator,
+		T::IntoIter: Clone,
+		F: Fn(&mut fmt::Formatter, iter::Take<T::IntoIter>) -> fmt::Result
+{
+	assert!(n != 0, "chunk size must be non-zero");
+	let iter = collection.into_iter();
+	crate::fmt(move |f| {
+		// Coalesce the many small writes of the groups before they reach the formatter
+		let mut writer = crate::BufferedFmt::<_>::new(f);
+		let mut iter = iter.clone();
+		loop {
+			let chunk = iter.clone().take(n);
+			if chunk.clone().next().is_none() {
+				break;
+			}
+			fmt::write(&mut writer, format_args!("{}", crate::fmt(|f| group(f, chunk.clone()))))?;
+			iter.nth(n - 1);
+		}
+		writer.finish()
+	})
+}
+
+/// Renders the value immediately and returns a displayable object owning the result.
+///
+/// This detaches a template from the data it borrows so it can be stored or sent to another thread.
+///
+/// # Examples
+///
+/// ```
+/// let name = String::from("World");
+/// let greeting = format_xml::owned(format_xml::xfmt! { <h1>"Hello "{name}</h1> });
+/// drop(name);
+///
+/// let result = std::thread::spawn(move || {
+/// 	format_xml::xfmt! { <body>|f| f.write_fmt(format_args!("{}", greeting))?;</body> }.to_string()
+/// }).join().unwrap();
+/// # assert_eq!(result, "<body><h1>Hello World</h1></body>");
+/// ```
+///
+/// The resulting string is `<body><h1>Hello World</h1></body>`.
+#[cfg(feature = "std")]
+#[inline]
+pub fn owned(value: impl fmt::Display) -> impl fmt::Display + fmt::Debug + Send + Sync + 'static {
+	let rendered = value.to_string().into_boxed_str();
+	crate::fmt(move |f| f.write_str(&rendered))
+}
+
+/// Trusted markup which is written without escaping when used as `{value}` in text elements.
+///
+/// See [`slot`] for more information.
+#[derive(Copy, Clone, Debug)]
+#[repr(transparent)]
+pub struct Slot<T>(pub T);
+impl<T: fmt::Display> fmt::Display for Slot<T> {
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.fmt(f)
+	}
+}
+impl<T> crate::NoEscape for Slot<T> {}
+
+/// Marks rendered markup as trusted to fill a hole in a layout template.
+///
+/// Values inside formatting braces are escaped by default, which mangles markup rendered by another template.
+/// Wrapping it in a slot writes it as-is when used as `{value}`, the markup is rendered exactly once.
+///
+/// The children are trusted markup: only pass templates or values which are already escaped.
+///
+/// # Examples
+///
+/// ```
+/// use std::fmt::Display;
+///
+/// fn page<'a>(title: &'a str, body: impl Display + 'a) -> impl Display + 'a {
+/// 	let body = format_xml::slot(body);
+/// 	format_xml::xfmt! { move
+/// 		<html>
+/// 			<head><title>{title}</title></head>
+/// 			<body>{body}</body>
+/// 		</html>
+/// 	}
+/// }
+///
+/// let name = "<World>";
+/// # let result =
+/// page("Tom & Jerry", format_xml::xfmt! { <h1>"Hi "{name}</h1> })
+/// # .to_string();
+/// # assert_eq!(result, "<html><head><title>Tom &amp; Jerry</title></head><body><h1>Hi &lt;World&gt;</h1></body></html>");
+/// ```
+///
+/// The resulting string is `<html><head><title>Tom &amp; Jerry</title></head><body><h1>Hi &lt;World&gt;</h1></body></html>`.
+///
+/// Slots are written as-is in text elements only, in attribute values they are escaped like any other value.
+/// Formatting specifiers such as `{value:>10}` always escape, even for slots.
+#[inline]
+pub fn slot<T: fmt::Display>(children: T) -> Slot<T> {
+	Slot(children)
+}
+
+/// Displays an iterable with given separator between each item, or the fallback if it is empty.
+///
+/// The iterable is traversed only once per render.
+///
+/// # Examples
+///
+/// ```
+/// let tags = ["rust", "xml"];
+/// assert_eq!(format_xml::join_or(", ", &tags, "none").to_string(), "rust, xml");
+///
+/// let tags: [&str; 0] = [];
+/// assert_eq!(format_xml::join_or(", ", &tags, "none").to_string(), "none");
+/// ```
+///
+/// Combined with [`slot`] the fallback can be markup:
+///
+/// ```
+/// let tags = ["a&b"];
+/// let empty: [&str; 0] = [];
+/// let render = |tags: &[&str]| format_xml::xfmt! {
+/// 	<p>{format_xml::slot(format_xml::join_or(", ", tags.iter().map(format_xml::escape_text), format_xml::xfmt! { <em>"none"</em> }))}</p>
+/// }.to_string();
+/// assert_eq!(render(&tags), "<p>a&amp;b</p>");
+/// assert_eq!(render(&empty), "<p><em>none</em></p>");
+/// ```
+#[inline]
+pub fn join_or<T, F>(sep: &'static str, collection: T, fallback: F) -> impl fmt::Display
+	where T: IntoIterator,
+		T::Item: fmt::Display,
+		T::IntoIter: Clone,
+		F: fmt::Display
+{
+	let iter = collection.into_iter();
+	crate::fmt(move |f| {
+		let mut iter = iter.clone();
+		match iter.next() {
+			None => fmt::Display::fmt(&fallback, f),
+			Some(first) => {
+				fmt::Display::fmt(&first, f)?;
+				for item in iter {
+					f.write_str(sep)?;
+					fmt::Display::fmt(&item, f)?;
+				}
+				Ok(())
+			},
+		}
+	})
+}
+
+/// Displays a slice of heterogeneous displayable items with given separator between each item.
+///
+/// Unlike [`join`](crate::join) the items may have different types and no `Clone` bound is required.
+///
+/// # Examples
+///
+/// ```
+/// let n = 42;
+/// let badge = format_xml::xfmt! { <b>{n}</b> };
+/// let name = format_xml::escape_text("Tom & Jerry");
+/// let items: [&dyn std::fmt::Display; 3] = [&badge, &n, &name];
+///
+/// let list = format_xml::join_dyn(" | ", &items);
+/// assert_eq!(list.to_string(), "<b>42</b> | 42 | Tom &amp; Jerry");
+/// assert_eq!(list.to_string(), "<b>42</b> | 42 | Tom &amp; Jerry");
+/// ```
+#[inline]
+pub fn join_dyn<'a>(sep: &'a str, items: &'a [&'a dyn fmt::Display]) -> impl fmt::Display + 'a {
+	crate::fmt(move |f| {
+		for (i, item) in items.iter().enumerate() {
+			if i != 0 {
+				f.write_str(sep)?;
+			}
+			fmt::Display::fmt(item, f)?;
+		}
+		Ok(())
+	})
+}
+
+/// Displays boxed heterogeneous displayable items with given separator between each item.
+///
+/// See [`join_dyn`] for borrowed items.
+///
+/// # Examples
+///
+/// ```
+/// use std::fmt::Display;
+///
+/// let mut items: Vec<Box<dyn Display>> = Vec::new();
+/// items.push(Box::new(format_xml::xfmt! { <i>"x"</i> }));
+/// items.push(Box::new(7));
+/// items.push(Box::new(format_xml::escape_text("<y>")));
+///
+/// assert_eq!(format_xml::join_boxed(", ", &items).to_string(), "<i>x</i>, 7, &lt;y&gt;");
+/// ```
+#[cfg(feature = "std")]
+#[inline]
+pub fn join_boxed<'a>(sep: &'a str, items: &'a [Box<dyn fmt::Display + 'a>]) -> impl fmt::Display + 'a {
+	crate::fmt(move |f| {
+		for (i, item) in items.iter().enumerate() {
+			if i != 0 {
+				f.write_str(sep)?;
+			}
+			fmt::Display::fmt(item, f)?;
+		}
+		Ok(())
+	})
+}
+
+/// Displays the number followed by the singular or plural word.
+///
+/// Uses simple English rules: the singular word when the number is `1` or `-1`, the plural word otherwise (including `0`).
+/// Accepts any primitive integer type, including `usize` lengths.
+///
+/// # Examples
+///
+/// ```
+/// let render = |n: i32| format_xml::xfmt! { <p>{format_xml::plural(n, "item", "items")}</p> }.to_string();
+/// assert_eq!(render(0), "<p>0 items</p>");
+/// assert_eq!(render(1), "<p>1 item</p>");
+/// assert_eq!(render(-1), "<p>-1 item</p>");
+/// assert_eq!(render(5), "<p>5 items</p>");
+///
+/// let files = vec!["a.txt", "b.txt"];
+/// assert_eq!(format_xml::plural(files.len(), "file", "files").to_string(), "2 files");
+/// assert_eq!(format_xml::plural(u128::MAX, "byte", "bytes").to_string(), "340282366920938463463374607431768211455 bytes");
+/// ```
+#[inline]
+pub fn plural<'a, N>(n: N, singular: &'a str, plural: &'a str) -> impl fmt::Display + 'a
+	where N: TryInto<i128> + Copy + fmt::Display + 'a
+{
+	let word = plural_word(n, singular, plural);
+	crate::fmt(move |f| {
+		fmt::Display::fmt(&n, f)?;
+		f.write_str(" ")?;
+		fmt::Display::fmt(&word, f)
+	})
+}
+
+/// Displays only the singular or plural word for the number.
+///
+/// See [`plural`] for the rules.
+///
+/// ```
+/// let n = 3;
+/// assert_eq!(format_xml::xfmt! { {n}" new "{format_xml::plural_word(n, "message", "messages")} }.to_string(), "3 new messages");
+/// assert_eq!(format_xml::plural_word([1].len(), "message", "messages"), "message");
+/// ```
+#[inline]
+pub fn plural_word<'a, N: TryInto<i128>>(n: N, singular: &'a str, plural: &'a str) -> &'a str {
+	match n.try_into() {
+		Ok(1 | -1) => singular,
+		_ => plural,
+	}
+}
+
+// Backslash escapes the quote character, `\` and line breaks
+struct QuoteWriter<'a, W: ?Sized> {
+	inner: &'a mut W,
+	quote: char,
+}
+impl<W: ?Sized + fmt::Write> fmt::Write for QuoteWriter<'_, W> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let mut start = 0;
+		for (i, chr) in s.char_indices() {
+			let escaped = match chr {
+				'\\' => "\\\\",
+				'\n' => "\\n",
+				'\r' => "\\r",
+				'\t' => "\\t",
+				'"' if self.quote == '"' => "\\\"",
+				'\'' if self.quote == '\'' => "\\'",
+				_ => continue,
+			};
+			self.inner.write_str(&s[start..i])?;
+			self.inner.write_str(escaped)?;
+			start = i + 1;
+		}
+		self.inner.write_str(&s[start..])
+	}
+}
+
+fn quote(f: &mut fmt::Formatter, quote: char, value: &dyn fmt::Display) -> fmt::Result {
+	use fmt::Write;
+	f.write_char(quote)?;
+	write!(QuoteWriter { inner: f, quote }, "{}", value)?;
+	f.write_char(quote)
+}
+
+/// Displays the value surrounded by `"`.
+///
+/// Embedded `"`, `\` and line breaks are backslash escaped like Rust and JSON strings, this is not xml escaping.
+/// When used inside formatting braces the result is escaped for the surrounding context as usual.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(format_xml::quoted("say \"hi\"").to_string(), r#""say \"hi\"""#);
+/// assert_eq!(format_xml::quoted("C:\\dir\nnext").to_string(), r#""C:\\dir\nnext""#);
+///
+/// let value = "a\"b";
+/// # let result =
+/// format_xml::xfmt! { <p title={format_xml::quoted(value)}>{format_xml::quoted(value)}</p> }
+/// # .to_string();
+/// # assert_eq!(result, r#"<p title="&quot;a\&quot;b&quot;">"a\"b"</p>"#);
+/// ```
+///
+/// The resulting string is `<p title="&quot;a\&quot;b&quot;">"a\"b"</p>`.
+#[inline]
+pub fn quoted<T: fmt::Display>(value: T) -> impl fmt::Display + fmt::Debug {
+	crate::fmt(move |f| quote(f, '"', &value))
+}
+
+/// Displays the value surrounded by `'`.
+///
+/// Like [`quoted`] but escapes embedded `'` instead of `"`.
+///
+/// ```
+/// assert_eq!(format_xml::single_quoted("it's \"fine\"").to_string(), r#"'it\'s "fine"'"#);
+/// ```
+#[inline]
+pub fn single_quoted<T: fmt::Display>(value: T) -> impl fmt::Display + fmt::Debug {
+	crate::fmt(move |f| quote(f, '\'', &value))
+}
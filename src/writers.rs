@@ -0,0 +1,771 @@
+// This module implements writers to layer under or over the escaping writers
+
+use core::fmt;
+
+/// Coalesces small writes in an inline buffer before forwarding them.
+///
+/// Templates write many small pieces, when the inner writer has a high cost per call (eg. `dyn fmt::Write` or an io adapter)
+/// collecting them in a buffer of `N` bytes reduces the number of calls.
+///
+/// Writes larger than the buffer are forwarded directly.
+/// The buffer is flushed when full, when dropped or by calling [`flush`](BufferedFmt::flush) or [`finish`](BufferedFmt::finish).
+/// Errors while flushing on drop are ignored, call `finish` to observe them.
+///
+/// # Examples
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut buf = String::new();
+/// let mut writer = format_xml::BufferedFmt::<_, 64>::new(&mut buf);
+/// write!(writer, "{}", format_xml::xfmt! { <p>"Hello "{"world"}</p> }).unwrap();
+/// writer.finish().unwrap();
+/// assert_eq!(buf, "<p>Hello world</p>");
+/// ```
+pub struct BufferedFmt<W: fmt::Write, const N: usize = 128> {
+	inner: W,
+	len: usize,
+	buf: [u8; N],
+}
+impl<W: fmt::Write, const N: usize> BufferedFmt<W, N> {
+	#[inline]
+	pub fn new(inner: W) -> BufferedFmt<W, N> {
+		BufferedFmt { inner, len: 0, buf: [0; N] }
+	}
+	/// Returns the buffered bytes not yet written to the inner writer.
+	#[inline]
+	pub fn buffer(&self) -> &str {
+		crate::escape::from_utf8(&self.buf[..self.len])
+	}
+	/// Writes the buffered bytes to the inner writer.
+	pub fn flush(&mut self) -> fmt::Result {
+		if self.len > 0 {
+			let len = self.len;
+			self.len = 0;
+			self.inner.write_str(crate::escape::from_utf8(&self.buf[..len]))?;
+		}
+		Ok(())
+	}
+	/// Flushes the buffer and returns any error.
+	#[inline]
+	pub fn finish(mut self) -> fmt::Result {
+		self.flush()
+	}
+}
+impl<W: fmt::Write, const N: usize> fmt::Write for BufferedFmt<W, N> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		// Only whole strings are copied into the buffer so it always contains valid utf8
+		if s.len() > N - self.len {
+			self.flush()?;
+		}
+		if s.len() >= N {
+			return self.inner.write_str(s);
+		}
+		self.buf[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+		self.len += s.len();
+		Ok(())
+	}
+}
+impl<W: fmt::Write, const N: usize> Drop for BufferedFmt<W, N> {
+	fn drop(&mut self) {
+		let _ = self.flush();
+	}
+}
+
+/// Duplicates every write to two writers.
+///
+/// Writes go to `a` first, then to `b`. The first error wins: if `a` fails the write is not forwarded to `b`.
+///
+/// # Examples
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut response = String::new();
+/// let mut cache = String::new();
+/// let mut writer = format_xml::tee(&mut response, &mut cache);
+/// write!(writer, "{}", format_xml::xfmt! { <p>{"a&b"}</p> }).unwrap();
+/// assert_eq!(response, "<p>a&amp;b</p>");
+/// assert_eq!(cache, response);
+/// ```
+pub struct TeeWriter<A, B> {
+	a: A,
+	b: B,
+}
+impl<A: fmt::Write, B: fmt::Write> TeeWriter<A, B> {
+	#[inline]
+	pub fn new(a: A, b: B) -> TeeWriter<A, B> {
+		TeeWriter { a, b }
+	}
+	/// Returns the inner writers.
+	#[inline]
+	pub fn into_inner(self) -> (A, B) {
+		(self.a, self.b)
+	}
+}
+impl<A: fmt::Write, B: fmt::Write> fmt::Write for TeeWriter<A, B> {
+	#[inline]
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		self.a.write_str(s)?;
+		self.b.write_str(s)
+	}
+}
+
+/// Duplicates every write to two writers.
+///
+/// See [`TeeWriter`] for more information.
+#[inline]
+pub fn tee<A: fmt::Write, B: fmt::Write>(a: A, b: B) -> TeeWriter<A, B> {
+	TeeWriter::new(a, b)
+}
+
+/// Digest computed by [`HashWriter`].
+///
+/// Implemented for every [`Hasher`](core::hash::Hasher) so no extra dependencies are needed.
+/// To use a cryptographic hash implement this trait for a wrapper around it:
+///
+/// ```ignore
+/// struct Sha256(sha2::Sha256);
+/// impl format_xml::WriteHash for Sha256 {
+/// 	type Digest = [u8; 32];
+/// 	fn update(&mut self, bytes: &[u8]) {
+/// 		sha2::Digest::update(&mut self.0, bytes);
+/// 	}
+/// 	fn digest(self) -> [u8; 32] {
+/// 		sha2::Digest::finalize(self.0).into()
+/// 	}
+/// }
+/// ```
+#[cfg(feature = "hash")]
+pub trait WriteHash {
+	type Digest;
+	fn update(&mut self, bytes: &[u8]);
+	fn digest(self) -> Self::Digest;
+}
+#[cfg(feature = "hash")]
+impl<H: core::hash::Hasher> WriteHash for H {
+	type Digest = u64;
+	#[inline]
+	fn update(&mut self, bytes: &[u8]) {
+		self.write(bytes);
+	}
+	#[inline]
+	fn digest(self) -> u64 {
+		self.finish()
+	}
+}
+
+/// Hashes the bytes while forwarding them.
+///
+/// Computes a digest of the rendered output (eg. for an `ETag` header) in the same pass that writes it.
+/// Only bytes accepted by the inner writer are hashed.
+///
+/// # Examples
+///
+/// ```
+/// use std::fmt::Write;
+/// use std::hash::Hasher;
+///
+/// let template = format_xml::xfmt! { <p>"Hello "{"world"}</p> };
+///
+/// let mut buf = String::new();
+/// let mut writer = format_xml::HashWriter::new(&mut buf, std::collections::hash_map::DefaultHasher::new());
+/// write!(writer, "{}", template).unwrap();
+/// let (_, etag) = writer.finish();
+///
+/// let mut hasher = std::collections::hash_map::DefaultHasher::new();
+/// hasher.write(template.to_string().as_bytes());
+/// assert_eq!(etag, hasher.finish());
+/// ```
+#[cfg(feature = "hash")]
+pub struct HashWriter<W, H> {
+	inner: W,
+	hasher: H,
+}
+#[cfg(feature = "hash")]
+impl<W: fmt::Write, H: WriteHash> HashWriter<W, H> {
+	#[inline]
+	pub fn new(inner: W, hasher: H) -> HashWriter<W, H> {
+		HashWriter { inner, hasher }
+	}
+	/// Returns the inner writer and the digest of all the bytes written.
+	#[inline]
+	pub fn finish(self) -> (W, H::Digest) {
+		(self.inner, self.hasher.digest())
+	}
+}
+#[cfg(feature = "hash")]
+impl<W: fmt::Write, H: WriteHash> fmt::Write for HashWriter<W, H> {
+	#[inline]
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		self.inner.write_str(s)?;
+		self.hasher.update(s.as_bytes());
+		Ok(())
+	}
+}
+
+/// Location in the written output.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Position {
+	/// Number of bytes written.
+	pub offset: usize,
+	/// Line number, starting at 1.
+	pub line: usize,
+	/// Column number in characters, starting at 1.
+	pub column: usize,
+}
+
+/// Tracks the line and column while forwarding writes.
+///
+/// Maps errors reported by a downstream parser (eg. at line 1234 column 17) back to the part of the template which wrote them.
+/// Lines are separated by `\n`, columns count characters.
+///
+/// # Examples
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut writer = format_xml::PositionWriter::new(String::new());
+/// write!(writer, "{}", format_xml::xfmt! { <root>"\n\t"<child /> }).unwrap();
+/// let checkpoint = writer.position();
+/// write!(writer, "{}", format_xml::xfmt! { "\n"</root> }).unwrap();
+///
+/// assert_eq!((checkpoint.line, checkpoint.column), (2, 11));
+/// assert_eq!((writer.position().line, writer.position().column), (3, 8));
+/// ```
+pub struct PositionWriter<W> {
+	inner: W,
+	position: Position,
+}
+impl<W: fmt::Write> PositionWriter<W> {
+	#[inline]
+	pub fn new(inner: W) -> PositionWriter<W> {
+		PositionWriter { inner, position: Position { offset: 0, line: 1, column: 1 } }
+	}
+	/// Returns the position where the next byte will be written.
+	#[inline]
+	pub fn position(&self) -> Position {
+		self.position
+	}
+	/// Returns the inner writer.
+	#[inline]
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+}
+impl<W: fmt::Write> fmt::Write for PositionWriter<W> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		self.inner.write_str(s)?;
+		self.position.offset += s.len();
+		match s.rfind('\n') {
+			Some(i) => {
+				self.position.line += s.bytes().filter(|&chr| chr == b'\n').count();
+				self.position.column = 1 + s[i + 1..].chars().count();
+			},
+			None => {
+				self.position.column += s.chars().count();
+			},
+		}
+		Ok(())
+	}
+}
+
+/// Forwards writes up to a limit in bytes.
+///
+/// The write crossing the limit is truncated at a character boundary, the writer is then marked as truncated.
+/// Further writes fail with [`fmt::Error`] to stop rendering early, or are silently discarded when created with [`discard`](TruncatingWriter::discard).
+///
+/// See [`render_preview`] to render a preview of a template.
+pub struct TruncatingWriter<W> {
+	inner: W,
+	remaining: usize,
+	truncated: bool,
+	discard: bool,
+}
+impl<W: fmt::Write> TruncatingWriter<W> {
+	/// Fails with [`fmt::Error`] once the limit is reached.
+	#[inline]
+	pub fn new(inner: W, limit: usize) -> TruncatingWriter<W> {
+		TruncatingWriter { inner, remaining: limit, truncated: false, discard: false }
+	}
+	/// Silently discards writes once the limit is reached.
+	#[inline]
+	pub fn discard(inner: W, limit: usize) -> TruncatingWriter<W> {
+		TruncatingWriter { inner, remaining: limit, truncated: false, discard: true }
+	}
+	/// Returns true if any output was cut off.
+	#[inline]
+	pub fn was_truncated(&self) -> bool {
+		self.truncated
+	}
+	/// Returns the inner writer.
+	#[inline]
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+}
+impl<W: fmt::Write> fmt::Write for TruncatingWriter<W> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		if s.len() <= self.remaining {
+			self.remaining -= s.len();
+			return self.inner.write_str(s);
+		}
+		let mut end = self.remaining;
+		while !s.is_char_boundary(end) {
+			end -= 1;
+		}
+		self.remaining = 0;
+		self.truncated = true;
+		self.inner.write_str(&s[..end])?;
+		if self.discard { Ok(()) } else { Err(fmt::Error) }
+	}
+}
+
+/// Renders at most `limit` bytes of the value.
+///
+/// Rendering stops as soon as the limit is reached.
+/// Returns the rendered string and true if it was cut off.
+///
+/// # Examples
+///
+/// ```
+/// let template = format_xml::xfmt! { <p>"Hello world!"</p> };
+/// assert_eq!(format_xml::render_preview(&template, 10), (String::from("<p>Hello w"), true));
+/// assert_eq!(format_xml::render_preview(&template, 100), (String::from("<p>Hello world!</p>"), false));
+/// ```
+#[cfg(feature = "std")]
+pub fn render_preview(value: impl fmt::Display, limit: usize) -> (String, bool) {
+	let mut writer = TruncatingWriter::new(String::new(), limit);
+	let result = fmt::write(&mut writer, format_args!("{}", value));
+	let truncated = writer.was_truncated();
+	debug_assert!(result.is_ok() || truncated);
+	(writer.into_inner(), truncated)
+}
+
+/// Bridges `fmt::Write` onto an `std::io::Write` sink.
+///
+/// Layer the escaping writers on top to escape straight into files and sockets.
+/// An io error is kept and the write fails with `fmt::Error`, call [`finish`](IoWriter::finish) to recover the io error.
+///
+/// # Examples
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut writer = format_xml::IoWriter::new(Vec::new());
+/// let result = write!(format_xml::EscapeText::wrap(&mut writer), "{}", "a<b");
+/// let bytes = writer.finish(result).unwrap();
+/// assert_eq!(bytes, b"a&lt;b");
+/// ```
+#[cfg(feature = "std")]
+pub struct IoWriter<W> {
+	inner: W,
+	error: Option<std::io::Error>,
+}
+#[cfg(feature = "std")]
+impl<W: std::io::Write> IoWriter<W> {
+	#[inline]
+	pub fn new(inner: W) -> IoWriter<W> {
+		IoWriter { inner, error: None }
+	}
+	/// Returns the io error which caused a write to fail.
+	#[inline]
+	pub fn take_error(&mut self) -> Option<std::io::Error> {
+		self.error.take()
+	}
+	/// Converts the result of formatting into an io result and returns the inner writer.
+	///
+	/// A formatting error not caused by the sink is returned as an error of kind `Other`.
+	pub fn finish(mut self, result: fmt::Result) -> std::io::Result<W> {
+		match (result, self.error.take()) {
+			(_, Some(err)) => Err(err),
+			(Ok(()), None) => Ok(self.inner),
+			(Err(fmt::Error), None) => Err(std::io::Error::other("formatter error")),
+		}
+	}
+}
+#[cfg(feature = "std")]
+impl<W: std::io::Write> fmt::Write for IoWriter<W> {
+	#[inline]
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		self.inner.write_all(s.as_bytes()).map_err(|err| {
+			self.error = Some(err);
+			fmt::Error
+		})
+	}
+}
+
+/// Renders the value to an `std::io::Write` sink without buffering it in a string.
+///
+/// The small writes of the template are coalesced in a [`BufferedFmt`] before they reach the sink.
+/// Returns the io error of the sink if writing fails.
+///
+/// # Examples
+///
+/// ```
+/// let mut out = Vec::new();
+/// format_xml::write_to(&mut out, format_xml::xfmt! { <p>{"a&b"}</p> }).unwrap();
+/// assert_eq!(out, b"<p>a&amp;b</p>");
+/// ```
+#[cfg(feature = "std")]
+pub fn write_to<W: std::io::Write>(inner: W, value: impl fmt::Display) -> std::io::Result<()> {
+	let mut writer = IoWriter::new(inner);
+	let mut buffered = BufferedFmt::<_, 512>::new(&mut writer);
+	let result = fmt::write(&mut buffered, format_args!("{}", value)).and_then(|()| buffered.finish());
+	writer.finish(result).map(drop)
+}
+
+#[cfg(feature = "metrics")]
+struct CountingWriter<'a, 'b> {
+	inner: &'a mut fmt::Formatter<'b>,
+	count: usize,
+}
+#[cfg(feature = "metrics")]
+impl<'a, 'b> fmt::Write for CountingWriter<'a, 'b> {
+	#[inline]
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		self.count += s.len();
+		self.inner.write_str(s)
+	}
+}
+
+/// Counts the bytes written while rendering the value.
+///
+/// The callback is invoked with the number of bytes written after every render, including renders which failed part way.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// static LAST_SIZE: AtomicUsize = AtomicUsize::new(0);
+///
+/// let page = format_xml::measured(format_xml::xfmt! { <p>"Hello"</p> }, |n| LAST_SIZE.store(n, Ordering::Relaxed));
+/// assert_eq!(page.to_string(), "<p>Hello</p>");
+/// assert_eq!(LAST_SIZE.load(Ordering::Relaxed), 12);
+/// ```
+#[cfg(feature = "metrics")]
+pub fn measured<T: fmt::Display, F: Fn(usize)>(value: T, callback: F) -> impl fmt::Display {
+	crate::fmt(move |f| {
+		let mut writer = CountingWriter { inner: f, count: 0 };
+		let result = fmt::write(&mut writer, format_args!("{}", value));
+		callback(writer.count);
+		result
+	})
+}
+
+#[cfg(feature = "bytes")]
+struct BytesWriter<'a>(&'a mut bytes::BytesMut);
+#[cfg(feature = "bytes")]
+impl<'a> fmt::Write for BytesWriter<'a> {
+	#[inline]
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		self.0.extend_from_slice(s.as_bytes());
+		Ok(())
+	}
+}
+
+/// Renders the value at the end of the buffer.
+///
+/// The value is rendered once and written directly to the buffer, which grows as needed.
+///
+/// # Examples
+///
+/// ```
+/// let mut buf = bytes::BytesMut::new();
+/// format_xml::render_into_bytes(&mut buf, format_xml::xfmt! { <p>{"a&b"}</p> }).unwrap();
+/// assert_eq!(&buf[..], b"<p>a&amp;b</p>");
+/// ```
+#[cfg(feature = "bytes")]
+pub fn render_into_bytes(buf: &mut bytes::BytesMut, value: impl fmt::Display) -> fmt::Result {
+	fmt::write(&mut BytesWriter(buf), format_args!("{}", value))
+}
+
+/// Renders the value to bytes.
+///
+/// # Panics
+///
+/// Panics if the value's `Display` implementation returns an error, like [`ToString`](std::string::ToString).
+///
+/// # Examples
+///
+/// ```
+/// let body = format_xml::render_bytes(format_xml::xfmt! { <p>"Hello"</p> });
+/// assert_eq!(&body[..], b"<p>Hello</p>");
+/// ```
+#[cfg(feature = "bytes")]
+pub fn render_bytes(value: impl fmt::Display) -> bytes::Bytes {
+	let mut buf = bytes::BytesMut::new();
+	render_into_bytes(&mut buf, value).expect("a Display implementation returned an error unexpectedly");
+	buf.freeze()
+}
+
+#[test]
+fn test_buffered() {
+	use fmt::Write;
+
+	// Counts the number of calls to the inner writer
+	struct Counter<'a>(&'a mut String, usize);
+	impl fmt::Write for Counter<'_> {
+		fn write_str(&mut self, s: &str) -> fmt::Result {
+			self.1 += 1;
+			self.0.push_str(s);
+			Ok(())
+		}
+	}
+
+	let items = ["a", "<b>", "ccccccccccccccccccccccccccccccccc", "d&e", "🦀"];
+	let template = crate::xfmt! { <ul> for item in (&items) { <li class={item}>{item}</li> } </ul> };
+	let expected = template.to_string();
+
+	let mut buf = String::new();
+	let mut writer = BufferedFmt::<_, 16>::new(Counter(&mut buf, 0));
+	write!(writer, "{}", template).unwrap();
+	writer.flush().unwrap();
+	assert!(writer.inner.1 < 20);
+	drop(writer);
+	assert_eq!(buf, expected);
+
+	// Dropping the writer flushes the remaining bytes
+	let mut buf = String::new();
+	let mut writer = BufferedFmt::<_>::new(&mut buf);
+	writer.write_str("tail").unwrap();
+	assert_eq!(writer.buffer(), "tail");
+	drop(writer);
+	assert_eq!(buf, "tail");
+}
+
+#[test]
+fn test_tee() {
+	use fmt::Write;
+
+	// Fails after accepting a number of bytes
+	struct Limit(String, usize);
+	impl fmt::Write for Limit {
+		fn write_str(&mut self, s: &str) -> fmt::Result {
+			if self.0.len() + s.len() > self.1 {
+				return Err(fmt::Error);
+			}
+			self.0.push_str(s);
+			Ok(())
+		}
+	}
+
+	let template = crate::xfmt! { <a href={"?a&b"}>"link"</a> };
+
+	let mut writer = tee(String::new(), String::new());
+	write!(writer, "{}", template).unwrap();
+	let (a, b) = writer.into_inner();
+	assert_eq!(a, template.to_string());
+	assert_eq!(a, b);
+
+	let mut writer = tee(Limit(String::new(), 9), String::new());
+	assert!(write!(writer, "{}", template).is_err());
+	let (a, b) = writer.into_inner();
+	assert_eq!(a.0, "<a href=\"");
+	assert_eq!(b, "<a href=\"");
+
+	let mut writer = tee(String::new(), Limit(String::new(), 9));
+	assert!(write!(writer, "{}", template).is_err());
+	let (a, b) = writer.into_inner();
+	assert_eq!(a, "<a href=\"?a");
+	assert_eq!(b.0, "<a href=\"");
+}
+
+#[cfg(feature = "hash")]
+#[test]
+fn test_hash() {
+	use std::collections::hash_map::DefaultHasher;
+	use std::hash::Hasher;
+	use fmt::Write;
+
+	let items = ["a", "<b>", "c&d", "🦀"];
+	let template = crate::xfmt! { <ul> for item in (&items) { <li title={item}>{item}</li> } </ul> };
+	let expected = {
+		let mut hasher = DefaultHasher::new();
+		hasher.write(template.to_string().as_bytes());
+		hasher.finish()
+	};
+
+	let mut writer = HashWriter::new(String::new(), DefaultHasher::new());
+	write!(writer, "{}", template).unwrap();
+	let (buf, digest) = writer.finish();
+	assert_eq!(buf, template.to_string());
+	assert_eq!(digest, expected);
+
+	// Composes with the buffered writer
+	let mut writer = HashWriter::new(String::new(), DefaultHasher::new());
+	let mut buffered = BufferedFmt::<_, 16>::new(&mut writer);
+	write!(buffered, "{}", template).unwrap();
+	buffered.finish().unwrap();
+	let (buf, digest) = writer.finish();
+	assert_eq!(buf, template.to_string());
+	assert_eq!(digest, expected);
+}
+
+#[test]
+fn test_position() {
+	use fmt::Write;
+
+	let mut writer = PositionWriter::new(String::new());
+	assert_eq!(writer.position(), Position { offset: 0, line: 1, column: 1 });
+	writer.write_str("<a>").unwrap();
+	assert_eq!(writer.position(), Position { offset: 3, line: 1, column: 4 });
+	writer.write_str("\n\n").unwrap();
+	assert_eq!(writer.position(), Position { offset: 5, line: 3, column: 1 });
+	writer.write_str("🦀é\nab").unwrap();
+	assert_eq!(writer.position(), Position { offset: 14, line: 4, column: 3 });
+	writer.write_str("").unwrap();
+	assert_eq!(writer.position(), Position { offset: 14, line: 4, column: 3 });
+}
+
+#[test]
+fn test_truncating() {
+	use std::cell::Cell;
+	use fmt::Write;
+
+	// Limit falls inside a multi-byte character
+	assert_eq!(render_preview("ab🦀cd", 4), (String::from("ab"), true));
+	assert_eq!(render_preview("ab🦀cd", 6), (String::from("ab🦀"), true));
+	assert_eq!(render_preview("ab🦀cd", 8), (String::from("ab🦀cd"), false));
+	assert_eq!(render_preview("", 0), (String::new(), false));
+
+	// Rendering stops promptly after the limit
+	let count = Cell::new(0);
+	let template = crate::xfmt! { for _ in (0..1000) { |f| { count.set(count.get() + 1); f.write_str("item")?; } } };
+	assert_eq!(render_preview(template, 10), (String::from("itemitemit"), true));
+	assert_eq!(count.get(), 3);
+
+	let mut writer = TruncatingWriter::discard(String::new(), 10);
+	write!(writer, "{}", template).unwrap();
+	assert!(writer.was_truncated());
+	assert_eq!(writer.into_inner(), "itemitemit");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_io() {
+	use std::io::{self, Write};
+
+	// Crosses the write_to buffer and the 4093 byte chunks below several times
+	let text = "Lorem ipsum <dolor> sit & 'amet' \"consectetur\"\n\u{1F980} ".repeat(200);
+	assert!(text.len() > 2 * 4093);
+
+	// The io path writes the same bytes as the fmt path
+	let page = &crate::xfmt! { <p title={text}>{text}</p> };
+	let mut out = Vec::new();
+	write_to(&mut out, page).unwrap();
+	assert_eq!(out, page.to_string().into_bytes());
+
+	// The small writes of the template are coalesced before they reach the sink
+	struct Calls(usize);
+	impl io::Write for Calls {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			self.0 += 1;
+			Ok(buf.len())
+		}
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+	let items = ["a&b"; 100];
+	let mut calls = Calls(0);
+	write_to(&mut calls, crate::xfmt! { <ul> for item in (&items) { <li>{item}</li> } </ul> }).unwrap();
+	assert!(calls.0 <= 4, "{} calls", calls.0);
+
+	let mut escaper = crate::EscapeIo::text(Vec::new());
+	for chunk in text.as_bytes().chunks(4093) {
+		escaper.write_all(chunk).unwrap();
+	}
+	assert_eq!(escaper.into_inner(), crate::escape_text(&text).to_string().into_bytes());
+
+	let mut escaper = crate::EscapeIo::attr_value(Vec::new());
+	write!(escaper, "{}", text).unwrap();
+	assert_eq!(escaper.into_inner(), crate::escape_attr(&text).to_string().into_bytes());
+
+	// The io error of the sink is returned
+	struct Full(usize);
+	impl io::Write for Full {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			if self.0 < buf.len() {
+				return Err(io::Error::new(io::ErrorKind::WriteZero, "disk full"));
+			}
+			self.0 -= buf.len();
+			Ok(buf.len())
+		}
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+	let err = write_to(Full(100), page).unwrap_err();
+	assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+	let err = crate::EscapeIo::text(Full(10)).write_all(b"a < b & c > d").unwrap_err();
+	assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+	let err = write_to(Vec::new(), crate::fmt(|_| Err(fmt::Error))).unwrap_err();
+	assert_eq!(err.kind(), io::ErrorKind::Other);
+}
+
+#[cfg(feature = "std")]
+#[test]
+#[ignore = "renders several megabytes"]
+fn test_io_large() {
+	use std::io::Write;
+
+	let text = "Lorem ipsum <dolor> sit & 'amet' \"consectetur\"\n\u{1F980} ".repeat(50_000);
+	assert!(text.len() > 2_000_000);
+
+	let page = &crate::xfmt! { <p title={text}>{text}</p> };
+	let mut out = Vec::new();
+	write_to(&mut out, page).unwrap();
+	assert_eq!(out, page.to_string().into_bytes());
+
+	let mut escaper = crate::EscapeIo::text(Vec::new());
+	for chunk in text.as_bytes().chunks(4093) {
+		escaper.write_all(chunk).unwrap();
+	}
+	assert_eq!(escaper.into_inner(), crate::escape_text(&text).to_string().into_bytes());
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn test_measured() {
+	use std::cell::RefCell;
+
+	let counts = RefCell::new(Vec::new());
+	let name = "a&b";
+	let page = measured(crate::xfmt! { <p title={name}>{name}</p> }, |n| counts.borrow_mut().push(n));
+	let first = page.to_string();
+	let second = page.to_string();
+	assert_eq!(first, second);
+	assert_eq!(*counts.borrow(), [first.len(), second.len()]);
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn test_bytes() {
+	use std::cell::Cell;
+
+	let renders = Cell::new(0);
+	let items = ["a", "<b>", "c&d"];
+	let template = &crate::xfmt! {
+		|_| { renders.set(renders.get() + 1); }
+		<ul>
+		for item in (&items) {
+			for _ in (0..100) {
+				<li title={item}>{item}</li>
+			}
+		}
+		</ul>
+	};
+	let expected = template.to_string().into_bytes();
+	renders.set(0);
+	assert_eq!(&render_bytes(template)[..], &expected[..]);
+	assert_eq!(renders.get(), 1);
+
+	let mut buf = bytes::BytesMut::with_capacity(4);
+	render_into_bytes(&mut buf, "head").unwrap();
+	render_into_bytes(&mut buf, template).unwrap();
+	assert_eq!(&buf[..4], b"head");
+	assert_eq!(&buf[4..], &expected[..]);
+	assert_eq!(renders.get(), 2);
+}
@@ -28,6 +28,37 @@
 /// The values inside formatting braces are escaped by default, the text literals are not.
 /// Use the [escape hatch](#escape-hatch) to bypass automatic escaping.
 ///
+/// A tag missing its closing `>` or `?>` is a compile error naming the tag, eg. ``missing closing `>` after `<svg ...`, found `<` ``:
+///
+/// ```compile_fail
+/// format_xml::xfmt! { <svg width="200" <line x1="0" /></svg> };
+/// ```
+///
+/// ### Bare words
+///
+/// ```rust
+/// let name = "World";
+/// let count = 3;
+///
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<p>Hello there, {name}! You have {count} new messages.</p>
+/// 	<p>Page 2 of 3</p>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, "<p>Hello there, World! You have 3 new messages.</p><p>Page 2 of 3</p>");
+/// ```
+///
+/// The resulting string is `<p>Hello there, World! You have 3 new messages.</p><p>Page 2 of 3</p>`.
+///
+/// A run of bare words starts with an identifier or punctuation and continues with identifiers, literals and the punctuation `,` `.` `!` `?` `;` `:`.
+/// The words, literals and values are separated by a single space, the punctuation is attached to the previous word.
+/// No space is added between the run and the literals or tags before or after it, nor between adjacent values.
+///
+/// A `<` starts a tag and a `{` starts a value.
+/// The keywords `if`, `else`, `match`, `for`, `while`, `loop`, `break`, `continue` and `let` always start control flow, quote them to write them as text.
+/// A template cannot start with a bare word followed by `:` as this selects the [escaping policy](#escaping-policy).
+///
 /// ### Formatting specifiers
 ///
 /// ```rust
@@ -45,6 +76,109 @@
 ///
 /// The rules for the specifiers are exactly the same as Rust's [standard formatting syntax](std::fmt).
 ///
+/// ### Optional values
+///
+/// ```rust
+/// let lang: Option<&str> = None;
+/// let title = Some("Home");
+/// let default_title = "Untitled";
+///
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<html lang={lang ?? "en"}>
+/// 		<title>{title ?? {default_title}}</title>
+/// 	</html>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<html lang="en"><title>Home</title></html>"#);
+/// ```
+///
+/// The resulting string is `<html lang="en"><title>Home</title></html>`.
+///
+/// An `Option` value followed by `??` and a literal or braced expression formats the value if `Some` or the fallback if `None`.
+/// Both are escaped and formatting specifiers apply to both, eg. `{value ?? "none":>8}`.
+///
+/// ```rust
+/// let title: Option<&str> = None;
+/// let lang = Some(String::from("en"));
+///
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<p title=?{title} lang=?{lang}>"Hello"</p>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<p lang="en">Hello</p>"#);
+/// ```
+///
+/// The resulting string is `<p lang="en">Hello</p>`.
+///
+/// An attribute written as `name=?{value}` with an `Option` value is omitted entirely if `None`, the formatting specifiers apply to the value if `Some`.
+/// Adjacent literals in attribute values are concatenated at compiletime:
+///
+/// ```rust
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<meta http-equiv="Content-Security-Policy" content=
+/// 		"default-src 'self'; "
+/// 		"img-src 'self' https://images.example.com; "
+/// 		"script-src 'self'; "
+/// 		"style-src 'self' 'unsafe-inline'" />
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<meta http-equiv="Content-Security-Policy" content="default-src 'self'; img-src 'self' https://images.example.com; script-src 'self'; style-src 'self' 'unsafe-inline'" />"#);
+/// ```
+///
+/// The resulting string is `<meta http-equiv="Content-Security-Policy" content="default-src 'self'; img-src 'self' https://images.example.com; script-src 'self'; style-src 'self' 'unsafe-inline'" />`.
+///
+/// A parenthesized sequence of literals and `{value}`s is written as a single attribute value, eg. `transform=("translate("{x}","{y}")")`.
+/// A list of entries in square brackets is written separated by spaces, an entry followed by `: condition` is written only if the condition is true.
+/// The entries are literals or escaped `{value}`s, eg. `class=["btn", "active": is_active, {extra}: has_extra]`.
+/// A list of `(property, value)` pairs in square brackets is written as inline style declarations `property: value;` separated by spaces.
+/// The values are escaped and `Option` values which are `None` are skipped, eg. `style=[("color", color), ("width", format_args!("{}px", width))]`.
+/// A boolean attribute written as `name?={condition}` is written without a value if the condition is true and omitted if false, eg. `<input type="checkbox" checked?={done} />`.
+///
+/// ### Attribute shorthand
+///
+/// ```rust
+/// let id = "user";
+/// let value = "Tom & Jerry";
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<input type="text" {id} name="user" {value} />
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<input type="text" id="user" name="user" value="Tom &amp; Jerry" />"#);
+/// ```
+///
+/// The resulting string is `<input type="text" id="user" name="user" value="Tom &amp; Jerry" />`.
+///
+/// Like Rust's struct field shorthand a braced identifier in place of an attribute is short for `ident={ident}`.
+/// Other expressions require the explicit form:
+///
+/// ```compile_fail
+/// let user = (1, "Casper");
+/// format_xml::xfmt! { <input {user.1} /> };
+/// ```
+///
+/// ### Attribute names from values
+///
+/// ```rust
+/// let key = "user id";
+/// let name = "aria-label";
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<div data-{key}="42" {name}="Profile" {""}="empty"></div>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<div data-user_id="42" aria-label="Profile" _="empty"></div>"#);
+/// ```
+///
+/// The resulting string is `<div data-user_id="42" aria-label="Profile" _="empty"></div>`.
+///
+/// An attribute name written as `{name}` or ending in `{name}` after a `-`, `:` or `.` is formatted from the value.
+/// Names cannot be escaped, every character which cannot appear in a name such as whitespace, quotes, `=`, `>` and `/` is replaced with `_`,
+/// a name starting with a digit, `-` or `.` is prefixed with `_` and an empty name is written as `_`, see [`EscapeAttrName`](crate::EscapeAttrName).
+///
 /// ### Escaping
 ///
 /// ```rust
@@ -63,13 +197,77 @@
 /// The values inside formatting braces are escaped by default, the text literals are not.
 ///
 /// * Text elements escape `<`, `&`, `>`.
-/// * Attribute values escape `<`, `&`, `>`, `'`, `"`.
-/// * Comment nodes escape `--` by removing it altogether.
-/// * CDATA sections escape `]]>`.
+/// * Attribute values escape `<`, `&`, `>`, `'`, `"` and the whitespace `\n`, `\t`, `\r`.
+/// * Unquoted attribute values written as `name=~{value}` escape whitespace and `"`, `'`, `` ` ``, `<`, `>`, `=`, `&`, see [`EscapeAttrUnquoted`](crate::EscapeAttrUnquoted).
+/// * Comment nodes escape `--` by removing it altogether, the markup inside is text and its values are not escaped otherwise, eg. `<!-- <b>{value}</b> -->`.
+/// * CDATA sections escape `]]>`, the markup inside is text and its values are not escaped otherwise, eg. `<![CDATA[<b>{value}</b>]]>`.
+/// * Attribute values in processing instructions escape `?>` as well, see [`EscapePi`](crate::EscapePi).
 ///
 /// Escaping is not implemented in some HTML contexts:
 /// inside `<script>`, `<style>` tags or their respective attribute equivalents (event handlers and inline styles),
 /// do not format user controlled values in these locations!
+/// Use [`escape_js`](crate::escape_js) for values in string literals inside `<script>` tags.
+/// Use [`escape_css`](crate::escape_css) for values in inline styles and `<style>` tags.
+///
+/// ### Embedding templates
+///
+/// ```rust
+/// let nav = format_xml::xfmt! { <nav><a href="/">"Home"</a></nav> };
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<header>{@nav}</header>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<header><nav><a href="/">Home</a></nav></header>"#);
+/// ```
+///
+/// The resulting string is `<header><nav><a href="/">Home</a></nav></header>`.
+///
+/// Templates embedded with `{@template}` are written as is, formatting them as `{template}` would escape their markup again.
+/// Only the types implementing [`Render`](crate::Render) can be embedded this way.
+///
+/// ### Raw values
+///
+/// ```rust
+/// // Pre-rendered markup from a trusted source
+/// let body = "<p>Hello <em>World</em></p>";
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<article>{% body %}</article>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, "<article><p>Hello <em>World</em></p></article>");
+/// ```
+///
+/// The resulting string is `<article><p>Hello <em>World</em></p></article>`.
+///
+/// **Danger**: values written as `{% value %}` are not escaped at all!
+/// Never use this syntax with user controlled values, it is an invitation for cross-site scripting.
+/// Formatting specifiers are supported like the regular braces, `{% value:>8 %}`.
+///
+/// ### Escaping policy
+///
+/// ```rust
+/// let text = "a > b && c < d";
+/// let title = "'quoted' \"title\"";
+/// # let result =
+/// format_xml::xfmt! { minimal:
+/// 	<p title={title}>{text}</p>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<p title="'quoted' &quot;title&quot;">a > b &amp;&amp; c &lt; d</p>"#);
+/// ```
+///
+/// The resulting string is `<p title="'quoted' &quot;title&quot;">a > b &amp;&amp; c &lt; d</p>`.
+///
+/// A policy name followed by `:` at the start of the template selects how the values are escaped, after `move` if present.
+/// The `minimal` policy escapes only what is required for well-formed xml, see [`EscapeTextMinimal`](crate::EscapeTextMinimal) and [`EscapeAttrValueMinimal`](crate::EscapeAttrValueMinimal).
+/// The `html` policy writes the quotes in attribute values as `&#39;` and `&#34;`, see [`EscapeAttrValueNumeric`](crate::EscapeAttrValueNumeric).
+/// [Strict templates](#strict-templates) with the `html` policy also check the html void elements.
+/// The `trusted` policy writes the values as is without escaping, see [`Trusted`](crate::Trusted). Only use it when every value is program controlled.
+/// The `newlines` policy converts `\r\n` and `\r` in the values to `\n` and the `preserve_newlines` policy writes `\r` as `&#13;`, see [`NormalizeNewlines`](crate::NormalizeNewlines).
+/// The policy applies to this template only, nested templates use their own.
+/// The escape hatch receives a formatter wrapper which dereferences to [`&mut Formatter`](std::fmt::Formatter).
 ///
 /// ### Supported syntax
 ///
@@ -89,6 +287,44 @@
 ///
 /// The resulting string is `<!doctype html><?xml version="1.0" encoding="UTF-8"?><tag-name></tag-name><self-closing-tag /><!-- comment --><![CDATA[cdata]]>`.
 ///
+/// Document type declarations take a name, the keywords `PUBLIC` or `SYSTEM` and quoted identifiers:
+///
+/// ```rust
+/// let dtd = "https://example.com/note.dtd";
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd">
+/// 	<!DOCTYPE note SYSTEM {dtd}>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd"><!DOCTYPE note SYSTEM "https://example.com/note.dtd">"#);
+/// ```
+///
+/// The resulting string is `<!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd"><!DOCTYPE note SYSTEM "https://example.com/note.dtd">`.
+///
+/// The identifiers are written in double quotes, literals cannot contain a double quote and values write it as `%22`, see [`EscapeDoctypeLiteral`](crate::EscapeDoctypeLiteral).
+///
+/// Fragments group sibling nodes without writing anything themselves:
+///
+/// ```rust
+/// let items = ["a", "b"];
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<dl>
+/// 	for item in (&items) {
+/// 		<>
+/// 			<dt>{item}</dt>
+/// 			<dd><>"Item "{item}</></dd>
+/// 		</>
+/// 	}
+/// 	</dl>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, "<dl><dt>a</dt><dd>Item a</dd><dt>b</dt><dd>Item b</dd></dl>");
+/// ```
+///
+/// The resulting string is `<dl><dt>a</dt><dd>Item a</dd><dt>b</dt><dd>Item b</dd></dl>`.
+///
 /// Examples of element naming and namespace syntax support:
 ///
 /// ```rust
@@ -106,13 +342,142 @@
 ///
 /// The resulting string is `<tag><tag-foo><tag.foo><ns:tag><_t-0.z>`.
 ///
-/// There are no restrictions on matching open/close tags or reject tags which cannot be self-closing.
+/// By default there are no restrictions on matching open/close tags or reject tags which cannot be self-closing, see `strict:` below to check them at compiletime.
+///
+/// ```rust
+/// let level = 2;
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<{format_args!("h{}", level)} class="title">"Chapter"</{format_args!("h{}", level)}>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<h2 class="title">Chapter</h2>"#);
+/// ```
+///
+/// The resulting string is `<h2 class="title">Chapter</h2>`.
+///
+/// Element names written as `{name}` or ending in `{name}` after a `-`, `:` or `.` are formatted from the value like [attribute names](#attribute-names-from-values).
+/// Dynamic names are not checked for matching open and close tags either.
+///
+/// With the `lint-lowercase` feature enabled, element and attribute names containing uppercase ASCII letters are rejected at compiletime.
+/// The mixed case names from the SVG vocabulary such as `viewBox` and `linearGradient` are allowed.
+/// Names written as string literals are not checked.
+/// The following example fails to compile with the `lint-lowercase` feature and writes the name as-is without it:
+///
+#[cfg_attr(feature = "lint-lowercase", doc = "```compile_fail")]
+#[cfg_attr(not(feature = "lint-lowercase"), doc = "```rust")]
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<div onClick="go()"></div>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<div onClick="go()"></div>"#);
+/// ```
+///
+/// ```rust
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<svg viewBox="0 0 10 10" "DATA-X"="1" />
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<svg viewBox="0 0 10 10" DATA-X="1" />"#);
+/// ```
 ///
 /// Unfinished implementation:
 ///
-/// * Document type definitions (DTD) are not correctly implemented. The `<!doctype>` tag is barely functional.
+/// * The internal subset of document type declarations is not supported, eg. `<!DOCTYPE note [<!ELEMENT note (#PCDATA)>]>`.
 /// * Processing instructions are not correctly implemented. The `<?xml?>` tag is barely functional.
 ///
+/// ### Strict templates
+///
+/// ```rust
+/// let items = ["a", "b"];
+/// # let result =
+/// format_xml::xfmt! { strict:
+/// 	<ul class="list">
+/// 		for item in (&items) {
+/// 			<li>{item}<img src="x.png" /></li>
+/// 		}
+/// 	</ul>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<ul class="list"><li>a<img src="x.png" /></li><li>b<img src="x.png" /></li></ul>"#);
+/// ```
+///
+/// The resulting string is `<ul class="list"><li>a<img src="x.png" /></li><li>b<img src="x.png" /></li></ul>`.
+///
+/// Templates starting with `strict:`, after `move` and before the escaping policy, check at compiletime that every open tag is closed by a matching close tag:
+///
+/// ```compile_fail
+/// format_xml::xfmt! { strict: <div><span></div></span> };
+/// ```
+///
+/// ```compile_fail
+/// format_xml::xfmt! { strict: <main><p>"unclosed"</main> };
+/// ```
+///
+/// ```compile_fail
+/// format_xml::xfmt! { strict: <p></p "x"> };
+/// ```
+///
+/// The body of `if`, `for`, `while` and `loop` blocks must close the tags it opens:
+///
+/// ```compile_fail
+/// let open = true;
+/// format_xml::xfmt! { strict: if (open) { <div> } </div> };
+/// ```
+///
+/// The `else` block of a `let` statement replaces the remainder of the enclosing block and must close the tags which are open in it:
+///
+/// ```compile_fail
+/// let value = Some(1);
+/// format_xml::xfmt! { strict: <p> let Some(x) = value else { "none" }; {x} </p> };
+/// ```
+///
+/// Elements with dynamic names match any close tag, the arms of `match` expressions and the escape hatch are not checked.
+/// Without `strict:` templates may freely write partial markup.
+///
+/// ```rust
+/// # let result =
+/// format_xml::xfmt! { strict: html:
+/// 	<p>"Line"<br>"Next line"<input type="checkbox" checked></p>
+/// 	<svg width="10" height="10"><rect width="10" height="10" /></svg>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<p>Line<br>Next line<input type="checkbox" checked></p><svg width="10" height="10"><rect width="10" height="10" /></svg>"#);
+/// ```
+///
+/// The resulting string is `<p>Line<br>Next line<input type="checkbox" checked></p><svg width="10" height="10"><rect width="10" height="10" /></svg>`.
+///
+/// Strict templates with the `html` policy follow the html rules instead.
+/// The void elements such as `<br>`, `<img>` and `<input>` have no close tag and cannot have content.
+/// Other elements cannot be self-closing, except inside `<svg>` and `<math>`:
+///
+/// ```compile_fail
+/// format_xml::xfmt! { strict: html: <div class="spacer" /> };
+/// ```
+///
+/// ```compile_fail
+/// format_xml::xfmt! { strict: html: <br>"text"</br> };
+/// ```
+///
+/// ### Obfuscated literals
+///
+/// ```rust
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<meta name="api-key" content=obf("secret-token") />
+/// 	<p>obf("internal build")</p>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<meta name="api-key" content="secret-token" /><p>internal build</p>"#);
+/// ```
+///
+/// The resulting string is `<meta name="api-key" content="secret-token" /><p>internal build</p>`.
+///
+/// Text and attribute value literals wrapped in `obf(...)` are obfuscated with [obfstr](https://crates.io/crates/obfstr) when the `obf` feature is enabled.
+/// The other literals are left as plain strings, the `obfstr` feature obfuscates all of them instead.
+///
 /// ### Control flow
 ///
 /// ```rust
@@ -166,78 +531,361 @@
 ///
 /// The resulting string is `<ul><li>1*5=5</li><li>2*5=10</li><li>3*5=15</li><li>4*5=20</li><li>5*5=25</li></ul>`.
 ///
-/// Control flow is only supported outside tags, not in attributes.
+/// The `let` statements accept a type annotation for identifiers and tuples, `let total: u64 = 1 << 40;`.
 ///
-/// ### Escape hatch
+/// ```rust
+/// let items = ["a", "b", "c"];
+///
+/// # let result =
+/// format_xml::xfmt! {
+/// 	let mut iter = items.iter().peekable();
+/// 	while let Some(item) = (iter.next()) {
+/// 		<span>{item}</span>
+/// 		if (iter.peek().is_some()) { ", " }
+/// 	}
+/// }
+/// # .to_string();
+/// # assert_eq!(result, "<span>a</span>, <span>b</span>, <span>c</span>");
+/// ```
+///
+/// The resulting string is `<span>a</span>, <span>b</span>, <span>c</span>`.
 ///
 /// ```rust
-/// fn compose(f: &mut std::fmt::Formatter, a: i32) -> std::fmt::Result {
-/// 	format_xml::write!(f, <span>{a}</span>)
+/// let pages = ["one", "two", "", "four"];
+///
+/// # let result =
+/// format_xml::xfmt! {
+/// 	let mut index = 0;
+/// 	'pages: loop {
+/// 		let page = pages[index];
+/// 		if (page.is_empty()) {
+/// 			"."
+/// 			break 'pages;
+/// 		}
+/// 		<p>{page}</p>
+/// 		|_| index += 1;
+/// 	}
 /// }
+/// # .to_string();
+/// # assert_eq!(result, "<p>one</p><p>two</p>.");
+/// ```
+///
+/// The resulting string is `<p>one</p><p>two</p>.`.
+///
+/// The `else` block of a `let` statement with a refutable pattern is written instead of the remainder of the enclosing block,
+/// that is the rest of the loop body, `if` branch, `match` arm or template the `let` statement appears in:
+///
+/// ```rust
+/// let users = [Some("Alice"), None, Some("Bob")];
 ///
 /// # let result =
 /// format_xml::xfmt! {
-/// 	<p>|f| compose(f, 42)?;</p>
+/// 	for user in (&users) {
+/// 		let Some(name) = user else {
+/// 			<li>"Anonymous"</li>
+/// 		};
+/// 		<li><b>{name}</b></li>
+/// 	}
 /// }
 /// # .to_string();
-/// # assert_eq!(result, r#"<p><span>42</span></p>"#);
+/// # assert_eq!(result, "<li><b>Alice</b></li><li>Anonymous</li><li><b>Bob</b></li>");
 /// ```
 ///
-/// The resulting string is `<p><span>42</span></p>`.
+/// The resulting string is `<li><b>Alice</b></li><li>Anonymous</li><li><b>Bob</b></li>`.
 ///
-/// Closure syntax provides an escape hatch to inject code if needed.
-/// The argument's type is [`&mut Formatter`](std::fmt::Formatter).
+/// The `else` block does not return from the template, the nodes after the enclosing block are still written.
 ///
-/// Important! Anything written to the formatter `f` is not escaped.
-/// This makes it useful to compose different components wich is not possible with `{}`.
-#[macro_export]
-macro_rules! xfmt {
-	(move $($tt:tt)*) => {
-		$crate::fmt(move |_f| {
-			$crate::__xfmt!{_f concat() $($tt)*}
-			Ok(())
-		})
-	};
-	($($tt:tt)*) => {
-		$crate::fmt(|_f| {
-			$crate::__xfmt!{_f concat() $($tt)*}
-			Ok(())
-		})
-	};
-}
-
-
-
-#[macro_export]
-#[doc(hidden)]
-macro_rules! __xfmt {
-	// tag close
-	($f:ident concat($($texts:expr,)*) </ @ident($tag:expr) > $($tail:tt)*) => {
-		$crate::__xfmt!{$f concat($($texts,)* "</", $tag, ">",) $($tail)*}
-	};
-	($f:ident concat($($texts:expr,)*) </ $($tail:tt)*) => {
-		$crate::__xfmt_ident!{__xfmt! [$f concat($($texts,)*) </] $($tail)*}
-	};
-
-	// comment
-	($f:ident concat($($texts:expr,)*) <!-- $($tail:tt)*) => {
-		$crate::__write_str!{$f concat($($texts,)* "<!-- ",)}
-		$crate::__xfmt_comment!{$f () $($tail)*}
-	};
-
-	// CDATA
-	($f:ident concat($($texts:expr,)*) <![CDATA[ $($tt:tt)* ]]> $($tail:tt)*) => {
-		$crate::__write_str!{$f concat($($texts,)* "<![CDATA[",)}
-		{
-			let _f = $crate::EscapeCharData::wrap($f);
-			$crate::__fmt!{_f $($tt)*}
-		}
-		$crate::__xfmt!{$f concat("]]>",) $($tail)*}
-	};
-
+/// **Note**: the close tags in the remainder of the block are skipped as well.
+/// Place the `let` statement before opening any elements in its block, or close the elements left open in the `else` block itself.
+/// Otherwise the output is malformed, eg. `<p> let Some(x) = y else { "none" }; {x} </p>` writes `<p>none` when `y` is `None`.
+///
+/// The `else` block after a `for` loop is written only when the loop body ran zero times:
+///
+/// ```rust
+/// let results: Vec<&str> = Vec::new();
+///
+/// # let result =
+/// format_xml::xfmt! {
+/// 	for item in (&results) {
+/// 		<li>{item}</li>
+/// 	}
+/// 	else {
+/// 		<p>"No results"</p>
+/// 	}
+/// }
+/// # .to_string();
+/// # assert_eq!(result, "<p>No results</p>");
+/// ```
+///
+/// The resulting string is `<p>No results</p>`.
+///
+/// The `sep` block after a `for` loop is written between consecutive iterations, it may be followed by an `else` block:
+///
+/// ```rust
+/// let tags = ["rust", "xml", "html"];
+///
+/// # let result =
+/// format_xml::xfmt! {
+/// 	for tag in (&tags) {
+/// 		<a href=("/tags/" {tag})>{tag}</a>
+/// 	}
+/// 	sep {
+/// 		" · "
+/// 	}
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<a href="/tags/rust">rust</a> · <a href="/tags/xml">xml</a> · <a href="/tags/html">html</a>"#);
+/// ```
+///
+/// The resulting string is `<a href="/tags/rust">rust</a> · <a href="/tags/xml">xml</a> · <a href="/tags/html">html</a>`.
+///
+/// The separator is written at the start of every iteration but the first, an iteration ending in `continue` is still separated from the next one.
+///
+/// Loops may be labeled and `break` and `continue` (with an optional label) are accepted inside `for`, `while` and `loop` bodies.
+/// Any literal text preceding them is written before the loop is exited or continued.
+///
+/// The template may be formatted more than once and cannot mutate the captured variables,
+/// create the iterator of a `while let` loop with `let` inside the template.
+///
+/// Attribute values accept `if` chains and `match` expressions.
+/// The branches contain literals, escaped `{value}`s and escape hatches like the attribute value itself:
+///
+/// ```rust
+/// enum State { Ok, Err(i32) }
+/// let active = true;
+/// let title: Option<&str> = None;
+/// let state = State::Err(404);
+///
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<a
+/// 		class=if (active) { "link active" } else { "link" }
+/// 		title=if let Some(title) = (title) { {title} }
+/// 		data-state=match state { State::Ok => "ok", State::Err(code) => { "err-"{code} } }
+/// 	>"Home"</a>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<a class="link active" title="" data-state="err-404">Home</a>"#);
+/// ```
+///
+/// The resulting string is `<a class="link active" title="" data-state="err-404">Home</a>`.
+///
+/// The quotes are written even if no branch is taken.
+///
+/// A `for` loop in place of an attribute repeats the attributes in its body.
+///
+/// ```rust
+/// let data = [("id", 42), ("count", 3)];
+///
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<div class="item" for (key, value) in (&data) { {format_args!("data-{}", key)}={value} }></div>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<div class="item" data-id="42" data-count="3"></div>"#);
+/// ```
+///
+/// The resulting string is `<div class="item" data-id="42" data-count="3"></div>`.
+///
+/// ### Escape hatch
+///
+/// ```rust
+/// fn compose(f: &mut std::fmt::Formatter, a: i32) -> std::fmt::Result {
+/// 	format_xml::prelude::write!(f, <span>{a}</span>)
+/// }
+///
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<p>|f| compose(f, 42)?;</p>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, r#"<p><span>42</span></p>"#);
+/// ```
+///
+/// The resulting string is `<p><span>42</span></p>`.
+///
+/// Closure syntax provides an escape hatch to inject code if needed.
+/// The argument's type is [`&mut Formatter`](std::fmt::Formatter) and it is only in scope for the injected code.
+///
+/// Important! Anything written to the formatter `f` is not escaped.
+/// This makes it useful to compose different components wich is not possible with `{}`.
+///
+/// The escape hatch is also accepted in place of an attribute to write attributes directly, see [`xattr!`].
+///
+/// ```rust
+/// let chunks = ["<script>", "alert(1)", "</script>"];
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<pre title=|f: attr| { for chunk in &chunks { f.write_str(chunk)?; } }>
+/// 		|f: text| { for chunk in &chunks { writeln!(f, "{}", chunk)?; } }
+/// 	</pre>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, "<pre title=\"&lt;script&gt;alert(1)&lt;/script&gt;\">&lt;script&gt;\nalert(1)\n&lt;/script&gt;\n</pre>");
+/// ```
+///
+/// The resulting string is `<pre title="&lt;script&gt;alert(1)&lt;/script&gt;">&lt;script&gt;\nalert(1)\n&lt;/script&gt;\n</pre>`.
+///
+/// The escape hatch written as `|f: text|` binds an [`EscapeText`](crate::EscapeText) writer instead and `|f: attr|` in attribute values binds an [`EscapeAttrValue`](crate::EscapeAttrValue) writer.
+/// Use these to stream values through the escaping, use `|f|` only to write markup.
+/// They escape the same as the default policy regardless of the policy of the template.
+// The formatter is bound to `_f` which is passed by name to the internal macros.
+// Local variables introduced by `macro_rules!` are hygienic: user code (let bindings, escape hatches, nested templates)
+// cannot observe or shadow this binding even when it uses the name `_f` itself.
+#[macro_export]
+macro_rules! xfmt {
+	(move strict: html: $($tt:tt)*) => {{
+		$crate::__xfmt_strict!{html [] $($tt)*}
+		$crate::xfmt!{move html: $($tt)*}
+	}};
+	(move strict: $policy:ident: $($tt:tt)*) => {{
+		$crate::__xfmt_strict!{xml [] $($tt)*}
+		$crate::xfmt!{move $policy: $($tt)*}
+	}};
+	(move strict: $($tt:tt)*) => {{
+		$crate::__xfmt_strict!{xml [] $($tt)*}
+		$crate::xfmt!{move $($tt)*}
+	}};
+	(strict: html: $($tt:tt)*) => {{
+		$crate::__xfmt_strict!{html [] $($tt)*}
+		$crate::xfmt!{html: $($tt)*}
+	}};
+	(strict: $policy:ident: $($tt:tt)*) => {{
+		$crate::__xfmt_strict!{xml [] $($tt)*}
+		$crate::xfmt!{$policy: $($tt)*}
+	}};
+	(strict: $($tt:tt)*) => {{
+		$crate::__xfmt_strict!{xml [] $($tt)*}
+		$crate::xfmt!{$($tt)*}
+	}};
+	(move $policy:ident: $($tt:tt)*) => {
+		$crate::fmt(move |_f| {
+			let _f = &mut $crate::PolicyWriter::<$crate::__xfmt_policy!($policy)>::new(_f);
+			$crate::__xfmt!{_f concat() $($tt)*}
+			Ok(())
+		})
+	};
+	($policy:ident: $($tt:tt)*) => {
+		$crate::fmt(|_f| {
+			let _f = &mut $crate::PolicyWriter::<$crate::__xfmt_policy!($policy)>::new(_f);
+			$crate::__xfmt!{_f concat() $($tt)*}
+			Ok(())
+		})
+	};
+	(move $($tt:tt)*) => {
+		$crate::fmt(move |_f| {
+			$crate::__xfmt!{_f concat() $($tt)*}
+			Ok(())
+		})
+	};
+	($($tt:tt)*) => {
+		$crate::fmt(|_f| {
+			$crate::__xfmt!{_f concat() $($tt)*}
+			Ok(())
+		})
+	};
+}
+
+
+/// Xml-like attribute list formatting syntax.
+///
+/// Returns a displayable object rendering only the attributes, using the same syntax as the attributes of a tag in [`xfmt!`].
+/// Every attribute is preceded by a space and the values are escaped.
+///
+/// ```rust
+/// let id = "main";
+/// let class = "a&b";
+/// let attrs = format_xml::xattr! { id={id} class={class} hidden };
+///
+/// # let result =
+/// format_xml::xfmt! {
+/// 	<div |f| f.write_fmt(format_args!("{}", attrs))?;></div>
+/// }
+/// # .to_string();
+/// # assert_eq!(result, format_xml::xfmt! { <div id={id} class={class} hidden></div> }.to_string());
+/// # assert_eq!(result, r#"<div id="main" class="a&amp;b" hidden></div>"#);
+/// ```
+///
+/// The resulting string is `<div id="main" class="a&amp;b" hidden></div>`.
+///
+/// Splice the attributes into a tag with the escape hatch, writing them with `{}` would escape them again.
+#[macro_export]
+macro_rules! xattr {
+	(move $($tt:tt)*) => {
+		$crate::fmt(move |_f| {
+			$crate::__xfmt_attrs!{__xattr_end! [] _f concat() $($tt)*}
+			Ok(())
+		})
+	};
+	($($tt:tt)*) => {
+		$crate::fmt(|_f| {
+			$crate::__xfmt_attrs!{__xattr_end! [] _f concat() $($tt)*}
+			Ok(())
+		})
+	};
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __xattr_end {
+	([] $f:ident concat($($texts:expr,)*)) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+	};
+	([] $f:ident concat($($texts:expr,)*) $($tail:tt)+) => {
+		compile_error!(concat!("expected an attribute, found: ", stringify!($($tail)+)));
+	};
+}
+
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __xfmt {
+	// fragment
+	($f:ident concat($($texts:expr,)*) < > $($tail:tt)*) => {
+		$crate::__xfmt!{$f concat($($texts,)*) $($tail)*}
+	};
+	($f:ident concat($($texts:expr,)*) </ > $($tail:tt)*) => {
+		$crate::__xfmt!{$f concat($($texts,)*) $($tail)*}
+	};
+
+	// tag close
+	($f:ident concat($($texts:expr,)*) </ @ident($tag:expr) > $($tail:tt)*) => {
+		$crate::__xfmt!{$f concat($($texts,)* "</", $tag, ">",) $($tail)*}
+	};
+	($f:ident concat($($texts:expr,)*) </ @ident_fmt([$($name:expr,)*] {$($e:tt)*}) > $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "</", $($name,)*)}
+		::core::fmt::Write::write_fmt(&mut $crate::EscapeAttrName::suffix(&mut *$f), ::core::format_args!("{}", $($e)*))?;
+		$crate::__xfmt!{$f concat(">",) $($tail)*}
+	};
+	($f:ident concat($($texts:expr,)*) </ {$($e:tt)*} > $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "</",)}
+		$crate::__xfmt_name!{$f $($e)*}
+		$crate::__xfmt!{$f concat(">",) $($tail)*}
+	};
+	($f:ident concat($($texts:expr,)*) </ $($tail:tt)*) => {
+		$crate::__xfmt_ident!{__xfmt! [$f concat($($texts,)*) </] $($tail)*}
+	};
+
+	// comment
+	($f:ident concat($($texts:expr,)*) <!-- $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "<!-- ",)}
+		$crate::__xfmt_comment!{$f () $($tail)*}
+	};
+
+	// CDATA
+	($f:ident concat($($texts:expr,)*) <![CDATA[ $($tt:tt)* ]]> $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "<![CDATA[",)}
+		{
+			let mut _w = $crate::EscapeCharData::wrap(&mut *$f);
+			let _f = &mut _w;
+			$crate::__xfmt!{_f concat() $($tt)*}
+		}
+		$crate::__xfmt!{$f concat("]]>",) $($tail)*}
+	};
+
 	// doctype
 	($f:ident concat($($texts:expr,)*) <! @ident($tag:expr) $($tail:tt)*) => {
-		$crate::__xfmt_attrs!{__xfmt_close_tag! $f concat($($texts,)* "<!", $tag,) $($tail)*}
+		$crate::__xfmt_doctype!{[$tag] $f concat($($texts,)* "<!", $tag,) $($tail)*}
 	};
 	($f:ident concat($($texts:expr,)*) <! $($tail:tt)*) => {
 		$crate::__xfmt_ident!{__xfmt! [$f concat($($texts,)*) <!] $($tail)*}
@@ -245,7 +893,7 @@ macro_rules! __xfmt {
 
 	// declaration
 	($f:ident concat($($texts:expr,)*) <? @ident($tag:expr) $($tail:tt)*) => {
-		$crate::__xfmt_attrs!{__xfmt_close_decl! $f concat($($texts,)* "<?", $tag,) $($tail)*}
+		$crate::__xfmt_attrs!{__xfmt_close_decl! ["<?", $tag] $f concat($($texts,)* "<?", $tag,) $($tail)*}
 	};
 	($f:ident concat($($texts:expr,)*) <? $($tail:tt)*) => {
 		$crate::__xfmt_ident!{__xfmt! [$f concat($($texts,)*) <?] $($tail)*}
@@ -253,12 +901,30 @@ macro_rules! __xfmt {
 
 	// tag open
 	($f:ident concat($($texts:expr,)*) < @ident($tag:expr) $($tail:tt)*) => {
-		$crate::__xfmt_attrs!{__xfmt_close_tag! $f concat($($texts,)* "<", $tag,) $($tail)*}
+		$crate::__xfmt_lint!{"element" $tag}
+		$crate::__xfmt_attrs!{__xfmt_close_tag! ["<", $tag] $f concat($($texts,)* "<", $tag,) $($tail)*}
+	};
+	($f:ident concat($($texts:expr,)*) < @ident_fmt([$($name:expr,)*] {$($e:tt)*}) $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "<", $($name,)*)}
+		::core::fmt::Write::write_fmt(&mut $crate::EscapeAttrName::suffix(&mut *$f), ::core::format_args!("{}", $($e)*))?;
+		$crate::__xfmt_attrs!{__xfmt_close_tag! ["<", $($name,)* "{", stringify!($($e)*), "}"] $f concat() $($tail)*}
+	};
+	($f:ident concat($($texts:expr,)*) < {$($e:tt)*} $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "<",)}
+		$crate::__xfmt_name!{$f $($e)*}
+		$crate::__xfmt_attrs!{__xfmt_close_tag! ["<{", stringify!($($e)*), "}"] $f concat() $($tail)*}
 	};
 	($f:ident concat($($texts:expr,)*) < $($tail:tt)*) => {
 		$crate::__xfmt_ident!{__xfmt! [$f concat($($texts,)*) <] $($tail)*}
 	};
 
+	// obfuscated text
+	($f:ident concat($($texts:expr,)*) obf($text:literal) $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		$f.write_str($crate::__obf!($text))?;
+		$crate::__xfmt!{$f concat() $($tail)*}
+	};
+
 	// text
 	($f:ident concat($($texts:expr,)*) $text1:literal $text2:literal $($tail:tt)*) => {
 		$crate::__xfmt!{$f concat($($texts,)* $text1, $text2,) $($tail)*}
@@ -267,151 +933,793 @@ macro_rules! __xfmt {
 		$crate::__xfmt!{$f concat($($texts,)* $text,) $($tail)*}
 	};
 
+	// render
+	($f:ident concat($($texts:expr,)*) {@ $e:expr} $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		$crate::XfmtWrite::__xfmt_raw($f, ::core::format_args!("{}", $crate::RenderDisplay(&$e)))?;
+		$crate::__xfmt_words!{$f concat() value $($tail)*}
+	};
+
+	// raw
+	($f:ident concat($($texts:expr,)*) {% $($e:tt)*} $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		$crate::__xfmt_raw!{$f [] $($e)*}
+		$crate::__xfmt_words!{$f concat() value $($tail)*}
+	};
+
 	// format
 	($f:ident concat($($texts:expr,)*) {$($e:tt)*} $($tail:tt)*) => {
 		$crate::__write_str!{$f concat($($texts,)*)}
-		::core::fmt::write($crate::EscapeText::wrap($f), $crate::__xfmt_format!([] $($e)*))?;
-		$crate::__xfmt!{$f concat() $($tail)*}
+		$crate::__xfmt_format!{$f __xfmt_text [] $($e)*}
+		$crate::__xfmt_words!{$f concat() value $($tail)*}
 	};
 
 	// escape hatch
+	($f:ident concat($($texts:expr,)*) |$ff:ident: text| $block:block $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		{
+			let $ff = $crate::EscapeText::wrap(&mut *$f);
+			$block
+		}
+		$crate::__xfmt!{$f concat() $($tail)*}
+	};
+	($f:ident concat($($texts:expr,)*) |$ff:ident: text| $stmt:stmt; $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		{
+			let $ff = $crate::EscapeText::wrap(&mut *$f);
+			$stmt
+		}
+		$crate::__xfmt!{$f concat() $($tail)*}
+	};
 	($f:ident concat($($texts:expr,)*) |$ff:pat_param| $block:block $($tail:tt)*) => {
 		$crate::__write_str!{$f concat($($texts,)*)}
-		let $ff = &mut *$f;
-		$block
+		{
+			let $ff = &mut *$f;
+			$block
+		}
 		$crate::__xfmt!{$f concat() $($tail)*}
 	};
 	($f:ident concat($($texts:expr,)*) |$ff:pat_param| $stmt:stmt; $($tail:tt)*) => {
 		$crate::__write_str!{$f concat($($texts,)*)}
-		let $ff = &mut *$f;
-		$stmt
+		{
+			let $ff = &mut *$f;
+			$stmt
+		}
 		$crate::__xfmt!{$f concat() $($tail)*}
 	};
 
+	// let else
+	($f:ident concat($($texts:expr,)*) let $p:pat = ($e:expr) else { $($els:tt)* }; $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		if let $p = $e {
+			$crate::__xfmt!{$f concat() $($tail)*}
+		}
+		else {
+			$crate::__xfmt!{$f concat() $($els)*}
+		}
+	};
+
 	// let
+	($f:ident concat($($texts:expr,)*) let mut $p:ident: $t:ty = $e:expr; $($tail:tt)*) => {
+		let mut $p: $t = $e;
+		$crate::__xfmt!{$f concat($($texts,)*) $($tail)*}
+	};
+	($f:ident concat($($texts:expr,)*) let $p:tt: $t:ty = $e:expr; $($tail:tt)*) => {
+		let $p: $t = $e;
+		$crate::__xfmt!{$f concat($($texts,)*) $($tail)*}
+	};
 	($f:ident concat($($texts:expr,)*) let $p:pat = $e:expr; $($tail:tt)*) => {
 		let $p = $e;
 		$crate::__xfmt!{$f concat($($texts,)*) $($tail)*}
 	};
+	($f:ident concat($($texts:expr,)*) let $p:pat = $($tail:tt)*) => {
+		$crate::__xfmt_let_else!{[$f concat($($texts,)*) $p] [] $($tail)*}
+	};
 
 	// if
 	($f:ident concat($($texts:expr,)*) if $($tail:tt)*) => {
 		$crate::__write_str!{$f concat($($texts,)*)}
-		$crate::__xfmt_if!{$f [] if $($tail)*}
+		$crate::__xfmt_if!{[__xfmt! $f concat()] [__xfmt! $f concat()] [] if $($tail)*}
 	};
 
 	// match
 	($f:ident concat($($texts:expr,)*) match ($e:expr) { $($body:tt)* } $($tail:tt)*) => {
 		$crate::__write_str!{$f concat($($texts,)*)}
-		$crate::__xfmt_match!{$f match ($e) {} $($body)*}
+		$crate::__xfmt_match!{[__xfmt! $f concat()] match ($e) {} $($body)*}
 		$crate::__xfmt!{$f concat() $($tail)*}
 	};
 	($f:ident concat($($texts:expr,)*) match $($tail:tt)*) => {
 		$crate::__with_parens!{__xfmt! [$f concat($($texts:expr,)*) match] [] $($tail)*}
 	};
 
+	// for sep
+	($f:ident concat($($texts:expr,)*) $($label:lifetime:)? for $p:pat in ($e:expr) { $($body:tt)*} sep { $($sep:tt)* } else { $($els:tt)* } $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		let mut _first = true;
+		$($label:)? for $p in $e {
+			if !_first {
+				$crate::__xfmt!{$f concat() $($sep)*}
+			}
+			_first = false;
+			$crate::__xfmt!{$f concat() $($body)*}
+		}
+		if _first {
+			$crate::__xfmt!{$f concat() $($els)*}
+		}
+		$crate::__xfmt!{$f concat() $($tail)*}
+	};
+	($f:ident concat($($texts:expr,)*) $($label:lifetime:)? for $p:pat in ($e:expr) { $($body:tt)*} sep { $($sep:tt)* } $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		let mut _first = true;
+		$($label:)? for $p in $e {
+			if !_first {
+				$crate::__xfmt!{$f concat() $($sep)*}
+			}
+			_first = false;
+			$crate::__xfmt!{$f concat() $($body)*}
+		}
+		$crate::__xfmt!{$f concat() $($tail)*}
+	};
+
+	// for else
+	($f:ident concat($($texts:expr,)*) $($label:lifetime:)? for $p:pat in ($e:expr) { $($body:tt)*} else { $($els:tt)* } $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		let mut _empty = true;
+		$($label:)? for $p in $e {
+			_empty = false;
+			$crate::__xfmt!{$f concat() $($body)*}
+		}
+		if _empty {
+			$crate::__xfmt!{$f concat() $($els)*}
+		}
+		$crate::__xfmt!{$f concat() $($tail)*}
+	};
+
 	// for
-	($f:ident concat($($texts:expr,)*) for $p:pat in ($e:expr) { $($body:tt)*} $($tail:tt)*) => {
+	($f:ident concat($($texts:expr,)*) $($label:lifetime:)? for $p:pat in ($e:expr) { $($body:tt)*} $($tail:tt)*) => {
 		$crate::__write_str!{$f concat($($texts,)*)}
-		for $p in $e {
+		$($label:)? for $p in $e {
 			$crate::__xfmt!{$f concat() $($body)*}
 		}
 		$crate::__xfmt!{$f concat() $($tail)*}
 	};
-	($f:ident concat($($texts:expr,)*) for $p:pat in $($tail:tt)*) => {
-		$crate::__with_parens!{__xfmt! [$f concat($($texts,)*) for $p in] [] $($tail)*}
+	($f:ident concat($($texts:expr,)*) $($label:lifetime:)? for $p:pat in $($tail:tt)*) => {
+		$crate::__with_parens!{__xfmt! [$f concat($($texts,)*) $($label:)? for $p in] [] $($tail)*}
+	};
+
+	// while let
+	($f:ident concat($($texts:expr,)*) $($label:lifetime:)? while let $p:pat = ($e:expr) { $($body:tt)* } $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		$($label:)? while let $p = $e {
+			$crate::__xfmt!{$f concat() $($body)*}
+		}
+		$crate::__xfmt!{$f concat() $($tail)*}
+	};
+	($f:ident concat($($texts:expr,)*) $($label:lifetime:)? while let $p:pat = $($tail:tt)*) => {
+		$crate::__with_parens!{__xfmt! [$f concat($($texts,)*) $($label:)? while let $p =] [] $($tail)*}
+	};
+
+	// while
+	($f:ident concat($($texts:expr,)*) $($label:lifetime:)? while ($e:expr) { $($body:tt)* } $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		$($label:)? while $e {
+			$crate::__xfmt!{$f concat() $($body)*}
+		}
+		$crate::__xfmt!{$f concat() $($tail)*}
+	};
+	($f:ident concat($($texts:expr,)*) $($label:lifetime:)? while $($tail:tt)*) => {
+		$crate::__with_parens!{__xfmt! [$f concat($($texts,)*) $($label:)? while] [] $($tail)*}
+	};
+
+	// loop
+	($f:ident concat($($texts:expr,)*) $($label:lifetime:)? loop { $($body:tt)* } $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		$($label:)? loop {
+			$crate::__xfmt!{$f concat() $($body)*}
+		}
+		$crate::__xfmt!{$f concat() $($tail)*}
+	};
+
+	// break
+	($f:ident concat($($texts:expr,)*) break $($label:lifetime)?; $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		break $($label)?;
+		$crate::__xfmt!{$f concat() $($tail)*}
 	};
 
+	// continue
+	($f:ident concat($($texts:expr,)*) continue $($label:lifetime)?; $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		continue $($label)?;
+		$crate::__xfmt!{$f concat() $($tail)*}
+	};
+
+	// bare words
+	($f:ident concat($($texts:expr,)*) $word:ident $($tail:tt)*) => {
+		$crate::__xfmt_words!{$f concat($($texts,)*) start $word $($tail)*}
+	};
+	($f:ident concat($($texts:expr,)*) , $($tail:tt)*) => { $crate::__xfmt_words!{$f concat($($texts,)*) next , $($tail)*} };
+	($f:ident concat($($texts:expr,)*) . $($tail:tt)*) => { $crate::__xfmt_words!{$f concat($($texts,)*) next . $($tail)*} };
+	($f:ident concat($($texts:expr,)*) ! $($tail:tt)*) => { $crate::__xfmt_words!{$f concat($($texts,)*) next ! $($tail)*} };
+	($f:ident concat($($texts:expr,)*) ? $($tail:tt)*) => { $crate::__xfmt_words!{$f concat($($texts,)*) next ? $($tail)*} };
+	($f:ident concat($($texts:expr,)*) ; $($tail:tt)*) => { $crate::__xfmt_words!{$f concat($($texts,)*) next ; $($tail)*} };
+	($f:ident concat($($texts:expr,)*) : $($tail:tt)*) => { $crate::__xfmt_words!{$f concat($($texts,)*) next : $($tail)*} };
+
 	// optimization
 	($f:ident concat($($texts:expr,)*) ($($tt:tt)*) $($tail:tt)*) => {
 		$crate::__xfmt!{$f concat($($texts,)*) $($tt)*}
 		$crate::__xfmt!{$f concat() $($tail)*}
 	};
-
-	// term
-	($f:ident concat()) => {};
-	($f:ident concat($($texts:expr,)*)) => {
-		$crate::__write_str!{$f concat($($texts,)*)}
+
+	// term
+	($f:ident concat()) => {};
+	($f:ident concat($($texts:expr,)*)) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+	};
+}
+
+
+
+// Writes a run of bare words separated by spaces, punctuation is attached to the previous word
+// The run continues after a value in the `value` state, where a literal ends the run
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __xfmt_words {
+	// keywords end the run
+	($f:ident concat($($texts:expr,)*) $state:ident if $($tail:tt)*) => { $crate::__xfmt_words!{@end $state $f concat($($texts,)*) if $($tail)*} };
+	($f:ident concat($($texts:expr,)*) $state:ident else $($tail:tt)*) => { $crate::__xfmt_words!{@end $state $f concat($($texts,)*) else $($tail)*} };
+	($f:ident concat($($texts:expr,)*) $state:ident match $($tail:tt)*) => { $crate::__xfmt_words!{@end $state $f concat($($texts,)*) match $($tail)*} };
+	($f:ident concat($($texts:expr,)*) $state:ident for $($tail:tt)*) => { $crate::__xfmt_words!{@end $state $f concat($($texts,)*) for $($tail)*} };
+	($f:ident concat($($texts:expr,)*) $state:ident while $($tail:tt)*) => { $crate::__xfmt_words!{@end $state $f concat($($texts,)*) while $($tail)*} };
+	($f:ident concat($($texts:expr,)*) $state:ident loop $($tail:tt)*) => { $crate::__xfmt_words!{@end $state $f concat($($texts,)*) loop $($tail)*} };
+	($f:ident concat($($texts:expr,)*) $state:ident break $($tail:tt)*) => { $crate::__xfmt_words!{@end $state $f concat($($texts,)*) break $($tail)*} };
+	($f:ident concat($($texts:expr,)*) $state:ident continue $($tail:tt)*) => { $crate::__xfmt_words!{@end $state $f concat($($texts,)*) continue $($tail)*} };
+	($f:ident concat($($texts:expr,)*) $state:ident let $($tail:tt)*) => { $crate::__xfmt_words!{@end $state $f concat($($texts,)*) let $($tail)*} };
+	($f:ident concat($($texts:expr,)*) $state:ident obf($text:literal) $($tail:tt)*) => { $crate::__xfmt_words!{@end $state $f concat($($texts,)*) obf($text) $($tail)*} };
+	(@end start $f:ident concat($($texts:expr,)*) $kw:ident $($tail:tt)*) => {
+		compile_error!(concat!("malformed `", stringify!($kw), "`, quote the keyword to write it as text: ", stringify!($kw $($tail)*)));
+	};
+	(@end $state:ident $f:ident concat($($texts:expr,)*) $($tail:tt)*) => {
+		$crate::__xfmt!{$f concat($($texts,)*) $($tail)*}
+	};
+
+	// punctuation
+	($f:ident concat($($texts:expr,)*) $state:ident , $($tail:tt)*) => { $crate::__xfmt_words!{$f concat($($texts,)* ",",) next $($tail)*} };
+	($f:ident concat($($texts:expr,)*) $state:ident . $($tail:tt)*) => { $crate::__xfmt_words!{$f concat($($texts,)* ".",) next $($tail)*} };
+	($f:ident concat($($texts:expr,)*) $state:ident ! $($tail:tt)*) => { $crate::__xfmt_words!{$f concat($($texts,)* "!",) next $($tail)*} };
+	($f:ident concat($($texts:expr,)*) $state:ident ? $($tail:tt)*) => { $crate::__xfmt_words!{$f concat($($texts,)* "?",) next $($tail)*} };
+	($f:ident concat($($texts:expr,)*) $state:ident ; $($tail:tt)*) => { $crate::__xfmt_words!{$f concat($($texts,)* ";",) next $($tail)*} };
+	($f:ident concat($($texts:expr,)*) $state:ident : $($tail:tt)*) => { $crate::__xfmt_words!{$f concat($($texts,)* ":",) next $($tail)*} };
+
+	// words
+	($f:ident concat($($texts:expr,)*) start $word:ident $($tail:tt)*) => {
+		$crate::__xfmt_words!{$f concat($($texts,)* stringify!($word),) next $($tail)*}
+	};
+	($f:ident concat($($texts:expr,)*) $state:ident $word:ident $($tail:tt)*) => {
+		$crate::__xfmt_words!{$f concat($($texts,)* " ", stringify!($word),) next $($tail)*}
+	};
+	($f:ident concat($($texts:expr,)*) next $text:literal $($tail:tt)*) => {
+		$crate::__xfmt_words!{$f concat($($texts,)* " ", $text,) next $($tail)*}
+	};
+
+	// values are separated from the words like a word
+	($f:ident concat($($texts:expr,)*) next {$($e:tt)*} $($tail:tt)*) => {
+		$crate::__xfmt!{$f concat($($texts,)* " ",) {$($e)*} $($tail)*}
+	};
+
+	// anything else ends the run
+	($f:ident concat($($texts:expr,)*) $state:ident $($tail:tt)*) => {
+		$crate::__xfmt!{$f concat($($texts,)*) $($tail)*}
+	};
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __xfmt_close_tag {
+	($ctx:tt $f:ident concat($($texts:expr,)*) > $($tail:tt)*) => {
+		$crate::__xfmt!{$f concat($($texts,)* ">",) $($tail)*}
+	};
+	($ctx:tt $f:ident concat($($texts:expr,)*) /> $($tail:tt)*) => {
+		$crate::__xfmt!{$f concat($($texts,)* " />",) $($tail)*}
+	};
+	([$($tag:expr),*] $f:ident concat($($texts:expr,)*)) => {
+		$crate::__xfmt!{$f concat($($texts,)* ">",)}
+		compile_error!(concat!("missing closing `>` after `", $($tag,)* " ...`"));
+	};
+	([$($tag:expr),*] $f:ident concat($($texts:expr,)*) $next:tt $($tail:tt)*) => {
+		compile_error!(concat!("missing closing `>` after `", $($tag,)* " ...`, found `", stringify!($next), "`"));
+	};
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __xfmt_doctype {
+	([$tag:expr] $f:ident concat($($texts:expr,)*) > $($tail:tt)*) => {
+		$crate::__xfmt!{$f concat($($texts,)* ">",) $($tail)*}
+	};
+	([$tag:expr] $f:ident concat($($texts:expr,)*) $word:ident $($tail:tt)*) => {
+		$crate::__xfmt_doctype!{[$tag] $f concat($($texts,)* " ", stringify!($word),) $($tail)*}
+	};
+	([$tag:expr] $f:ident concat($($texts:expr,)*) $text:literal $($tail:tt)*) => {
+		const _: () = if !$crate::__doctype_literal($text) {
+			::core::panic!("{}", ::core::concat!("the literals in `<!", $tag, ">` cannot contain a double quote: ", stringify!($text)));
+		};
+		$crate::__xfmt_doctype!{[$tag] $f concat($($texts,)* " \"", $text, "\"",) $($tail)*}
+	};
+	([$tag:expr] $f:ident concat($($texts:expr,)*) {$($e:tt)*} $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* " \"",)}
+		::core::fmt::Write::write_fmt($crate::EscapeDoctypeLiteral::wrap(&mut *$f), ::core::format_args!("{}", $($e)*))?;
+		$crate::__xfmt_doctype!{[$tag] $f concat("\"",) $($tail)*}
+	};
+	([$tag:expr] $f:ident concat($($texts:expr,)*) [$($subset:tt)*] $($tail:tt)*) => {
+		compile_error!(concat!("internal subsets in `<!", $tag, ">` are not supported, write them as a raw value"));
+	};
+	([$tag:expr] $f:ident concat($($texts:expr,)*)) => {
+		compile_error!(concat!("missing closing `>` after `<!", $tag, " ...`"));
+	};
+	([$tag:expr] $f:ident concat($($texts:expr,)*) $next:tt $($tail:tt)*) => {
+		compile_error!(concat!("expected a name, literal or `{value}` in `<!", $tag, ">`, found `", stringify!($next), "`"));
+	};
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __xfmt_close_decl {
+	($ctx:tt $f:ident concat($($texts:expr,)*) ?> $($tail:tt)*) => {
+		$crate::__xfmt!{$f concat($($texts,)* "?>",) $($tail)*}
+	};
+	([$($tag:expr),*] $f:ident concat($($texts:expr,)*)) => {
+		$crate::__xfmt!{$f concat($($texts,)* "?>",)}
+		compile_error!(concat!("missing closing `?>` after `", $($tag,)* " ...`"));
+	};
+	([$($tag:expr),*] $f:ident concat($($texts:expr,)*) $next:tt $($tail:tt)*) => {
+		compile_error!(concat!("missing closing `?>` after `", $($tag,)* " ...`, found `", stringify!($next), "`"));
+	};
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __xfmt_attrs {
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) @ident($key:expr) = ? {$($e:tt)*} $($tail:tt)*) => {
+		$crate::__xfmt_lint!{"attribute" $key}
+		$crate::__write_str!{$f concat($($texts,)*)}
+		$crate::__xfmt_attr_opt!{$term! $ctx $f [" ", $key, "=\"",] [] $($e)*}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat() $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) @ident($key:expr) ? = {$($e:tt)*} $($tail:tt)*) => {
+		$crate::__xfmt_lint!{"attribute" $key}
+		$crate::__write_str!{$f concat($($texts,)*)}
+		if $($e)* {
+			$crate::__write_str!{$f concat(" ", $key,)}
+		}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat() $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) @ident($key:expr) = $($tail:tt)*) => {
+		$crate::__xfmt_lint!{"attribute" $key}
+		$crate::__xfmt_attrvalue!{$term! $ctx $f concat($($texts,)* " ", $key, "=",) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) @ident($key:expr) $($tail:tt)*) => {
+		$crate::__xfmt_lint!{"attribute" $key}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat($($texts,)* " ", $key,) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) for - $($tail:tt)*) => {
+		$crate::__xfmt_ident!{__xfmt_attrs! [$term! $ctx $f concat($($texts,)*) ] for - $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) for $p:pat in ($e:expr) { $($body:tt)* } $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		for $p in $e {
+			$crate::__xfmt_attrs!{__xattr_end! [] $f concat() $($body)*}
+		}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat() $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) for $p:pat in $($tail:tt)*) => {
+		$crate::__with_parens!{__xfmt_attrs! [$term! $ctx $f concat($($texts,)*) for $p in] [] $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) $key:ident $($tail:tt)*) => {
+		$crate::__xfmt_ident!{__xfmt_attrs! [$term! $ctx $f concat($($texts,)*) ] $key $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) {$($key:tt)*} = $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* " ",)}
+		$crate::__xfmt_name!{$f $($key)*}
+		$crate::__xfmt_attrvalue!{$term! $ctx $f concat("=",) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) @ident_fmt([$($name:expr,)*] {$($key:tt)*}) = $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* " ", $($name,)*)}
+		::core::fmt::Write::write_fmt(&mut $crate::EscapeAttrName::suffix(&mut *$f), ::core::format_args!("{}", $($key)*))?;
+		$crate::__xfmt_attrvalue!{$term! $ctx $f concat("=",) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) {$key:ident} $($tail:tt)*) => {
+		$crate::__xfmt_lint!{"attribute" stringify!($key)}
+		$crate::__xfmt_attrvalue!{$term! $ctx $f concat($($texts,)* " ", stringify!($key), "=",) {$key} $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) {$($e:tt)*} $($tail:tt)*) => {
+		compile_error!(concat!("attribute shorthand requires a single identifier, use `name={", stringify!($($e)*), "}` instead"));
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) $key:literal ? = {$($e:tt)*} $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		if $($e)* {
+			$crate::__write_str!{$f concat(" ", $key,)}
+		}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat() $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) $key:literal = ? {$($e:tt)*} $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		$crate::__xfmt_attr_opt!{$term! $ctx $f [" ", $key, "=\"",] [] $($e)*}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat() $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) $key:literal = $($tail:tt)*) => {
+		$crate::__xfmt_attrvalue!{$term! $ctx $f concat($($texts,)* " ", $key, "=",) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) |$ff:pat_param| $block:block $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		{
+			let $ff = &mut *$f;
+			$block
+		}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat() $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) |$ff:pat_param| $stmt:stmt; $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		{
+			let $ff = &mut *$f;
+			$stmt
+		}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat() $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) $($tail:tt)*) => {
+		$crate::$term!{$ctx $f concat($($texts,)*) $($tail)*}
+	};
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __xfmt_attrvalue {
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) ~{$e:expr} $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		{
+			let mut _w = $crate::EscapeAttrUnquoted::new(&mut *$f);
+			::core::fmt::Write::write_fmt(&mut _w, ::core::format_args!("{}", $e))?;
+			if _w.is_empty() {
+				$f.write_str("\"\"")?;
+			}
+		}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat() $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) obf($text:literal) $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		$f.write_str($crate::__obf!($text))?;
+		$crate::__xfmt_attrs!{$term! $ctx $f concat("\"",) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) $text:literal $($tail:tt)*) => {
+		$crate::__xfmt_attrlit!{$term! $ctx $f concat($($texts,)* "\"", $text,) $($tail)*}
+	};
+	(__xfmt_close_decl! $ctx:tt $f:ident concat($($texts:expr,)*) ($($body:tt)*) $($tail:tt)*) => {
+		$crate::__xfmt_attrtext!{$f __xfmt_pi concat($($texts,)* "\"",) $($body)*}
+		$crate::__xfmt_attrs!{__xfmt_close_decl! $ctx $f concat("\"",) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) ($($body:tt)*) $($tail:tt)*) => {
+		$crate::__xfmt_attrtext!{$f __xfmt_attr concat($($texts,)* "\"",) $($body)*}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat("\"",) $($tail)*}
+	};
+	(__xfmt_close_decl! $ctx:tt $f:ident concat($($texts:expr,)*) [$(($name:literal, $value:expr)),* $(,)?] $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		$crate::__xfmt_style!{$f __xfmt_pi $(($name, $value))*}
+		$crate::__xfmt_attrs!{__xfmt_close_decl! $ctx $f concat("\"",) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) [$(($name:literal, $value:expr)),* $(,)?] $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		$crate::__xfmt_style!{$f __xfmt_attr $(($name, $value))*}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat("\"",) $($tail)*}
+	};
+	(__xfmt_close_decl! $ctx:tt $f:ident concat($($texts:expr,)*) [$($list:tt)*] $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		{
+			#[allow(unused_mut)]
+			let mut _sep = false;
+			$crate::__xfmt_list!{$f __xfmt_pi _sep $($list)*}
+		}
+		$crate::__xfmt_attrs!{__xfmt_close_decl! $ctx $f concat("\"",) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) [$($list:tt)*] $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		{
+			#[allow(unused_mut)]
+			let mut _sep = false;
+			$crate::__xfmt_list!{$f __xfmt_attr _sep $($list)*}
+		}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat("\"",) $($tail)*}
+	};
+	(__xfmt_close_decl! $ctx:tt $f:ident concat($($texts:expr,)*) match ($e:expr) { $($body:tt)* } $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		$crate::__xfmt_match!{[__xfmt_attrtext! $f __xfmt_pi concat()] match ($e) {} $($body)*}
+		$crate::__xfmt_attrs!{__xfmt_close_decl! $ctx $f concat("\"",) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) match ($e:expr) { $($body:tt)* } $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		$crate::__xfmt_match!{[__xfmt_attrtext! $f __xfmt_attr concat()] match ($e) {} $($body)*}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat("\"",) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) match $($tail:tt)*) => {
+		$crate::__with_parens!{__xfmt_attrvalue! [$term! $ctx $f concat($($texts,)*) match] [] $($tail)*}
+	};
+	(__xfmt_close_decl! $ctx:tt $f:ident concat($($texts:expr,)*) if $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		$crate::__xfmt_if!{[__xfmt_attrtext! $f __xfmt_pi concat()] [__xfmt_attrs! __xfmt_close_decl! $ctx $f concat("\"",)] [] if $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) if $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		$crate::__xfmt_if!{[__xfmt_attrtext! $f __xfmt_attr concat()] [__xfmt_attrs! $term! $ctx $f concat("\"",)] [] if $($tail)*}
+	};
+	(__xfmt_close_decl! $ctx:tt $f:ident concat($($texts:expr,)*) {$($e:tt)*} $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		$crate::__xfmt_format!{$f __xfmt_pi [] $($e)*}
+		$crate::__xfmt_attrs!{__xfmt_close_decl! $ctx $f concat("\"",) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) {$($e:tt)*} $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		$crate::__xfmt_format!{$f __xfmt_attr [] $($e)*}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat("\"",) $($tail)*}
+	};
+	(__xfmt_close_decl! $ctx:tt $f:ident concat($($texts:expr,)*) |$ff:ident: attr| $block:block $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		$crate::__xfmt_attrtext!{$f __xfmt_pi concat() |$ff: attr| $block}
+		$crate::__xfmt_attrs!{__xfmt_close_decl! $ctx $f concat("\"",) $($tail)*}
+	};
+	(__xfmt_close_decl! $ctx:tt $f:ident concat($($texts:expr,)*) |$ff:ident: attr| $stmt:stmt; $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		$crate::__xfmt_attrtext!{$f __xfmt_pi concat() |$ff: attr| { $stmt }}
+		$crate::__xfmt_attrs!{__xfmt_close_decl! $ctx $f concat("\"",) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) |$ff:ident: attr| $block:block $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		$crate::__xfmt_attrtext!{$f __xfmt_attr concat() |$ff: attr| $block}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat("\"",) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) |$ff:ident: attr| $stmt:stmt; $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		$crate::__xfmt_attrtext!{$f __xfmt_attr concat() |$ff: attr| { $stmt }}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat("\"",) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) |$ff:pat_param| $block:block $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		{
+			let $ff = &mut *$f;
+			$block
+		}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat("\"",) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) |$ff:pat_param| $stmt:stmt; $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)* "\"",)}
+		{
+			let $ff = &mut *$f;
+			$stmt
+		}
+		$crate::__xfmt_attrs!{$term! $ctx $f concat("\"",) $($tail)*}
 	};
 }
 
-
-
+// Concatenates adjacent literals in an attribute value, a literal followed by `=` or `?` is the name of the next attribute
 #[macro_export]
 #[doc(hidden)]
-macro_rules! __xfmt_close_tag {
-	($f:ident concat($($texts:expr,)*) > $($tail:tt)*) => {
-		$crate::__xfmt!{$f concat($($texts,)* ">",) $($tail)*}
+macro_rules! __xfmt_attrlit {
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) $key:literal = $($tail:tt)*) => {
+		$crate::__xfmt_attrs!{$term! $ctx $f concat($($texts,)* "\"",) $key = $($tail)*}
 	};
-	($f:ident concat($($texts:expr,)*) /> $($tail:tt)*) => {
-		$crate::__xfmt!{$f concat($($texts,)* " />",) $($tail)*}
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) $key:literal ? $($tail:tt)*) => {
+		$crate::__xfmt_attrs!{$term! $ctx $f concat($($texts,)* "\"",) $key ? $($tail)*}
 	};
-	($f:ident concat($($texts:expr,)*)) => {
-		$crate::__xfmt!{$f concat($($texts,)* ">",)}
-		compile_error!("missing closing `>`");
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) $text:literal $($tail:tt)*) => {
+		$crate::__xfmt_attrlit!{$term! $ctx $f concat($($texts,)* $text,) $($tail)*}
+	};
+	($term:ident! $ctx:tt $f:ident concat($($texts:expr,)*) $($tail:tt)*) => {
+		$crate::__xfmt_attrs!{$term! $ctx $f concat($($texts,)* "\"",) $($tail)*}
 	};
 }
 
+// Writes an element or attribute name formatted from the value, an empty name is written as `_`
 #[macro_export]
 #[doc(hidden)]
-macro_rules! __xfmt_close_decl {
-	($f:ident concat($($texts:expr,)*) ?> $($tail:tt)*) => {
-		$crate::__xfmt!{$f concat($($texts,)* "?>",) $($tail)*}
+macro_rules! __xfmt_name {
+	($f:ident $($e:tt)*) => {
+		{
+			let mut _w = $crate::EscapeAttrName::new(&mut *$f);
+			::core::fmt::Write::write_fmt(&mut _w, ::core::format_args!("{}", $($e)*))?;
+			if _w.is_empty() {
+				$f.write_str("_")?;
+			}
+		}
 	};
-	($f:ident concat($($texts:expr,)*)) => {
-		$crate::__xfmt!{$f concat($($texts,)* "?>",)}
-		compile_error!("missing closing `?>`");
+}
+
+// Writes the declarations of a style map separated by spaces, `None` values are skipped
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __xfmt_style {
+	($f:ident $escape:ident $(($name:literal, $value:expr))*) => {
+		{
+			#[allow(unused_imports)]
+			use $crate::{OptionKind as _, SomeKind as _};
+			#[allow(unused_mut)]
+			let mut _sep = false;
+			$(
+				if let ::core::option::Option::Some(_v) = (&$crate::EscapeSpec(&$value)).__xfmt_some() {
+					if _sep {
+						$f.write_str(" ")?;
+					}
+					_sep = true;
+					$f.write_str($crate::obfstr!(concat!($name, ": ")))?;
+					$crate::XfmtWrite::$escape($f, ::core::format_args!("{}", _v))?;
+					$f.write_str(";")?;
+				}
+			)*
+		}
 	};
 }
 
+// Writes the entries of a list attribute value separated by spaces, an entry followed by `: condition` is written only if the condition is true
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __xfmt_list {
+	($f:ident $escape:ident $sep:ident [$($entry:tt)*] : {$($c:tt)*} $(, $($tail:tt)*)?) => {
+		if $($c)* {
+			$crate::__xfmt_list!{@entry $f $escape $sep $($entry)*}
+		}
+		$crate::__xfmt_list!{$f $escape $sep $($($tail)*)?}
+	};
+	($f:ident $escape:ident $sep:ident $text:literal : $($tail:tt)*) => {
+		$crate::__until_comma!{__xfmt_list! [$f $escape $sep [$text] :] {} $($tail)*}
+	};
+	($f:ident $escape:ident $sep:ident {$($e:tt)*} : $($tail:tt)*) => {
+		$crate::__until_comma!{__xfmt_list! [$f $escape $sep [{$($e)*}] :] {} $($tail)*}
+	};
+	($f:ident $escape:ident $sep:ident $text:literal $(, $($tail:tt)*)?) => {
+		$crate::__xfmt_list!{@entry $f $escape $sep $text}
+		$crate::__xfmt_list!{$f $escape $sep $($($tail)*)?}
+	};
+	($f:ident $escape:ident $sep:ident {$($e:tt)*} $(, $($tail:tt)*)?) => {
+		$crate::__xfmt_list!{@entry $f $escape $sep {$($e)*}}
+		$crate::__xfmt_list!{$f $escape $sep $($($tail)*)?}
+	};
+	($f:ident $escape:ident $sep:ident) => {};
+	($f:ident $escape:ident $sep:ident $($tail:tt)+) => {
+		compile_error!(concat!("expected a literal or `{value}` list entry, found: ", stringify!($($tail)+)));
+	};
 
+	(@entry $f:ident $escape:ident $sep:ident $text:literal) => {
+		if $sep {
+			$f.write_str(" ")?;
+		}
+		$sep = true;
+		$f.write_str($crate::obfstr!($text))?;
+	};
+	(@entry $f:ident $escape:ident $sep:ident {$($e:tt)*}) => {
+		if $sep {
+			$f.write_str(" ")?;
+		}
+		$sep = true;
+		$crate::__xfmt_format!{$f $escape [] $($e)*}
+	};
+}
 
+// Writes the attribute if the Option value is Some, the formatting specifiers apply to the value
 #[macro_export]
 #[doc(hidden)]
-macro_rules! __xfmt_attrs {
-	($term:ident! $f:ident concat($($texts:expr,)*) @ident($key:expr) = $($tail:tt)*) => {
-		$crate::__xfmt_attrvalue!{$term! $f concat($($texts,)* " ", $key, "=",) $($tail)*}
+macro_rules! __xfmt_attr_opt {
+	($term:ident! $ctx:tt $f:ident [$($name:expr,)*] [$($e:tt)*] : $($s:tt)*) => {
+		$crate::__xfmt_attr_opt!{@write $term! $ctx $f [$($name,)*] [$($e)*] [: $($s)*]}
 	};
-	($term:ident! $f:ident concat($($texts:expr,)*) @ident($key:expr) $($tail:tt)*) => {
-		$crate::__xfmt_attrs!{$term! $f concat($($texts,)* " ", $key,) $($tail)*}
+	($term:ident! $ctx:tt $f:ident [$($name:expr,)*] [$($e:tt)*] ; $($s:tt)*) => {
+		$crate::__xfmt_attr_opt!{@write $term! $ctx $f [$($name,)*] [$($e)*] [: $($s)*]}
 	};
-	($term:ident! $f:ident concat($($texts:expr,)*) $key:ident $($tail:tt)*) => {
-		$crate::__xfmt_ident!{__xfmt_attrs! [$term! $f concat($($texts,)*) ] $key $($tail)*}
+	($term:ident! $ctx:tt $f:ident [$($name:expr,)*] [$($e:tt)*] $nom:tt $($tail:tt)*) => {
+		$crate::__xfmt_attr_opt!{$term! $ctx $f [$($name,)*] [$($e)* $nom] $($tail)*}
 	};
-	($term:ident! $f:ident concat($($texts:expr,)*) $key:literal = $($tail:tt)*) => {
-		$crate::__xfmt_attrvalue!{$term! $f concat($($texts,)* " ", $key, "=",) $($tail)*}
+	($term:ident! $ctx:tt $f:ident [$($name:expr,)*] [$($e:tt)*]) => {
+		$crate::__xfmt_attr_opt!{@write $term! $ctx $f [$($name,)*] [$($e)*] []}
 	};
-	($term:ident! $f:ident concat($($texts:expr,)*) $($tail:tt)*) => {
-		$crate::$term!{$f concat($($texts,)*) $($tail)*}
+	(@write __xfmt_close_decl! $ctx:tt $f:ident [$($name:expr,)*] [$e:expr] [$($s:tt)*]) => {
+		if let ::core::option::Option::Some(_v) = &$e {
+			$crate::__write_str!{$f concat($($name,)*)}
+			$crate::__xfmt_format!{$f __xfmt_pi [_v] $($s)*}
+			$f.write_str("\"")?;
+		}
+	};
+	(@write $term:ident! $ctx:tt $f:ident [$($name:expr,)*] [$e:expr] [$($s:tt)*]) => {
+		if let ::core::option::Option::Some(_v) = &$e {
+			$crate::__write_str!{$f concat($($name,)*)}
+			$crate::__xfmt_format!{$f __xfmt_attr [_v] $($s)*}
+			$f.write_str("\"")?;
+		}
 	};
 }
 
+// The contents of an attribute value inside control flow, the quotes are written by the caller
 #[macro_export]
 #[doc(hidden)]
-macro_rules! __xfmt_attrvalue {
-	($term:ident! $f:ident concat($($texts:expr,)*) $text:literal $($tail:tt)*) => {
-		$crate::__xfmt_attrs!{$term! $f concat($($texts,)* "\"", $text, "\"",) $($tail)*}
+macro_rules! __xfmt_attrtext {
+	($f:ident $escape:ident concat($($texts:expr,)*) obf($text:literal) $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		$f.write_str($crate::__obf!($text))?;
+		$crate::__xfmt_attrtext!{$f $escape concat() $($tail)*}
 	};
-	($term:ident! $f:ident concat($($texts:expr,)*) {$($e:tt)*} $($tail:tt)*) => {
-		$crate::__write_str!{$f concat($($texts,)* "\"",)}
-		::core::fmt::write($crate::EscapeAttrValue::wrap($f), $crate::__xfmt_format!([] $($e)*))?;
-		$crate::__xfmt_attrs!{$term! $f concat("\"",) $($tail)*}
+	($f:ident $escape:ident concat($($texts:expr,)*) $text:literal $($tail:tt)*) => {
+		$crate::__xfmt_attrtext!{$f $escape concat($($texts,)* $text,) $($tail)*}
 	};
-	($term:ident! $f:ident concat($($texts:expr,)*) |$ff:pat_param| $block:block $($tail:tt)*) => {
-		$crate::__write_str!{$f concat($($texts,)* "\"",)}
-		let $ff = &mut *$f;
-		$block
-		$crate::__xfmt_attrs!{$term! $f concat("\"",) $($tail)*}
+	($f:ident $escape:ident concat($($texts:expr,)*) {$($e:tt)*} $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		$crate::__xfmt_format!{$f $escape [] $($e)*}
+		$crate::__xfmt_attrtext!{$f $escape concat() $($tail)*}
 	};
-	($term:ident! $f:ident concat($($texts:expr,)*) |$ff:pat_param| $stmt:stmt; $($tail:tt)*) => {
-		$crate::__write_str!{$f concat($($texts,)* "\"",)}
-		let $ff = &mut *$f;
-		$stmt
-		$crate::__xfmt_attrs!{$term! $f concat("\"",) $($tail)*}
+	($f:ident $escape:ident concat($($texts:expr,)*) if $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		$crate::__xfmt_if!{[__xfmt_attrtext! $f $escape concat()] [__xfmt_attrtext! $f $escape concat()] [] if $($tail)*}
+	};
+	($f:ident $escape:ident concat($($texts:expr,)*) match ($e:expr) { $($body:tt)* } $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		$crate::__xfmt_match!{[__xfmt_attrtext! $f $escape concat()] match ($e) {} $($body)*}
+		$crate::__xfmt_attrtext!{$f $escape concat() $($tail)*}
+	};
+	($f:ident $escape:ident concat($($texts:expr,)*) match $($tail:tt)*) => {
+		$crate::__with_parens!{__xfmt_attrtext! [$f $escape concat($($texts,)*) match] [] $($tail)*}
+	};
+	($f:ident __xfmt_pi concat($($texts:expr,)*) |$ff:ident: attr| $block:block $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		{
+			let mut _pi = $crate::EscapePi::new(&mut *$f);
+			let $ff = $crate::EscapeAttrValue::wrap(&mut _pi);
+			$block
+		}
+		$crate::__xfmt_attrtext!{$f __xfmt_pi concat() $($tail)*}
+	};
+	($f:ident __xfmt_pi concat($($texts:expr,)*) |$ff:ident: attr| $stmt:stmt; $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		{
+			let mut _pi = $crate::EscapePi::new(&mut *$f);
+			let $ff = $crate::EscapeAttrValue::wrap(&mut _pi);
+			$stmt
+		}
+		$crate::__xfmt_attrtext!{$f __xfmt_pi concat() $($tail)*}
+	};
+	($f:ident $escape:ident concat($($texts:expr,)*) |$ff:ident: attr| $block:block $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		{
+			let $ff = $crate::EscapeAttrValue::wrap(&mut *$f);
+			$block
+		}
+		$crate::__xfmt_attrtext!{$f $escape concat() $($tail)*}
+	};
+	($f:ident $escape:ident concat($($texts:expr,)*) |$ff:ident: attr| $stmt:stmt; $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		{
+			let $ff = $crate::EscapeAttrValue::wrap(&mut *$f);
+			$stmt
+		}
+		$crate::__xfmt_attrtext!{$f $escape concat() $($tail)*}
+	};
+	($f:ident $escape:ident concat($($texts:expr,)*) |$ff:pat_param| $block:block $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		{
+			let $ff = &mut *$f;
+			$block
+		}
+		$crate::__xfmt_attrtext!{$f $escape concat() $($tail)*}
+	};
+	($f:ident $escape:ident concat($($texts:expr,)*) |$ff:pat_param| $stmt:stmt; $($tail:tt)*) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+		{
+			let $ff = &mut *$f;
+			$stmt
+		}
+		$crate::__xfmt_attrtext!{$f $escape concat() $($tail)*}
+	};
+	($f:ident $escape:ident concat($($texts:expr,)*)) => {
+		$crate::__write_str!{$f concat($($texts,)*)}
+	};
+	($f:ident $escape:ident concat($($texts:expr,)*) $($tail:tt)+) => {
+		compile_error!(concat!("expected a literal, `{value}`, escape hatch or control flow in the attribute value, found: ", stringify!($($tail)+)));
 	};
 }
 
-
 // Parse an xml identifier:
 //
 // * A single (string) literal to bypass any of the other rules
@@ -444,6 +1752,9 @@ macro_rules! __xfmt_ident {
 #[macro_export]
 #[doc(hidden)]
 macro_rules! __xfmt_ident_cont {
+	($next:ident! [$($prefix:tt)*] [$($tt:expr,)*] {$($e:tt)*} $($tail:tt)*) => {
+		$crate::$next!{$($prefix)* @ident_fmt([$($tt,)*] {$($e)*}) $($tail)*}
+	};
 	($next:ident! [$($prefix:tt)*] [$($tt:expr,)*] $frag:ident- $($tail:tt)*) => {
 		$crate::__xfmt_ident_cont!{$next! [$($prefix)*] [$($tt,)* stringify!($frag), "-",] $($tail)*}
 	};
@@ -462,10 +1773,14 @@ macro_rules! __xfmt_ident_cont {
 macro_rules! __xfmt_comment {
 	($f:ident ($($tt:tt)*) --> $($tail:tt)*) => {
 		{
-			let _f = $crate::EscapeComment::wrap($f);
-			$crate::__fmt!{_f $($tt)*}
+			let mut _w = $crate::EscapeComment::wrap(&mut *$f);
+			{
+				let _f = &mut _w;
+				$crate::__xfmt!{_f concat() $($tt)*}
+			}
+			_w.close()?;
 		}
-		$crate::__xfmt!{$f concat(" -->",) $($tail)*}
+		$crate::__xfmt!{$f concat() $($tail)*}
 	};
 	($f:ident ($($tt:tt)*) $nom:tt $($tail:tt)*) => {
 		$crate::__xfmt_comment!{$f ($($tt)* $nom) $($tail)*}
@@ -477,31 +1792,110 @@ macro_rules! __xfmt_comment {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! __xfmt_format {
-	([$($e:tt)*] : $($tail:tt)*) => {
-		$crate::__xfmt_format_expr!([$($e)*] : $($tail)*)
+	($f:ident $escape:ident [$($e:tt)*] : $($tail:tt)*) => {
+		$crate::XfmtWrite::$escape($f, $crate::__xfmt_format_expr!([$($e)*] : $($tail)*))?;
+	};
+	($f:ident $escape:ident [$($e:tt)*] ; $($tail:tt)*) => {
+		$crate::XfmtWrite::$escape($f, $crate::__xfmt_format_expr!([$($e)*] : $($tail)*))?;
 	};
-	([$($e:tt)*] ; $($tail:tt)*) => {
-		$crate::__xfmt_format_expr!([$($e)*] : $($tail)*)
+	($f:ident $escape:ident [$($e:tt)*] ? ? $fallback:literal $($tail:tt)*) => {
+		$crate::__xfmt_format_or!{$f $escape [$($e)*] [$fallback] $($tail)*}
 	};
-	([$($e:tt)*] $nom:tt $($tail:tt)*) => {
-		$crate::__xfmt_format!([$($e)* $nom] $($tail)*)
+	($f:ident $escape:ident [$($e:tt)*] ? ? {$($fallback:tt)*} $($tail:tt)*) => {
+		$crate::__xfmt_format_or!{$f $escape [$($e)*] [$($fallback)*] $($tail)*}
 	};
-	([$($e:tt)*]) => {
-		$crate::__xfmt_format_expr!([$($e)*])
+	($f:ident $escape:ident [$($e:tt)*] $nom:tt $($tail:tt)*) => {
+		$crate::__xfmt_format!{$f $escape [$($e)* $nom] $($tail)*}
+	};
+	($f:ident $escape:ident [$($e:tt)*]) => {
+		$crate::__xfmt_format_value!{$f $escape $($e)*}
 	};
 }
 
+// Strips the closing `%` of a raw value
 #[doc(hidden)]
 #[macro_export]
-macro_rules! __xfmt_format_expr {
-	([$e:expr]) => {
-		::core::format_args!("{}", $e)
+macro_rules! __xfmt_raw {
+	($f:ident [$($e:tt)*] %) => {
+		$crate::__xfmt_format!{$f __xfmt_raw [] $($e)*}
+	};
+	($f:ident [$($e:tt)*] $nom:tt $($tail:tt)*) => {
+		$crate::__xfmt_raw!{$f [$($e)* $nom] $($tail)*}
+	};
+	($f:ident [$($e:tt)*]) => {
+		compile_error!(concat!("expected `%}` after raw value: ", stringify!($($e)*)));
 	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __xfmt_format_expr {
 	([$e:expr $(, $w:expr)?] $($s:tt)*) => {
 		::core::format_args!(concat!("{", $(stringify!($s),)* "}"), $e $(,$w)?)
 	};
 }
 
+// Formats the Option value if Some or the fallback if None
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __xfmt_format_or {
+	($f:ident $escape:ident [$($e:tt)*] [$($fallback:tt)*]) => {
+		$crate::__xfmt_format_or!{$f $escape [$($e)*] [$($fallback)*] :}
+	};
+	($f:ident $escape:ident [$($e:tt)*] [$($fallback:tt)*] ; $($s:tt)*) => {
+		$crate::__xfmt_format_or!{$f $escape [$($e)*] [$($fallback)*] : $($s)*}
+	};
+	($f:ident $escape:ident [$e:expr] [$fallback:expr] : $($s:tt)*) => {
+		match &$e {
+			::core::option::Option::Some(_v) => $crate::XfmtWrite::$escape($f, $crate::__xfmt_format_expr!([_v] : $($s)*))?,
+			::core::option::Option::None => $crate::XfmtWrite::$escape($f, $crate::__xfmt_format_expr!([$fallback] : $($s)*))?,
+		}
+	};
+}
+
+// Values without formatting specifiers skip the escaping writer if their type cannot produce special characters
+// String values are written to the escaping writer directly instead of through the formatting machinery
+// Values with specifiers are always escaped as the fill character may need escaping
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __xfmt_format_value {
+	// Trusted markup is written as is in text elements only
+	($f:ident __xfmt_text $e:expr) => {
+		match &$e {
+			_v => {
+				#[allow(unused_imports)]
+				use $crate::{EscapeKind as _, NoEscapeKind as _, DisplayKind as _, StrKind as _};
+				if (&$crate::EscapeSpec(_v)).__xfmt_escape() {
+					match (&$crate::EscapeSpec(_v)).__xfmt_str() {
+						::core::option::Option::Some(_s) => $crate::XfmtWrite::__xfmt_text($f, _s)?,
+						::core::option::Option::None => $crate::XfmtWrite::__xfmt_text($f, ::core::format_args!("{}", _v))?,
+					}
+				}
+				else {
+					$f.write_fmt(::core::format_args!("{}", _v))?;
+				}
+			}
+		}
+	};
+	($f:ident $escape:ident $e:expr) => {
+		match &$e {
+			_v => {
+				#[allow(unused_imports)]
+				use $crate::{EscapeAttrKind as _, NoEscapeAttrKind as _, DisplayKind as _, StrKind as _};
+				if (&$crate::EscapeSpec(_v)).__xfmt_escape_attr() {
+					match (&$crate::EscapeSpec(_v)).__xfmt_str() {
+						::core::option::Option::Some(_s) => $crate::XfmtWrite::$escape($f, _s)?,
+						::core::option::Option::None => $crate::XfmtWrite::$escape($f, ::core::format_args!("{}", _v))?,
+					}
+				}
+				else {
+					$f.write_fmt(::core::format_args!("{}", _v))?;
+				}
+			}
+		}
+	};
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! __write_str {
@@ -517,50 +1911,66 @@ macro_rules! __write_str {
 #[macro_export]
 macro_rules! __xfmt_if {
 	// if let
-	($f:ident [$($c:tt)*] if let $p:pat = ($e:expr) { $($body:tt)* } $($tail:tt)*) => {
-		$crate::__xfmt_if!{$f [$($c)* if let $p = $e { $crate::__xfmt!{$f concat() $($body)*} }] $($tail)*}
+	([$body:ident! $($args:tt)*] [$($next:tt)*] [$($c:tt)*] if let $p:pat = ($e:expr) { $($then:tt)* } $($tail:tt)*) => {
+		$crate::__xfmt_if!{[$body! $($args)*] [$($next)*] [$($c)* if let $p = $e { $crate::$body!{$($args)* $($then)*} }] $($tail)*}
 	};
-	($f:ident [$($c:tt)*] if let $p:pat = $($tail:tt)*) => {
-		$crate::__with_parens!{__xfmt_if! [$f [$($c)*] if let $p =] [] $($tail)*}
+	([$($body:tt)*] [$($next:tt)*] [$($c:tt)*] if let $p:pat = $($tail:tt)*) => {
+		$crate::__with_parens!{__xfmt_if! [[$($body)*] [$($next)*] [$($c)*] if let $p =] [] $($tail)*}
 	};
 
 	// if
-	($f:ident [$($c:tt)*] if ($e:expr) { $($body:tt)* } $($tail:tt)*) => {
-		$crate::__xfmt_if!{$f [$($c)* if $e { $crate::__xfmt!{$f concat() $($body)*} }] $($tail)*}
+	([$body:ident! $($args:tt)*] [$($next:tt)*] [$($c:tt)*] if ($e:expr) { $($then:tt)* } $($tail:tt)*) => {
+		$crate::__xfmt_if!{[$body! $($args)*] [$($next)*] [$($c)* if $e { $crate::$body!{$($args)* $($then)*} }] $($tail)*}
 	};
-	($f:ident [$($c:tt)*] if $($tail:tt)*) => {
-		$crate::__with_parens!{__xfmt_if! [$f [$($c)*] if] [] $($tail)*}
+	([$($body:tt)*] [$($next:tt)*] [$($c:tt)*] if $($tail:tt)*) => {
+		$crate::__with_parens!{__xfmt_if! [[$($body)*] [$($next)*] [$($c)*] if] [] $($tail)*}
 	};
 
 	// else if let
-	($f:ident [$($c:tt)*] else if let $p:pat = ($e:expr) { $($body:tt)* } $($tail:tt)*) => {
-		$crate::__xfmt_if!{$f [$($c)* else if let $p = $e { $crate::__xfmt!{$f concat() $($body)*} }] $($tail)*}
+	([$body:ident! $($args:tt)*] [$($next:tt)*] [$($c:tt)*] else if let $p:pat = ($e:expr) { $($then:tt)* } $($tail:tt)*) => {
+		$crate::__xfmt_if!{[$body! $($args)*] [$($next)*] [$($c)* else if let $p = $e { $crate::$body!{$($args)* $($then)*} }] $($tail)*}
 	};
-	($f:ident [$($c:tt)*] else if let $p:pat = $($tail:tt)*) => {
-		$crate::__with_parens!{__xfmt_if! [$f [$($c)*] else if let $p =] [] $($tail)*}
+	([$($body:tt)*] [$($next:tt)*] [$($c:tt)*] else if let $p:pat = $($tail:tt)*) => {
+		$crate::__with_parens!{__xfmt_if! [[$($body)*] [$($next)*] [$($c)*] else if let $p =] [] $($tail)*}
 	};
 
 	// else if
-	($f:ident [$($c:tt)*] else if ($e:expr) { $($body:tt)* } $($tail:tt)*) => {
-		$crate::__xfmt_if!{$f [$($c)* else if $e { $crate::__xfmt!{$f concat() $($body)*} }] $($tail)*}
+	([$body:ident! $($args:tt)*] [$($next:tt)*] [$($c:tt)*] else if ($e:expr) { $($then:tt)* } $($tail:tt)*) => {
+		$crate::__xfmt_if!{[$body! $($args)*] [$($next)*] [$($c)* else if $e { $crate::$body!{$($args)* $($then)*} }] $($tail)*}
 	};
-	($f:ident [$($c:tt)*] else if $($tail:tt)*) => {
-		$crate::__with_parens!{__xfmt_if! [$f [$($c)*] else if] [] $($tail)*}
+	([$($body:tt)*] [$($next:tt)*] [$($c:tt)*] else if $($tail:tt)*) => {
+		$crate::__with_parens!{__xfmt_if! [[$($body)*] [$($next)*] [$($c)*] else if] [] $($tail)*}
 	};
 
 	// else
-	($f:ident [$($c:tt)*] else { $($body:tt)* } $($tail:tt)*) => {
+	([$body:ident! $($args:tt)*] [$next:ident! $($next_args:tt)*] [$($c:tt)*] else { $($then:tt)* } $($tail:tt)*) => {
 		$($c)*
 		else {
-			$crate::__xfmt!{$f concat() $($body)*}
+			$crate::$body!{$($args)* $($then)*}
 		}
-		$crate::__xfmt!{$f concat() $($tail)*}
+		$crate::$next!{$($next_args)* $($tail)*}
 	};
 
 	// term
-	($f:ident [$($c:tt)*] $($tail:tt)*) => {
+	([$($body:tt)*] [$next:ident! $($next_args:tt)*] [$($c:tt)*] $($tail:tt)*) => {
 		$($c)*
-		$crate::__xfmt!{$f concat() $($tail)*}
+		$crate::$next!{$($next_args)* $($tail)*}
+	};
+}
+
+
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __xfmt_let_else {
+	([$f:ident concat($($texts:expr,)*) $p:pat] [$($e:tt)*] else { $($els:tt)* }; $($tail:tt)*) => {
+		$crate::__xfmt!{$f concat($($texts,)*) let $p = ($($e)*) else { $($els)* }; $($tail)*}
+	};
+	([$($prefix:tt)*] [$($e:tt)*] $nom:tt $($tail:tt)*) => {
+		$crate::__xfmt_let_else!{[$($prefix)*] [$($e)* $nom] $($tail)*}
+	};
+	([$($prefix:tt)*] [$($e:tt)*]) => {
+		compile_error!(concat!("expected `;` or `else` after: ", stringify!($($e)*)));
 	};
 }
 
@@ -569,18 +1979,18 @@ macro_rules! __xfmt_if {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! __xfmt_match {
-	($f:ident match ($e:expr) {$($arms:tt)*} $p:pat $(if $guard:expr)? => { $($body:tt)* }, $($tail:tt)*) => {
-		$crate::__xfmt_match!{$f match ($e) {$($arms)* $p $(if $guard)? => { $crate::__xfmt!{$f concat() $($body)*} }} $($tail)*}
+	([$body:ident! $($args:tt)*] match ($e:expr) {$($arms:tt)*} $($p:pat)|+ $(if $guard:expr)? => { $($then:tt)* }, $($tail:tt)*) => {
+		$crate::__xfmt_match!{[$body! $($args)*] match ($e) {$($arms)* $($p)|+ $(if $guard)? => { $crate::$body!{$($args)* $($then)*} }} $($tail)*}
 	};
-	($f:ident match ($e:expr) {$($arms:tt)*} $p:pat $(if $guard:expr)? => { $($body:tt)* } $($tail:tt)*) => {
-		$crate::__xfmt_match!{$f match ($e) {$($arms)* $p $(if $guard)? => { $crate::__xfmt!{$f concat() $($body)*} }} $($tail)*}
+	([$body:ident! $($args:tt)*] match ($e:expr) {$($arms:tt)*} $($p:pat)|+ $(if $guard:expr)? => { $($then:tt)* } $($tail:tt)*) => {
+		$crate::__xfmt_match!{[$body! $($args)*] match ($e) {$($arms)* $($p)|+ $(if $guard)? => { $crate::$body!{$($args)* $($then)*} }} $($tail)*}
 	};
-	($f:ident match ($e:expr) {$($arms:tt)*} $p:pat $(if $guard:expr)? => $($tail:tt)*) => {
-		$crate::__until_comma!{__xfmt_match! [$f match ($e) {$($arms)*} $p $(if $guard)? =>] {} $($tail)*}
+	([$($body:tt)*] match ($e:expr) {$($arms:tt)*} $($p:pat)|+ $(if $guard:expr)? => $($tail:tt)*) => {
+		$crate::__until_comma!{__xfmt_match! [[$($body)*] match ($e) {$($arms)*} $($p)|+ $(if $guard)? =>] {} $($tail)*}
 	};
-	($f:ident match ($e:expr) {$($pat:pat $(if $guard:expr)? => $block:block)*}) => {
+	([$($body:tt)*] match ($e:expr) {$($($pat:pat)|+ $(if $guard:expr)? => $block:block)*}) => {
 		match $e {
-			$($pat $(if $guard)? => $block)*
+			$($($pat)|+ $(if $guard)? => $block)*
 		}
 	};
 }
@@ -638,3 +2048,530 @@ fn test_syntax() {
 	let _ = xfmt!{for _ in 0..4 {}};
 	let _ = xfmt!{match true { false => "false", true => "true"}};
 }
+
+#[test]
+fn test_hygiene() {
+	let _f = "outer";
+	let result = xfmt! {
+		<a>{_f}</a>
+		let _f = 1;
+		<b>{_f}</b>
+		|f| { let _f = 2; write!(f, "{}", _f)?; }
+		|_f| _f.write_str("<hatch>")?;
+		|f| f.write_fmt(format_args!("{}", xfmt!{<c>{_f}</c>}))?;
+		for _f in (3..5) { <d>{_f}</d> }
+		<![CDATA[{_f}]]>
+		<!-- {_f} -->
+	};
+	assert_eq!(result.to_string(), "<a>outer</a><b>1</b>2<hatch><c>1</c><d>3</d><d>4</d><![CDATA[1]]><!-- 1 -->");
+}
+
+#[test]
+fn test_format_or() {
+	#[track_caller]
+	fn check(value: Option<&str>, fallback: &str, expected: &str) {
+		assert_eq!(xfmt!{<a b={value ?? "x&y"} c={value ?? {fallback}}>{value ?? "<none>"}{value ?? {fallback}:>6}</a>}.to_string(), expected);
+	}
+	check(Some("v'"), "fb", "<a b=\"v&apos;\" c=\"v&apos;\">v'    v'</a>");
+	check(None, "fb", "<a b=\"x&amp;y\" c=\"fb\">&lt;none&gt;    fb</a>");
+	check(None, "&", "<a b=\"x&amp;y\" c=\"&amp;\">&lt;none&gt;     &amp;</a>");
+
+	let flags = 2;
+	let owned = Some(String::from("owned"));
+	assert_eq!(xfmt!{{(flags | 1)}" "{owned ?? "none"}}.to_string(), "3 owned");
+
+	// Bitwise or is not a fallback
+	let mask = 4;
+	assert_eq!(xfmt!{<a b={flags | 1}>{flags | mask}{flags | 1:#x}</a>}.to_string(), "<a b=\"3\">60x3</a>");
+}
+
+#[test]
+fn test_obf() {
+	let name = "x";
+	assert_eq!(xfmt!{<a b=obf("c&") d={name}>"e" obf("f<") obf("g")</a>}.to_string(), r#"<a b="c&" d="x">ef<g</a>"#);
+
+	// Unmarked literals render unchanged, also next to marked literals and values named obf
+	let obf = "<v>";
+	let unmarked = xfmt!{<a b="c&amp;" d={obf}>"e" "f&lt;" {obf}</a>}.to_string();
+	assert_eq!(unmarked, r#"<a b="c&amp;" d="&lt;v&gt;">ef&lt;&lt;v&gt;</a>"#);
+	assert_eq!(xfmt!{<a b=obf("c&amp;") d={obf}>"e" obf("f&lt;") {obf}</a>}.to_string(), unmarked);
+	assert_eq!(xfmt!{<a b="c&amp;" d={obf}>obf("e") "f&lt;" {obf}</a>}.to_string(), unmarked);
+}
+
+// The output is identical with and without the `obfstr` feature
+#[test]
+fn test_obfstr() {
+	let value = "<'a'> & \"b\"\n-- ]]>";
+	let page = xfmt! {
+		<p title={value}>{value}<!-- {value} --><![CDATA[{value}]]></p>
+	};
+	assert_eq!(page.to_string(), "<p title=\"&lt;&apos;a&apos;&gt; &amp; &quot;b&quot;&#10;-- ]]&gt;\">&lt;'a'&gt; &amp; \"b\"\n-- ]]&gt;<!-- <'a'> & \"b\"\n ]]> --><![CDATA[<'a'> & \"b\"\n-- ]]]]><![CDATA[>]]></p>");
+}
+
+#[test]
+fn test_attr_shorthand() {
+	let id = 42;
+	let class = "a\"b";
+	assert_eq!(xfmt!{<p {id} lang="en" {class}>{id}</p>}.to_string(), r#"<p id="42" lang="en" class="a&quot;b">42</p>"#);
+	assert_eq!(xattr!{{class} hidden {id}}.to_string(), r#" class="a&quot;b" hidden id="42""#);
+}
+
+// String values are written directly, the output matches formatting them through `Display`
+#[test]
+fn test_str_values() {
+	use std::borrow::Cow;
+	let a = "<a & 'b'>";
+	let b = String::from(a);
+	let c: Cow<str> = Cow::Borrowed(a);
+	let d: Box<str> = a.into();
+	let e = &&b;
+	let expected = xfmt!{<p title={format_args!("{}", a)}>{format_args!("{}", a)}</p>}.to_string();
+	assert_eq!(xfmt!{<p title={a}>{a}</p>}.to_string(), expected);
+	assert_eq!(xfmt!{<p title={b}>{b}</p>}.to_string(), expected);
+	assert_eq!(xfmt!{<p title={c}>{c}</p>}.to_string(), expected);
+	assert_eq!(xfmt!{<p title={d}>{d}</p>}.to_string(), expected);
+	assert_eq!(xfmt!{<p title={e}>{e}</p>}.to_string(), expected);
+	assert_eq!(xfmt!{minimal: <p title={a}>{a}</p>}.to_string(), "<p title=\"&lt;a &amp; 'b'>\">&lt;a &amp; 'b'></p>");
+}
+
+#[test]
+fn test_attr_if() {
+	#[derive(PartialEq)]
+	enum State { Open { count: i32 }, Closed }
+	let render = |state: &State, title: Option<&str>| xfmt! {
+		<div
+			class=if (matches!(state, State::Open { .. })) { "open" } else if (*state == State::Closed) { "closed" } else { "unknown" }
+			title=if let Some(title) = (title) { "Title: "{title} }
+			data-count=if let State::Open { count } = (state) { {count} " items" if (*count == 0) { " (empty)" } }
+		/>
+	}.to_string();
+	assert_eq!(render(&State::Open { count: 0 }, Some("<A & B>")), r#"<div class="open" title="Title: &lt;A &amp; B&gt;" data-count="0 items (empty)" />"#);
+	assert_eq!(render(&State::Open { count: 3 }, None), r#"<div class="open" title="" data-count="3 items" />"#);
+	assert_eq!(render(&State::Closed, Some("\"quoted\"")), r#"<div class="closed" title="Title: &quot;quoted&quot;" data-count="" />"#);
+
+	// Processing instruction attribute values are escaped the same as without control flow
+	let value = "?>";
+	assert_eq!(xfmt!{<?target a=if (true) { {value} }?>}.to_string(), xfmt!{<?target a={value}?>}.to_string());
+	assert_eq!(xattr!{ hidden=if (false) { "hidden" } id="x" }.to_string(), r#" hidden="" id="x""#);
+}
+
+#[test]
+fn test_attr_match() {
+	enum State { Ok, Err, Pending(u32) }
+	let render = |state: &State| xfmt! {
+		<div class=match state { State::Ok => "ok", State::Err => "err", State::Pending(n) if *n > 1 => { "pending "{n} } State::Pending(_) => "<pending>" }
+			data-done=match (state) { State::Ok | State::Err => "yes", _ => |f| f.write_str("no")?; } />
+	}.to_string();
+	assert_eq!(render(&State::Ok), r#"<div class="ok" data-done="yes" />"#);
+	assert_eq!(render(&State::Err), r#"<div class="err" data-done="yes" />"#);
+	assert_eq!(render(&State::Pending(3)), r#"<div class="pending 3" data-done="no" />"#);
+	assert_eq!(render(&State::Pending(1)), r#"<div class="<pending>" data-done="no" />"#);
+
+	// Multiple patterns per arm in node position
+	let value = "<b>";
+	let kind = |n: i32| xfmt! { match n { 0 | 1 => { "small" }, _ => { {value} } } }.to_string();
+	assert_eq!(kind(1), "small");
+	assert_eq!(kind(2), "&lt;b&gt;");
+}
+
+#[test]
+fn test_attr_for() {
+	use std::collections::BTreeMap;
+	let mut map = BTreeMap::new();
+	map.insert("data-b", "2");
+	map.insert("data-a", "1");
+	assert_eq!(xfmt!{<div for (k, v) in (&map) { {k}={v} }></div>}.to_string(), r#"<div data-a="1" data-b="2"></div>"#);
+
+	// Keys and values are escaped, other attributes work in the body
+	let mut map = BTreeMap::new();
+	map.insert("a b", "\"quoted\"");
+	map.insert("", "empty");
+	assert_eq!(xattr!{ id="x" for (k, v) in (&map) { {k}={v} hidden } lang="en" }.to_string(), r#" id="x" _="empty" hidden a_b="&quot;quoted&quot;" hidden lang="en""#);
+
+	// An attribute named `for` is still an attribute
+	assert_eq!(xfmt!{<label for="name" for-x="y"></label>}.to_string(), r#"<label for="name" for-x="y"></label>"#);
+	assert_eq!(xfmt!{<p for i in 0..2 { {format_args!("a{}", i)}=if (i == 1) { "one" } } />}.to_string(), r#"<p a0="" a1="one" />"#);
+}
+
+#[test]
+fn test_attr_opt() {
+	let some_str: Option<&str> = Some("a&b");
+	let some_string: Option<String> = Some(String::from("\"x\""));
+	let none_str: Option<&str> = None;
+	let none_string: Option<String> = None;
+	assert_eq!(xfmt!{<p a=?{some_str} b=?{none_str} "c"=?{some_string} d=?{none_string}>"!"</p>}.to_string(), r#"<p a="a&amp;b" c="&quot;x&quot;">!</p>"#);
+
+	// Formatting specifiers apply to the value
+	let width = Some(42);
+	assert_eq!(xfmt!{<p width=?{width:>5} height=?{None::<i32>:>5} />}.to_string(), r#"<p width="   42" />"#);
+
+	// Move capture mode and xattr!
+	let render = |value: Option<String>| xfmt! { move <p title=?{value}></p> }.to_string();
+	assert_eq!(render(Some("<t>".into())), r#"<p title="&lt;t&gt;"></p>"#);
+	assert_eq!(render(None), "<p></p>");
+	assert_eq!(xattr!{ id="x" title=?{none_str} hidden }.to_string(), r#" id="x" hidden"#);
+	assert_eq!(xfmt!{<?target a=?{Some("?>")}?>}.to_string(), xfmt!{<?target a={"?>"}?>}.to_string());
+}
+
+#[test]
+fn test_attr_bool() {
+	struct Form { locked: bool }
+	impl Form {
+		fn locked(&self) -> bool { self.locked }
+	}
+	let render = |checked: bool, form: &Form| xfmt! {
+		<input type="checkbox" checked?={checked} disabled?={form.locked()} "data-x"?={!checked} name="c" />
+	}.to_string();
+	assert_eq!(render(true, &Form { locked: true }), r#"<input type="checkbox" checked disabled name="c" />"#);
+	assert_eq!(render(false, &Form { locked: false }), r#"<input type="checkbox" data-x name="c" />"#);
+	assert_eq!(xattr!{ hidden?={false} }.to_string(), "");
+}
+
+#[test]
+fn test_attr_list() {
+	let render = |a: bool, b: bool, extra: &str| xfmt! {
+		<p class=["a": a, "b": b, {extra}: !extra.is_empty()]></p>
+	}.to_string();
+	assert_eq!(render(false, false, ""), r#"<p class=""></p>"#);
+	assert_eq!(render(false, true, ""), r#"<p class="b"></p>"#);
+	assert_eq!(render(true, true, "x&y"), r#"<p class="a b x&amp;y"></p>"#);
+	assert_eq!(render(false, false, "<z>"), r#"<p class="&lt;z&gt;"></p>"#);
+
+	// Unconditional entries and trailing commas
+	let n = 3;
+	assert_eq!(xattr!{ class=["btn", {n}, "big": n > 2,] id="x" }.to_string(), r#" class="btn 3 big" id="x""#);
+	assert_eq!(xattr!{ class=[] }.to_string(), r#" class="""#);
+}
+
+#[test]
+fn test_attr_style() {
+	struct Style<'a> {
+		color: &'a str,
+		width: u32,
+		background: Option<String>,
+		font: Option<&'a str>,
+	}
+	let render = |style: &Style| xfmt! {
+		<div style=[
+			("color", style.color),
+			("width", format_args!("{}px", style.width)),
+			("background", &style.background),
+			("font-family", style.font),
+		] title="t"></div>
+	}.to_string();
+	let style = Style { color: "red", width: 10, background: None, font: Some("\"Fira\" & co") };
+	assert_eq!(render(&style), r#"<div style="color: red; width: 10px; font-family: &quot;Fira&quot; &amp; co;" title="t"></div>"#);
+	let style = Style { color: "blue", width: 0, background: Some("<url>".into()), font: None };
+	assert_eq!(render(&style), r#"<div style="color: blue; width: 0px; background: &lt;url&gt;;" title="t"></div>"#);
+
+	// Every entry skipped and the literal form
+	let none: Option<i32> = None;
+	assert_eq!(xattr!{ style=[("a", none)] class="x" style="color: red" }.to_string(), r#" style="" class="x" style="color: red""#);
+}
+
+#[test]
+fn test_attr_parens() {
+	let (x, y) = (10, -2.5);
+	let name = "a&b";
+	assert_eq!(xfmt!{<g transform=("translate("{x}","{y}")") data-x=("name:" "=" {name} ",n:"{x:>3}) />}.to_string(), r#"<g transform="translate(10,-2.5)" data-x="name:=a&amp;b,n: 10" />"#);
+	assert_eq!(xattr!{ a=() b=("x" if (x > 0) { "+" }) }.to_string(), r#" a="" b="x+""#);
+}
+
+#[test]
+fn test_attr_literals() {
+	let w = 10;
+	assert_eq!(xfmt!{<p width="10" "px" "DATA-X"="1" "2" "b"?={true} c=("x" "y"{w}"z" "!") d="M0 0" " L1 1">"t"</p>}.to_string(), r#"<p width="10px" DATA-X="12" b c="xy10z!" d="M0 0 L1 1">t</p>"#);
+}
+
+#[test]
+fn test_attr_name_fmt() {
+	let key = "a b=\"c\"/>";
+	let i = 3;
+	assert_eq!(xfmt!{<p {key}={i} data-{key}="x" ns:{i}=("v"{i}) {""}="e" />}.to_string(), r#"<p a_b__c___="3" data-a_b__c___="x" ns:3="v3" _="e" />"#);
+	assert_eq!(xattr!{ data-x-{i}={i * 2} }.to_string(), r#" data-x-3="6""#);
+}
+
+#[test]
+fn test_element_name_fmt() {
+	let heading = |n: u32, text: &str| xfmt! {
+		<section><{format_args!("h{}", n)} id=("h"{n})>{text}</{format_args!("h{}", n)}></section>
+	}.to_string();
+	let html = heading(3, "A & B");
+	assert_eq!(html, r#"<section><h3 id="h3">A &amp; B</h3></section>"#);
+	let doc = roxmltree::Document::parse(&html).unwrap();
+	let h = doc.root_element().first_child().unwrap();
+	assert_eq!(h.tag_name().name(), "h3");
+	assert_eq!(h.text(), Some("A & B"));
+
+	// Invalid names are sanitized
+	let name = "a b>c";
+	let ns = "x";
+	assert_eq!(xfmt!{<{name} {""}="1"/><{""}></{name}><svg:{ns}></svg:{ns}>}.to_string(), r#"<a_b_c _="1" /><_></a_b_c><svg:x></svg:x>"#);
+}
+
+#[test]
+fn test_fragment() {
+	let show = true;
+	assert_eq!(xfmt!{<></>}.to_string(), "");
+	assert_eq!(xfmt!{<>"a"<>{1}</>"b"</><p><></></p>}.to_string(), "a1b<p></p>");
+	assert_eq!(xfmt!{if (show) { <><i>"x"</i><b>"y"</b></> } match show { true => <>"t"</>, false => <>"f"</> }}.to_string(), "<i>x</i><b>y</b>t");
+}
+
+#[test]
+fn test_while() {
+	let items = [1, 2, 3];
+	let page = xfmt! {
+		let mut iter = items.iter();
+		<ul>
+		while let Some(&item) = iter.next() {
+			<li>{item}</li>
+		}
+		</ul>
+	};
+	// Formatting twice starts over with a new iterator
+	assert_eq!(page.to_string(), "<ul><li>1</li><li>2</li><li>3</li></ul>");
+	assert_eq!(page.to_string(), "<ul><li>1</li><li>2</li><li>3</li></ul>");
+
+	// The condition guards against looping forever
+	let page = xfmt! {
+		let counter = std::cell::Cell::new(0);
+		while (counter.get() < 3) {
+			{counter.get()}
+			|_| counter.set(counter.get() + 1);
+		}
+		while counter.get() > 10 && counter.get() < 20 {
+			"never"
+		}
+	};
+	assert_eq!(page.to_string(), "012");
+}
+
+#[test]
+fn test_loop() {
+	let items = [1, 3, 5, 6, 7];
+	let page = xfmt! {
+		let mut iter = items.iter();
+		<ul>
+		loop {
+			let item = match iter.next() { Some(&item) if item % 2 == 1 => item, _ => 0 };
+			if (item == 0) {
+				"<!-- end -->"
+				break;
+			}
+			<li>{item}</li>
+		}
+		</ul>
+	};
+	assert_eq!(page.to_string(), "<ul><li>1</li><li>3</li><li>5</li><!-- end --></ul>");
+}
+
+#[test]
+fn test_break_continue() {
+	let items = [1, 2, 3, 4, 5, 6, 7];
+	assert_eq!(xfmt!{for i in (&items) { let odd = i % 2 == 1; if (!odd) { "." continue; } {i} }}.to_string(), "1.3.5.7");
+	assert_eq!(xfmt!{for (n, i) in (items.iter().enumerate()) { if (n == 3) { "!" break; } {i} }}.to_string(), "123!");
+
+	let page = xfmt! {
+		'rows: for row in 0..4 {
+			<tr>
+			for col in 0..4 {
+				if (col > row) { </tr> continue 'rows; }
+				if (row == 3) { "end" break 'rows; }
+				<td>{row * col}</td>
+			}
+			</tr>
+		}
+	};
+	assert_eq!(page.to_string(), "<tr><td>0</td></tr><tr><td>0</td><td>1</td></tr><tr><td>0</td><td>2</td><td>4</td></tr><tr>end");
+
+	assert_eq!(xfmt!{ let mut i = 0; 'w: while (i < 10) { |_| i += 1; if (i % 3 != 0) { continue 'w; } {i} }}.to_string(), "369");
+}
+
+#[test]
+fn test_for_else() {
+	let empty: Vec<i32> = Vec::new();
+	assert_eq!(xfmt!{for i in (&empty) { <li>{i}</li> } else { <p>"none"</p> }}.to_string(), "<p>none</p>");
+	let items = vec![1, 2];
+	assert_eq!(xfmt!{for i in (&items) { <li>{i}</li> } else { <p>"none"</p> } "."}.to_string(), "<li>1</li><li>2</li>.");
+	assert_eq!(xfmt!{for i in items.iter().filter(|&&i| i > 5) { {i} } else { "filtered" }}.to_string(), "filtered");
+	assert_eq!(xfmt!{for i in (&items) { if (*i == 1) { "first" break; } } else { "none" }}.to_string(), "first");
+	assert_eq!(xfmt!{
+		for row in (&[&[][..], &[1][..]]) {
+			<tr>
+			for i in (row.iter()) { <td>{i}</td> } else { <td>"-"</td> }
+			</tr>
+		}
+		else { "none" }
+	}.to_string(), "<tr><td>-</td></tr><tr><td>1</td></tr>");
+}
+
+#[test]
+fn test_for_sep() {
+	let items = [1, 2, 3];
+	assert_eq!(xfmt!{for i in (&items[..0]) { {i} } sep { ", " }}.to_string(), "");
+	assert_eq!(xfmt!{for i in (&items[..1]) { {i} } sep { ", " }}.to_string(), "1");
+	assert_eq!(xfmt!{for i in (&items) { {i} } sep { <hr/> }}.to_string(), "1<hr />2<hr />3");
+	assert_eq!(xfmt!{for i in (&items) { {i} } sep { <i>{i - 1}</i> }}.to_string(), "1<i>1</i>2<i>2</i>3");
+	assert_eq!(xfmt!{for i in (&items[..0]) { {i} } sep { ", " } else { "none" } "."}.to_string(), "none.");
+	assert_eq!(xfmt!{for i in (&items) { {i} } sep { ", " } else { "none" } "."}.to_string(), "1, 2, 3.");
+	assert_eq!(xfmt!{
+		for row in (&[&items[..], &items[..2]]) {
+			"["
+			for i in (row.iter()) { {i} } sep { "," }
+			"]"
+		}
+		sep { ";" }
+	}.to_string(), "[1,2,3];[1,2]");
+}
+
+#[test]
+fn test_let_else() {
+	let value: Result<i32, &str> = "bad".parse::<i32>().map_err(|_| "bad");
+	assert_eq!(xfmt!{let Ok(v) = value else { <p>"error"</p> }; <p>{v}</p>}.to_string(), "<p>error</p>");
+	let value: Result<i32, &str> = Ok(3);
+	assert_eq!(xfmt!{let Ok(v) = (value) else { <p>"error"</p> }; <p>{v}</p>}.to_string(), "<p>3</p>");
+
+	// Only the remainder of the enclosing block is skipped
+	let items = [Some(1), None, Some(3)];
+	assert_eq!(xfmt!{
+		<ul>
+		for item in (&items) {
+			<li>
+			let &Some(i) = item else { "-" </li> continue; };
+			{i}
+			</li>
+		}
+		</ul>
+	}.to_string(), "<ul><li>1</li><li>-</li><li>3</li></ul>");
+	assert_eq!(xfmt!{
+		if (true) { let Some(i) = items[1] else { "none" }; {i} }
+		"."
+	}.to_string(), "none.");
+}
+
+#[test]
+fn test_let_typed() {
+	// Without the annotation the literal is an i32 and the shift overflows
+	assert_eq!(xfmt!{let big: u64 = 1 << 40; {big}}.to_string(), "1099511627776");
+	let (total, count) = (7, 2);
+	assert_eq!(xfmt!{let avg: f64 = total as f64 / count as f64; {avg:.2}}.to_string(), "3.50");
+	assert_eq!(xfmt!{for i in (0..2) { let (a, b): (u8, char) = (i, 'x'); {a}{b} }}.to_string(), "0x1x");
+	assert_eq!(xfmt!{let mut n: i64 = 2; |_| n *= -3; {n}}.to_string(), "-6");
+}
+
+#[test]
+fn test_raw() {
+	let markup = "<b>&amp;</b>";
+	assert_eq!(xfmt!{<p>{% markup %}{markup}</p>}.to_string(), "<p><b>&amp;</b>&lt;b&gt;&amp;amp;&lt;/b&gt;</p>");
+	assert_eq!(xfmt!{{% markup:>14 %}}.to_string(), "  <b>&amp;</b>");
+	assert_eq!(xfmt!{{% 7 % 4 %}}.to_string(), "3");
+	assert_eq!(xfmt!{{% format_args!("<{}>", 1) %}}.to_string(), "<1>");
+	let none: Option<&str> = None;
+	assert_eq!(xfmt!{{% none ?? "<i>none</i>" %}}.to_string(), "<i>none</i>");
+	assert_eq!(xfmt!{minimal: {% markup %}}.to_string(), markup);
+	assert_eq!(xfmt!{html: {% markup %}}.to_string(), markup);
+}
+
+#[test]
+fn test_escape_hatch_writer() {
+	let script = "<script>alert('x')</script>";
+	assert_eq!(xfmt!{<p>|f: text| f.write_str(script)?;</p>}.to_string(), "<p>&lt;script&gt;alert('x')&lt;/script&gt;</p>");
+	assert_eq!(xfmt!{<p>|f: text| { for c in script.chars().take(8) { write!(f, "{}", c)?; } }|f| f.write_str("<br/>")?;</p>}.to_string(), "<p>&lt;script&gt;<br/></p>");
+	assert_eq!(xfmt!{<p title=|f: attr| f.write_str(script)?;></p>}.to_string(), "<p title=\"&lt;script&gt;alert(&apos;x&apos;)&lt;/script&gt;\"></p>");
+	assert_eq!(xfmt!{<?pi v=("a" |f: attr| f.write_str("?>")?; "b")?>}.to_string(), "<?pi v=\"a?&gt;b\"?>");
+}
+
+#[test]
+fn test_bare_words() {
+	assert_eq!(xfmt!{<p>Hello there, world.</p>}.to_string(), "<p>Hello there, world.</p>");
+	assert_eq!(xfmt!{<p>Wait; what? Yes: this!</p>}.to_string(), "<p>Wait; what? Yes: this!</p>");
+	assert_eq!(xfmt!{Page 2 of 3, "quoted  text" here}.to_string(), "Page 2 of 3, quoted  text here");
+	assert_eq!(xfmt!{"Hello" world}.to_string(), "Helloworld");
+	let name = "<World>";
+	assert_eq!(xfmt!{Hello {name}, bye<b>bold</b>text}.to_string(), "Hello &lt;World&gt;, bye<b>bold</b>text");
+	assert_eq!(xfmt!{{name}"."{name} x}.to_string(), "&lt;World&gt;.&lt;World&gt; x");
+
+	// Keywords end the run
+	let items = [1, 2];
+	assert_eq!(xfmt!{<p>Items: for i in (&items) { Item {i}. } Done</p>}.to_string(), "<p>Items:Item 1.Item 2.Done</p>");
+	assert_eq!(xfmt!{{items[0]}, {items[1]}! ok? {items.len()}: yes; no.}.to_string(), "1, 2! ok? 2: yes; no.");
+	assert_eq!(xfmt!{Yes if (items.len() > 1) { many } else { few }}.to_string(), "Yesmany");
+	assert_eq!(xfmt!{<p>One "if" two</p>}.to_string(), "<p>One if two</p>");
+}
+
+#[test]
+fn test_strict() {
+	let page = xfmt! { strict: html:
+		<!doctype html>
+		<html lang="en">
+			<!-- "comment with <tags>" -->
+			<![CDATA["<cdata>"]]>
+			<?pi x="y"?>
+			<head><meta charset="utf-8"><link rel="stylesheet" href="a.css"></head>
+		</html>
+	};
+	assert_eq!(page.to_string(), "<!doctype html><html lang=\"en\"><!-- comment with <tags> --><![CDATA[<cdata>]]><?pi x=\"y\"?><head><meta charset=\"utf-8\"><link rel=\"stylesheet\" href=\"a.css\"></head></html>");
+
+	let name = "section";
+	let names = xfmt! { strict:
+		<><svg:rect x="0" /><"custom-tag"></"custom-tag"><my-el></my-el></>
+		<{name}></{name}><x-{name}></x-{name}>
+	};
+	assert_eq!(names.to_string(), "<svg:rect x=\"0\" /><custom-tag></custom-tag><my-el></my-el><section></section><x-section></x-section>");
+
+	let items = [Some(1), None];
+	let flow = xfmt! { strict:
+		<body data-x={1 > 0}>
+			for item in (&items) {
+				let Some(i) = item else { <i>"none"</i> };
+				<b>{i}</b>
+			}
+			sep { <hr/> }
+			if (items.len() > 1) { <p>"many"</p> } else { <p>"few"</p> }
+			match items[0] { Some(_) => <em>"some"</em>, None => {} }
+			|f| f.write_str("<raw>")?;
+		</body>
+	};
+	assert_eq!(flow.to_string(), "<body data-x=\"true\"><b>1</b><hr /><i>none</i><p>many</p><em>some</em><raw></body>");
+
+	// The else block closes the tags open in its block
+	let list = xfmt! { strict: <ul> for item in (&items) { <li> let Some(i) = item else { "-"</li> }; {i}</li> } </ul> };
+	assert_eq!(list.to_string(), "<ul><li>1</li><li>-</li></ul>");
+
+	let plain = move || xfmt! { move strict: <p>{items.len()}</p> };
+	assert_eq!(plain().to_string(), "<p>2</p>");
+
+	// Only html knows the void elements
+	let rss = xfmt! { strict: <item><link>"https://example.com/"</link><br /></item> };
+	assert_eq!(rss.to_string(), "<item><link>https://example.com/</link><br /></item>");
+
+	let html = move || xfmt! { move strict: html:
+		<form><label>"Name"<input name="name"></label><hr><img src="a.png" alt=""></form>
+		<math><mi>"x"</mi><mspace width="1em" /></math>
+		<svg-icon></svg-icon><p></p>
+	};
+	assert_eq!(html().to_string(), "<form><label>Name<input name=\"name\"></label><hr><img src=\"a.png\" alt=\"\"></form><math><mi>x</mi><mspace width=\"1em\" /></math><svg-icon></svg-icon><p></p>");
+}
+
+#[test]
+fn test_doctype() {
+	assert_eq!(xfmt! { <!doctype html> }.to_string(), "<!doctype html>");
+	let xhtml = xfmt! {
+		<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd">
+		<html xmlns="http://www.w3.org/1999/xhtml"></html>
+	};
+	assert_eq!(xhtml.to_string(), r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd"><html xmlns="http://www.w3.org/1999/xhtml"></html>"#);
+
+	let svg = xfmt! {
+		<!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd">
+		<svg xmlns="http://www.w3.org/2000/svg" />
+	}.to_string();
+	assert_eq!(svg, r#"<!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd"><svg xmlns="http://www.w3.org/2000/svg" />"#);
+	let doc = roxmltree::Document::parse_with_options(&svg, roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() }).unwrap();
+	assert_eq!(doc.root_element().tag_name().name(), "svg");
+
+	let dtd = "file:///notes/\"note\".dtd";
+	let note = xfmt! { <!DOCTYPE note SYSTEM {dtd}><note /> }.to_string();
+	assert_eq!(note, r#"<!DOCTYPE note SYSTEM "file:///notes/%22note%22.dtd"><note />"#);
+	assert!(roxmltree::Document::parse_with_options(&note, roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() }).is_ok());
+}